@@ -1,7 +1,7 @@
 use custom_elements::{inject_style, CustomElement};
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{window, HtmlElement, Node, Text};
+use web_sys::{window, Document, HtmlElement, Node, Text};
 
 // The boring part: a basic DOM component
 struct MyWebComponent {
@@ -69,7 +69,7 @@ impl CustomElement for MyWebComponent {
         log("disconnected");
     }
 
-    fn adopted_callback(&mut self, _this: &HtmlElement) {
+    fn adopted_callback(&mut self, _this: &HtmlElement, _old: Document, _new: Document) {
         log("adopted");
     }
 }
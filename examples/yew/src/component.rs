@@ -1,26 +1,32 @@
+use serde::Deserialize;
 use yew::prelude::*;
 
 pub enum Msg {
     AddOne,
-    Set(i64),
+}
+
+// `YewElement` parses the element's `value` attribute into this field
+#[derive(Properties, PartialEq, Default, Deserialize)]
+#[serde(default)]
+pub struct Props {
+    pub value: i64,
 }
 
 pub struct Model {
-    // `ComponentLink` is like a reference to a component.
-    // It can be used to send messages to the component
-    link: ComponentLink<Self>,
     value: i64,
 }
 
 impl Component for Model {
     type Message = Msg;
-    type Properties = ();
+    type Properties = Props;
 
-    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        Self { link, value: 0 }
+    fn create(ctx: &Context<Self>) -> Self {
+        Self {
+            value: ctx.props().value,
+        }
     }
 
-    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::AddOne => {
                 self.value += 1;
@@ -28,24 +34,19 @@ impl Component for Model {
                 // re-render for it to appear on the page
                 true
             }
-            Msg::Set(value) => {
-                self.value = value;
-                true
-            }
         }
     }
 
-    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
-        // Should only return "true" if new properties are different to
-        // previously received properties.
-        // This component has no properties so we will always return "false".
-        false
+    fn changed(&mut self, ctx: &Context<Self>, _old_props: &Self::Properties) -> bool {
+        // the `value` attribute was changed, so start counting from the new value
+        self.value = ctx.props().value;
+        true
     }
 
-    fn view(&self) -> Html {
+    fn view(&self, ctx: &Context<Self>) -> Html {
         html! {
             <div>
-                <button onclick=self.link.callback(|_| Msg::AddOne)>{ "+1" }</button>
+                <button onclick={ctx.link().callback(|_| Msg::AddOne)}>{ "+1" }</button>
                 <p>{ self.value }</p>
             </div>
         }
@@ -0,0 +1,154 @@
+// Accessibility checks run on a component's DOM after `inject_children`, in development builds.
+//
+// The checks are lightweight, and catch the most common mistakes rather than everything an audit tool would: images
+// without alternative text, interactive elements without an accessible name, and focusable elements with no role to
+// tell assistive technology what they are. Each problem is logged as a warning once per tag name.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement};
+
+use crate::{host, logging, query_all};
+
+thread_local! {
+    static WARNED: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+// the roles of interactive widgets, which need an accessible name
+const WIDGET_ROLES: &[&str] = &[
+    "button",
+    "checkbox",
+    "combobox",
+    "link",
+    "menuitem",
+    "menuitemcheckbox",
+    "menuitemradio",
+    "option",
+    "radio",
+    "searchbox",
+    "slider",
+    "spinbutton",
+    "switch",
+    "tab",
+    "textbox",
+    "treeitem",
+];
+
+// elements that are interactive, and have an implicit role, without any attributes
+const NATIVE_INTERACTIVE: &[&str] = &["a", "button", "input", "select", "summary", "textarea"];
+
+// Checks the component's DOM, and the element itself, and warns about each problem found.
+pub(crate) fn audit(this: &HtmlElement, tag_name: &str) {
+    let host = host(this);
+    let mut elements: Vec<Element> = query_all(&host, "*");
+    elements.push(host.clone().into());
+    for element in elements {
+        if is_hidden(&element) {
+            continue;
+        }
+        if let Some(problem) = problem(&element) {
+            let message = format!(
+                "<{}> accessibility: {} {}",
+                tag_name,
+                describe(&element),
+                problem
+            );
+            if WARNED.with(|warned| warned.borrow_mut().insert(message.clone())) {
+                logging::warn(&message);
+            }
+        }
+    }
+}
+
+fn problem(element: &Element) -> Option<&'static str> {
+    let tag = element.local_name();
+    let role = element.get_attribute("role");
+    if matches!(role.as_deref(), Some("presentation") | Some("none")) {
+        return None;
+    }
+    if tag == "img" && !element.has_attribute("alt") {
+        return Some("has no alt text; use alt=\"\" if it is decorative");
+    }
+    let is_widget = match role.as_deref() {
+        Some(role) => WIDGET_ROLES.contains(&role),
+        None => is_native_interactive(element, &tag),
+    };
+    if is_widget && !has_name(element, &tag) {
+        return Some("is interactive but has no accessible name");
+    }
+    let focusable = element
+        .get_attribute("tabindex")
+        .and_then(|tabindex| tabindex.parse::<i32>().ok())
+        .map(|tabindex| tabindex >= 0)
+        .unwrap_or(false);
+    if focusable && role.is_none() && !is_native_interactive(element, &tag) {
+        return Some("can be focused but has no role");
+    }
+    None
+}
+
+fn is_native_interactive(element: &Element, tag: &str) -> bool {
+    match tag {
+        "a" => element.has_attribute("href"),
+        "input" => element.get_attribute("type").as_deref() != Some("hidden"),
+        _ => NATIVE_INTERACTIVE.contains(&tag),
+    }
+}
+
+fn is_hidden(element: &Element) -> bool {
+    element
+        .closest("[aria-hidden=true], [hidden]")
+        .ok()
+        .flatten()
+        .is_some()
+}
+
+// Whether the element has an accessible name, from the attributes that name any element, from a label, or from its
+// content.
+fn has_name(element: &Element, tag: &str) -> bool {
+    let non_empty = |name: &str| {
+        element
+            .get_attribute(name)
+            .map(|value| !value.trim().is_empty())
+            .unwrap_or(false)
+    };
+    if non_empty("aria-label") || non_empty("aria-labelledby") || non_empty("title") {
+        return true;
+    }
+    match tag {
+        "input" | "select" | "textarea" => {
+            let labels = js_sys::Reflect::get(element, &JsValue::from_str("labels"))
+                .ok()
+                .and_then(|labels| labels.dyn_into::<web_sys::NodeList>().ok());
+            let input_type = element.get_attribute("type").unwrap_or_default();
+            labels.map(|labels| labels.length() > 0).unwrap_or(false)
+                || (matches!(input_type.as_str(), "button" | "submit" | "reset")
+                    && non_empty("value"))
+                || (input_type == "image" && non_empty("alt"))
+        }
+        _ => {
+            let text = element.text_content().unwrap_or_default();
+            !text.trim().is_empty()
+                || element
+                    .query_selector("img[alt]:not([alt=\"\"])")
+                    .ok()
+                    .flatten()
+                    .is_some()
+        }
+    }
+}
+
+// A short description of the element for a warning, like `<img class="logo">`.
+fn describe(element: &Element) -> String {
+    let mut description = format!("<{}", element.local_name());
+    for attribute in ["id", "class", "role", "src", "href"] {
+        if let Some(value) = element.get_attribute(attribute) {
+            description.push_str(&format!(" {}=\"{}\"", attribute, value));
+        }
+    }
+    description.push('>');
+    description
+}
@@ -0,0 +1,209 @@
+//! A `requestAnimationFrame` loop tied to a custom element's lifecycle, for canvas, WebGL, and other animated components.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Event, HtmlElement, Window};
+
+use crate::scope::Scope;
+use crate::window_of;
+
+type FrameCallback = Closure<dyn FnMut(f64)>;
+type Listener = Closure<dyn FnMut(Event)>;
+
+struct LoopState {
+    callback: RefCell<Box<dyn FnMut(f64)>>,
+    frame: RefCell<Option<FrameCallback>>,
+    visibility_listener: RefCell<Option<Listener>>,
+    // the window of the element's document, set each time it is connected
+    window: RefCell<Option<Window>>,
+    request_id: Cell<Option<i32>>,
+    last_timestamp: Cell<Option<f64>>,
+    running: Cell<bool>,
+    connected: Cell<bool>,
+}
+
+impl LoopState {
+    fn request_frame(&self) {
+        if self.request_id.get().is_some() {
+            return;
+        }
+        if let (Some(frame), Some(window)) = (&*self.frame.borrow(), &*self.window.borrow()) {
+            let id = window
+                .request_animation_frame(frame.as_ref().unchecked_ref())
+                .unwrap_throw();
+            self.request_id.set(Some(id));
+        }
+    }
+
+    fn cancel_frame(&self) {
+        if let (Some(id), Some(window)) = (self.request_id.take(), &*self.window.borrow()) {
+            window.cancel_animation_frame(id).unwrap_throw();
+        }
+        // the time spent paused shouldn't count as one very long frame
+        self.last_timestamp.set(None);
+    }
+
+    // Runs the loop while `host` is connected, pausing it while the document is hidden.
+    fn connect(state: &Rc<Self>, host: &HtmlElement) {
+        if !state.running.get() || state.connected.replace(true) {
+            return;
+        }
+        let window = window_of(host);
+        let document = window.document().unwrap_throw();
+        *state.window.borrow_mut() = Some(window);
+        if let Some(listener) = &*state.visibility_listener.borrow() {
+            document
+                .add_event_listener_with_callback(
+                    "visibilitychange",
+                    listener.as_ref().unchecked_ref(),
+                )
+                .unwrap_throw();
+        }
+        if !document.hidden() {
+            state.request_frame();
+        }
+        Scope::new(host).on_cleanup({
+            let state = state.clone();
+            move || state.disconnect()
+        });
+    }
+
+    // Pauses the loop until the element is connected again.
+    fn disconnect(&self) {
+        if !self.connected.replace(false) {
+            return;
+        }
+        self.cancel_frame();
+        let window = self.window.borrow().clone();
+        if let (Some(listener), Some(window)) = (&*self.visibility_listener.borrow(), window) {
+            window
+                .document()
+                .unwrap_throw()
+                .remove_event_listener_with_callback(
+                    "visibilitychange",
+                    listener.as_ref().unchecked_ref(),
+                )
+                .unwrap_throw();
+        }
+    }
+}
+
+/// A running `requestAnimationFrame` loop, created by [AnimationLoop::start].
+///
+/// The loop is paused while the document is hidden and while the element is disconnected, and resumes when the
+/// document is shown and the element reconnected. It stops for good when [stop](AnimationLoop::stop) is called.
+/// Since it survives being disconnected, it is started once, for example the first time
+/// [connected_callback](crate::CustomElement::connected_callback) runs.
+#[derive(Clone)]
+pub struct AnimationLoop {
+    state: Rc<LoopState>,
+}
+
+impl AnimationLoop {
+    /// Starts calling `callback` once per animation frame, with the number of milliseconds elapsed
+    /// since the previous frame (`0.0` for the first frame, and for the first frame after a pause).
+    ///
+    /// ```rust,ignore
+    /// fn connected_callback(&mut self, this: &HtmlElement) {
+    ///     if self.animation.is_none() {
+    ///         let scene = self.scene.clone();
+    ///         self.animation = Some(AnimationLoop::start(this, move |dt| {
+    ///             scene.borrow_mut().step(dt);
+    ///         }));
+    ///     }
+    /// }
+    /// ```
+    pub fn start(this: &HtmlElement, callback: impl FnMut(f64) + 'static) -> Self {
+        let host = crate::host(this);
+        let state = Rc::new(LoopState {
+            callback: RefCell::new(Box::new(callback)),
+            frame: RefCell::new(None),
+            visibility_listener: RefCell::new(None),
+            window: RefCell::new(None),
+            request_id: Cell::new(None),
+            last_timestamp: Cell::new(None),
+            running: Cell::new(true),
+            connected: Cell::new(false),
+        });
+
+        // the closures only hold the state weakly, so that the loop is dropped along with its element
+        let frame = Closure::wrap(Box::new({
+            let state = Rc::downgrade(&state);
+            move |timestamp: f64| {
+                let state = match state.upgrade() {
+                    Some(state) => state,
+                    None => return,
+                };
+                state.request_id.set(None);
+                if !state.running.get() {
+                    return;
+                }
+                let dt = state
+                    .last_timestamp
+                    .replace(Some(timestamp))
+                    .map(|last| timestamp - last)
+                    .unwrap_or(0.0);
+                (state.callback.borrow_mut())(dt);
+                if state.running.get() && state.connected.get() {
+                    state.request_frame();
+                }
+            }
+        }) as Box<dyn FnMut(f64)>);
+        *state.frame.borrow_mut() = Some(frame);
+
+        let visibility_listener = Closure::wrap(Box::new({
+            let state = Rc::downgrade(&state);
+            move |_: Event| {
+                let state = match state.upgrade() {
+                    Some(state) => state,
+                    None => return,
+                };
+                let hidden = match &*state.window.borrow() {
+                    Some(window) => window.document().unwrap_throw().hidden(),
+                    None => true,
+                };
+                if hidden {
+                    state.cancel_frame();
+                } else {
+                    state.request_frame();
+                }
+            }
+        }) as Box<dyn FnMut(Event)>);
+        *state.visibility_listener.borrow_mut() = Some(visibility_listener);
+
+        // the element holds the state, through the hook that resumes the loop each time it is connected, which is
+        // given the element rather than holding it, since the element would then never be collected
+        let connect_hook = Closure::wrap(Box::new({
+            let state = state.clone();
+            move |host: HtmlElement| LoopState::connect(&state, &host)
+        }) as Box<dyn FnMut(HtmlElement)>);
+        on_connect(&host, &connect_hook.into_js_value());
+
+        if host.is_connected() {
+            LoopState::connect(&state, &host);
+        }
+        Self { state }
+    }
+
+    /// Stops the loop. It cannot be restarted; call [AnimationLoop::start] again instead.
+    pub fn stop(&self) {
+        if !self.state.running.replace(false) {
+            return;
+        }
+        self.state.disconnect();
+    }
+
+    /// Whether the loop is still running (though it may be paused while the document is hidden or the element is
+    /// disconnected).
+    pub fn is_running(&self) -> bool {
+        self.state.running.get()
+    }
+}
+
+#[wasm_bindgen(module = "/src/make_custom_element.js")]
+extern "C" {
+    fn on_connect(el: &HtmlElement, hook: &JsValue);
+}
@@ -0,0 +1,888 @@
+//! Helpers for working with the custom element's attributes.
+//!
+//! HTML [boolean attributes](https://html.spec.whatwg.org/multipage/common-microsyntaxes.html#boolean-attributes)
+//! are `true` when present and `false` when absent, whatever their value: `disabled="false"` means disabled.
+//! Declare such attributes in [observed_bool_attributes](crate::CustomElement::observed_bool_attributes)
+//! to have their changes delivered as a `bool`.
+//!
+//! Components can also declare an [attribute schema](crate::CustomElement::attribute_schema). With the `dev` feature
+//! enabled, the crate warns when an element is given an attribute that is not in its schema, or a value that does not
+//! match the attribute's type, which catches typos like `varient="primary"` that would otherwise silently do nothing.
+//!
+//! Changes to attributes that are written often, like one mirroring a search box, can be
+//! [debounced or throttled](crate::CustomElement::attribute_timing) before they reach
+//! [attribute_changed_callback](crate::CustomElement::attribute_changed_callback).
+//!
+//! Instead of handling each change in `attribute_changed_callback`, components can keep an observed attribute's
+//! parsed value in an [Attribute] cell, which the crate keeps up to date and which notifies its subscribers.
+//!
+//! The host's `class` and `style` attributes change too often to go through `observedAttributes`; components that
+//! need them can [observe them in batches](crate::CustomElement::observe_host_class) instead.
+//!
+//! Components that wrap a native element can [forward](forward_attributes) their attributes to it.
+//!
+//! With the `dev` feature enabled, each element also keeps a journal of its last 64 observed attribute changes, which
+//! `el.__ceDebug()` returns from the browser console, as `{ tag, attributes, changes }`, where each change has the
+//! attribute's `name`, `oldValue`, `newValue`, and the `time` it changed.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+#[cfg(feature = "dev")]
+use std::collections::HashSet;
+use std::rc::{Rc, Weak};
+use std::str::FromStr;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Element, Event, HtmlElement};
+use web_sys::{MutationObserver, MutationObserverInit, MutationRecord};
+
+#[cfg(feature = "dev")]
+use crate::logging;
+use crate::scope::Scope;
+use crate::{host, internals};
+
+/// Adds the boolean attribute (with an empty value) if `value` is `true`, and removes it otherwise.
+pub fn set_bool_attr(this: &HtmlElement, name: &str, value: bool) {
+    let host = host(this);
+    if value {
+        host.set_attribute(name, "").unwrap_throw();
+    } else {
+        host.remove_attribute(name).unwrap_throw();
+    }
+}
+
+/// Whether the boolean attribute is present, whatever its value.
+pub fn has_bool_attr(this: &HtmlElement, name: &str) -> bool {
+    host(this).has_attribute(name)
+}
+
+/// Copies the host's attributes, other than those in `except`, to `target`, and keeps copying them as they change
+/// until the element is disconnected, so usually called in
+/// [connected_callback](crate::CustomElement::connected_callback). Useful for components that wrap a native element,
+/// like an `<input>`, so that attributes like `placeholder` or `aria-label` given to the component reach it:
+///
+/// ```rust,ignore
+/// fn connected_callback(&mut self, this: &HtmlElement) {
+///     attributes::forward_attributes(this, &self.input, &["id", "class", "style"]);
+/// }
+/// ```
+///
+/// An attribute is only written if its value on `target` differs, so that forwarding in both directions, or from
+/// an element to itself, does not loop forever. Removing an attribute from the host removes it from `target`.
+pub fn forward_attributes(this: &HtmlElement, target: &Element, except: &[&str]) {
+    let host = host(this);
+    let except: Rc<[String]> = except.iter().map(|name| name.to_string()).collect();
+    let forward = {
+        let (host, target) = (host.clone(), target.clone());
+        move |name: &str| {
+            if except.iter().any(|except| except == name) {
+                return;
+            }
+            let value = host.get_attribute(name);
+            if target.get_attribute(name) != value {
+                match value {
+                    Some(value) => target.set_attribute(name, &value).unwrap_throw(),
+                    None => target.remove_attribute(name).unwrap_throw(),
+                }
+            }
+        }
+    };
+    for name in host.get_attribute_names().iter() {
+        forward(&name.as_string().unwrap_throw());
+    }
+
+    let observer_callback = Closure::wrap(Box::new(move |records: js_sys::Array| {
+        for record in records.iter() {
+            let record: MutationRecord = record.unchecked_into();
+            if let Some(name) = record.attribute_name() {
+                forward(&name);
+            }
+        }
+    }) as Box<dyn FnMut(js_sys::Array)>);
+    let observer = MutationObserver::new(observer_callback.as_ref().unchecked_ref()).unwrap_throw();
+    let options = MutationObserverInit::new();
+    options.set_attributes(true);
+    observer
+        .observe_with_options(&host, &options)
+        .unwrap_throw();
+    Scope::new(&host).on_cleanup(move || {
+        observer.disconnect();
+        drop(observer_callback);
+    });
+}
+
+/// Classes, inline styles, and attributes that the crate applies to an element's host when it is first connected,
+/// before [connected_callback](crate::CustomElement::connected_callback). See
+/// [host_setup](crate::CustomElement::host_setup).
+///
+/// Values the page set are kept: an attribute or style property the element already has is not changed, and classes
+/// are only ever added.
+///
+/// ```rust,ignore
+/// fn host_setup() -> HostSetup {
+///     HostSetup::new()
+///         .class("card")
+///         .style("display", "block")
+///         .attribute("role", "region")
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HostSetup {
+    pub classes: Vec<String>,
+    pub styles: Vec<(String, String)>,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl HostSetup {
+    /// Nothing to apply.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a class.
+    pub fn class(mut self, class: impl Into<String>) -> Self {
+        self.classes.push(class.into());
+        self
+    }
+
+    /// Sets an inline style property, like `"display"`, unless the page already set it.
+    pub fn style(mut self, property: impl Into<String>, value: impl Into<String>) -> Self {
+        self.styles.push((property.into(), value.into()));
+        self
+    }
+
+    /// Sets an attribute, unless the page already set it.
+    pub fn attribute(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.push((name.into(), value.into()));
+        self
+    }
+
+    /// Whether there is nothing to apply.
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty() && self.styles.is_empty() && self.attributes.is_empty()
+    }
+
+    pub(crate) fn apply(&self, host: &HtmlElement) {
+        for (name, value) in &self.attributes {
+            if !host.has_attribute(name) {
+                host.set_attribute(name, value).unwrap_throw();
+            }
+        }
+        let style = host.style();
+        for (property, value) in &self.styles {
+            if style.get_property_value(property).unwrap_throw().is_empty() {
+                style.set_property(property, value).unwrap_throw();
+            }
+        }
+        for class in &self.classes {
+            host.class_list().add_1(class).unwrap_throw();
+        }
+    }
+}
+
+// Reflects the `disabled` attribute of a [disableable](crate::CustomElement::disableable) element.
+pub(crate) fn apply_disabled(host: &HtmlElement, disabled: bool) {
+    if disabled {
+        host.set_attribute("aria-disabled", "true").unwrap_throw();
+    } else {
+        host.remove_attribute("aria-disabled").unwrap_throw();
+    }
+    internals::set_state(host, "disabled", disabled);
+}
+
+// Stops pointer events on a disableable element, and inside it, from reaching any listener while it is disabled.
+// Capturing listeners on the host run before those on anything inside it, and before its own bubbling listeners.
+pub(crate) fn block_events_when_disabled(host: &HtmlElement) {
+    BLOCK_WHEN_DISABLED.with(|listener| {
+        for event_type in [
+            "click",
+            "dblclick",
+            "auxclick",
+            "pointerdown",
+            "pointerup",
+            "mousedown",
+            "mouseup",
+            "touchstart",
+        ] {
+            host.add_event_listener_with_callback_and_bool(
+                event_type,
+                listener.as_ref().unchecked_ref(),
+                true,
+            )
+            .unwrap_throw();
+        }
+    });
+}
+
+thread_local! {
+    // shared by every disableable element, so that none of them has to keep a listener of its own
+    static BLOCK_WHEN_DISABLED: Closure<dyn FnMut(Event)> = Closure::wrap(Box::new(|event: Event| {
+        let host: HtmlElement = event.current_target().unwrap_throw().unchecked_into();
+        if host.has_attribute("disabled") {
+            event.prevent_default();
+            event.stop_immediate_propagation();
+        }
+    }) as Box<dyn FnMut(Event)>);
+}
+
+/// The values an attribute in a component's [attribute schema](crate::CustomElement::attribute_schema) accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeType {
+    /// Any value.
+    String,
+    /// A value that parses as a number.
+    Number,
+    /// A boolean attribute, which is `true` when present. Its value is ignored, but `"false"` is reported,
+    /// since it almost always means the author expected the attribute to be `false`.
+    Bool,
+    /// One of the given values.
+    Enum(&'static [&'static str]),
+}
+
+impl AttributeType {
+    /// Whether `value` is valid for this type.
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            AttributeType::String => true,
+            AttributeType::Number => value.trim().parse::<f64>().is_ok(),
+            AttributeType::Bool => value != "false",
+            AttributeType::Enum(values) => values.contains(&value),
+        }
+    }
+}
+
+// attributes any element may have, which a schema does not need to list
+#[cfg(feature = "dev")]
+const GLOBAL_ATTRIBUTES: &[&str] = &[
+    "accesskey",
+    "autocapitalize",
+    "autofocus",
+    "class",
+    "contenteditable",
+    "dir",
+    "draggable",
+    "enterkeyhint",
+    "exportparts",
+    "hidden",
+    "id",
+    "inert",
+    "inputmode",
+    "is",
+    "itemid",
+    "itemprop",
+    "itemref",
+    "itemscope",
+    "itemtype",
+    "lang",
+    "nonce",
+    "part",
+    "popover",
+    "role",
+    "slot",
+    "spellcheck",
+    "style",
+    "tabindex",
+    "title",
+    "translate",
+];
+
+// Whether any element may have the attribute. An `on<type>` attribute, or `on-<type>` for the element's declared
+// events, is only global if `is_handler` finds an `on<type>` event handler property.
+#[cfg(feature = "dev")]
+fn is_global(name: &str, is_handler: impl FnOnce(&str) -> bool) -> bool {
+    if GLOBAL_ATTRIBUTES.contains(&name) || name.starts_with("aria-") || name.starts_with("data-") {
+        return true;
+    }
+    match name.strip_prefix("on") {
+        Some(event_type) if !event_type.is_empty() => {
+            let event_type = event_type.strip_prefix('-').unwrap_or(event_type);
+            is_handler(&format!("on{}", event_type))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(feature = "dev")]
+thread_local! {
+    // each problem is reported once per tag, rather than once per element
+    static WARNED: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+#[cfg(feature = "dev")]
+fn check_attribute(
+    host: &HtmlElement,
+    tag_name: &str,
+    schema: &[(String, AttributeType)],
+    name: &str,
+) {
+    let value = match host.get_attribute(name) {
+        Some(value) => value,
+        None => return,
+    };
+    // the element's handler properties include `HTMLElement.prototype`'s, and those for its declared events
+    let is_handler =
+        |property: &str| js_sys::Reflect::has(host, &JsValue::from_str(property)).unwrap_or(false);
+    let message = match schema.iter().find(|(attr, _)| attr == name) {
+        Some((_, ty)) if !ty.accepts(&value) => format!(
+            "<{}> attribute \"{}\" has invalid value \"{}\" (expected {:?})",
+            tag_name, name, value, ty
+        ),
+        None if !is_global(name, is_handler) => {
+            format!("<{}> has unknown attribute \"{}\"", tag_name, name)
+        }
+        _ => return,
+    };
+    let first = WARNED.with(|warned| warned.borrow_mut().insert(message.clone()));
+    if first {
+        logging::warn(&message);
+    }
+}
+
+// how many of an element's attribute changes its journal keeps
+#[cfg(feature = "dev")]
+const JOURNAL_LENGTH: usize = 64;
+
+// The most recent changes to one element's observed attributes, oldest first, which `el.__ceDebug()` returns in
+// development builds, along with the element's current attributes.
+#[cfg(feature = "dev")]
+#[derive(Clone, Default)]
+pub(crate) struct AttributeJournal(Rc<RefCell<std::collections::VecDeque<JsValue>>>);
+
+#[cfg(feature = "dev")]
+impl AttributeJournal {
+    pub(crate) fn record(&self, name: &str, old_value: Option<&str>, new_value: Option<&str>) {
+        let entry = js_sys::Object::new();
+        let set = |key: &str, value: JsValue| {
+            js_sys::Reflect::set(&entry, &JsValue::from_str(key), &value).unwrap_throw();
+        };
+        set("name", JsValue::from_str(name));
+        set(
+            "oldValue",
+            old_value.map(JsValue::from_str).unwrap_or(JsValue::NULL),
+        );
+        set(
+            "newValue",
+            new_value.map(JsValue::from_str).unwrap_or(JsValue::NULL),
+        );
+        set("time", JsValue::from_f64(js_sys::Date::now()));
+        let mut entries = self.0.borrow_mut();
+        if entries.len() == JOURNAL_LENGTH {
+            entries.pop_front();
+        }
+        entries.push_back(entry.into());
+    }
+
+    // `{ tag, attributes, changes }`, with the element's current attributes as an object
+    pub(crate) fn report(&self, host: &HtmlElement, tag_name: &str) -> JsValue {
+        let attributes = js_sys::Object::new();
+        for name in host.get_attribute_names().iter() {
+            let value = host.get_attribute(&name.as_string().unwrap_throw());
+            js_sys::Reflect::set(&attributes, &name, &JsValue::from(value)).unwrap_throw();
+        }
+        let report = js_sys::Object::new();
+        js_sys::Reflect::set(
+            &report,
+            &JsValue::from_str("tag"),
+            &JsValue::from_str(tag_name),
+        )
+        .unwrap_throw();
+        js_sys::Reflect::set(&report, &JsValue::from_str("attributes"), &attributes).unwrap_throw();
+        let changes = self.0.borrow().iter().collect::<js_sys::Array>();
+        js_sys::Reflect::set(&report, &JsValue::from_str("changes"), &changes).unwrap_throw();
+        report.into()
+    }
+}
+
+// Checks the element's attributes against its schema now, and again whenever they change until it is disconnected.
+#[cfg(feature = "dev")]
+pub(crate) fn check_schema(
+    this: &HtmlElement,
+    tag_name: &str,
+    schema: &std::rc::Rc<[(String, AttributeType)]>,
+) {
+    if schema.is_empty() {
+        return;
+    }
+    let host = host(this);
+    for name in host.get_attribute_names().iter() {
+        check_attribute(&host, tag_name, schema, &name.as_string().unwrap_throw());
+    }
+
+    let callback = Closure::wrap(Box::new({
+        let host = host.clone();
+        let tag_name = tag_name.to_string();
+        let schema = schema.clone();
+        move |records: js_sys::Array| {
+            for record in records.iter() {
+                let record: MutationRecord = record.unchecked_into();
+                if let Some(name) = record.attribute_name() {
+                    check_attribute(&host, &tag_name, &schema, &name);
+                }
+            }
+        }
+    }) as Box<dyn FnMut(js_sys::Array)>);
+    let observer = MutationObserver::new(callback.as_ref().unchecked_ref()).unwrap_throw();
+    let options = MutationObserverInit::new();
+    options.set_attributes(true);
+    observer
+        .observe_with_options(&host, &options)
+        .unwrap_throw();
+    Scope::new(&host).on_cleanup(move || {
+        observer.disconnect();
+        drop(callback);
+    });
+}
+
+/// How changes to an attribute are delivered to
+/// [attribute_changed_callback](crate::CustomElement::attribute_changed_callback).
+/// See [attribute_timing](crate::CustomElement::attribute_timing).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeTiming {
+    /// Waits until the attribute has not changed for this many milliseconds, then delivers one change, from the
+    /// value before the first change to the latest value.
+    Debounce(i32),
+    /// Delivers the first change immediately, then at most one change every this many milliseconds, each from the
+    /// value at the previous delivery to the latest value.
+    Throttle(i32),
+}
+
+type Deliver = Box<dyn Fn(&HtmlElement, String, Option<String>, Option<String>)>;
+
+struct PendingChange {
+    element: HtmlElement,
+    // the change to deliver when the timer fires, if there is one
+    change: Option<(Option<String>, Option<String>)>,
+    // cancelled when the change is dropped
+    _timer: Timer,
+}
+
+struct Timer {
+    handle: i32,
+    _callback: Closure<dyn FnMut()>,
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        window()
+            .unwrap_throw()
+            .clear_timeout_with_handle(self.handle);
+    }
+}
+
+// The debounced and throttled attribute changes of one element, waiting for their timers.
+pub(crate) struct DelayedAttributes {
+    timing: Rc<[(String, AttributeTiming)]>,
+    deliver: Deliver,
+    pending: RefCell<HashMap<String, PendingChange>>,
+}
+
+impl DelayedAttributes {
+    pub(crate) fn new(
+        timing: Rc<[(String, AttributeTiming)]>,
+        deliver: impl Fn(&HtmlElement, String, Option<String>, Option<String>) + 'static,
+    ) -> Rc<Self> {
+        Rc::new(Self {
+            timing,
+            deliver: Box::new(deliver),
+            pending: RefCell::new(HashMap::new()),
+        })
+    }
+
+    pub(crate) fn change(
+        self: &Rc<Self>,
+        element: &HtmlElement,
+        name: String,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    ) {
+        let timing = self
+            .timing
+            .iter()
+            .find(|(attr, _)| *attr == name)
+            .map(|(_, timing)| *timing);
+        match timing {
+            None => (self.deliver)(element, name, old_value, new_value),
+            Some(AttributeTiming::Debounce(ms)) => {
+                let mut pending = self.pending.borrow_mut();
+                let old_value = match pending.remove(&name) {
+                    Some(previous) => previous.change.map(|(old, _)| old).unwrap_or(old_value),
+                    None => old_value,
+                };
+                let timer = self.start_timer(&name, ms);
+                pending.insert(
+                    name,
+                    PendingChange {
+                        element: element.clone(),
+                        change: Some((old_value, new_value)),
+                        _timer: timer,
+                    },
+                );
+            }
+            Some(AttributeTiming::Throttle(ms)) => {
+                let cooling_down = match self.pending.borrow_mut().get_mut(&name) {
+                    Some(pending) => {
+                        let old_value = match pending.change.take() {
+                            Some((old, _)) => old,
+                            None => old_value.clone(),
+                        };
+                        pending.change = Some((old_value, new_value.clone()));
+                        true
+                    }
+                    None => false,
+                };
+                if !cooling_down {
+                    let timer = self.start_timer(&name, ms);
+                    self.pending.borrow_mut().insert(
+                        name.clone(),
+                        PendingChange {
+                            element: element.clone(),
+                            change: None,
+                            _timer: timer,
+                        },
+                    );
+                    (self.deliver)(element, name, old_value, new_value);
+                }
+            }
+        }
+    }
+
+    // Delivers every pending change immediately, cancelling their timers.
+    pub(crate) fn flush(&self) {
+        let pending = self.pending.borrow_mut().drain().collect::<Vec<_>>();
+        for (name, pending) in pending {
+            if let Some((old_value, new_value)) = pending.change {
+                (self.deliver)(&pending.element, name, old_value, new_value);
+            }
+        }
+    }
+
+    // Starts a timer that fires the pending change to `name`. Dropping the timer cancels it.
+    fn start_timer(self: &Rc<Self>, name: &str, ms: i32) -> Timer {
+        let this = Rc::downgrade(self);
+        let name = name.to_string();
+        let callback = Closure::once(move || {
+            if let Some(this) = this.upgrade() {
+                this.fire(name);
+            }
+        });
+        let handle = window()
+            .unwrap_throw()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                callback.as_ref().unchecked_ref(),
+                ms,
+            )
+            .unwrap_throw();
+        Timer {
+            handle,
+            _callback: callback,
+        }
+    }
+
+    fn fire(self: &Rc<Self>, name: String) {
+        let pending = match self.pending.borrow_mut().remove(&name) {
+            Some(pending) => pending,
+            None => return,
+        };
+        if let Some((old_value, new_value)) = pending.change {
+            // a throttled attribute that changed during the wait starts another one
+            if let Some(AttributeTiming::Throttle(ms)) = self
+                .timing
+                .iter()
+                .find(|(attr, _)| *attr == name)
+                .map(|(_, timing)| *timing)
+            {
+                let timer = self.start_timer(&name, ms);
+                self.pending.borrow_mut().insert(
+                    name.clone(),
+                    PendingChange {
+                        element: pending.element.clone(),
+                        change: None,
+                        _timer: timer,
+                    },
+                );
+            }
+            (self.deliver)(&pending.element, name, old_value, new_value);
+        }
+    }
+}
+
+// Watches the host's `class` and/or `style` attributes while it is connected, calling `callback` with the name and
+// value of each one that changed, at most once per attribute for each batch of mutations. The current values are
+// delivered immediately.
+pub(crate) fn observe_host(
+    this: &HtmlElement,
+    names: &[&str],
+    callback: impl Fn(&HtmlElement, String, String) + 'static,
+) {
+    let host = host(this);
+    for name in names {
+        callback(
+            &host,
+            name.to_string(),
+            host.get_attribute(name).unwrap_or_default(),
+        );
+    }
+
+    let observer_callback = Closure::wrap(Box::new({
+        let host = host.clone();
+        move |records: js_sys::Array| {
+            let mut changed: Vec<String> = Vec::new();
+            for record in records.iter() {
+                let record: MutationRecord = record.unchecked_into();
+                if let Some(name) = record.attribute_name() {
+                    if !changed.contains(&name) {
+                        changed.push(name);
+                    }
+                }
+            }
+            for name in changed {
+                let value = host.get_attribute(&name).unwrap_or_default();
+                callback(&host, name, value);
+            }
+        }
+    }) as Box<dyn FnMut(js_sys::Array)>);
+    let observer = MutationObserver::new(observer_callback.as_ref().unchecked_ref()).unwrap_throw();
+    let options = MutationObserverInit::new();
+    options.set_attributes(true);
+    options.set_attribute_filter(
+        &names
+            .iter()
+            .map(|name| JsValue::from_str(name))
+            .collect::<js_sys::Array>(),
+    );
+    observer
+        .observe_with_options(&host, &options)
+        .unwrap_throw();
+    Scope::new(&host).on_cleanup(move || {
+        observer.disconnect();
+        drop(observer_callback);
+    });
+}
+
+type Parse<T> = Box<dyn Fn(Option<&str>) -> T>;
+type Subscriber<T> = Rc<dyn Fn(&T)>;
+
+struct AttributeState<T> {
+    name: String,
+    raw: RefCell<Option<String>>,
+    value: RefCell<T>,
+    parse: Parse<T>,
+    subscribers: RefCell<Vec<Subscriber<T>>>,
+    // the cells bound to the attribute on the element, and this cell's update function among them
+    cells: js_sys::Array,
+    update: JsValue,
+}
+
+impl<T> AttributeState<T> {
+    fn update(&self, raw: Option<&str>) {
+        if self.raw.borrow().as_deref() == raw {
+            return;
+        }
+        *self.raw.borrow_mut() = raw.map(String::from);
+        *self.value.borrow_mut() = (self.parse)(raw);
+        // cloned, so that subscribers can subscribe others or read the attribute
+        let subscribers = self.subscribers.borrow().clone();
+        let value = self.value.borrow();
+        for subscriber in subscribers {
+            subscriber(&value);
+        }
+    }
+}
+
+impl<T> Drop for AttributeState<T> {
+    // removes the cell's update function from the element, so that cells created on each connect do not pile up
+    fn drop(&mut self) {
+        let index = self.cells.index_of(&self.update, 0);
+        if index >= 0 {
+            let length = self.cells.length();
+            self.cells.copy_within(index, index + 1, length as i32);
+            self.cells.set_length(length - 1);
+        }
+    }
+}
+
+/// An observable cell holding the parsed value of one of the element's attributes, which the crate updates whenever
+/// the attribute changes, before [attribute_changed_callback](crate::CustomElement::attribute_changed_callback) is
+/// invoked.
+///
+/// This keeps the attribute-to-state pattern in one place: rather than parsing each attribute in
+/// `attribute_changed_callback` and storing it in a field, a component stores an `Attribute` and reads it or
+/// subscribes to it, and framework adapters can forward it into their own reactive state.
+///
+/// The attribute must be one of the element's [observed_attributes](crate::CustomElement::observed_attributes)
+/// (or [observed_bool_attributes](crate::CustomElement::observed_bool_attributes)), since those are the only changes
+/// the crate is told about. Clones share the same value and subscribers.
+///
+/// ```rust,ignore
+/// fn constructor(&mut self, this: &HtmlElement) {
+///     self.size = Attribute::parsed(this, "size");
+///     let label = self.label.clone();
+///     self.size.subscribe(move |size: &u32| label.set_text_content(Some(&size.to_string())));
+/// }
+/// ```
+pub struct Attribute<T> {
+    state: Rc<AttributeState<T>>,
+}
+
+impl<T> Clone for Attribute<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T: 'static> Attribute<T> {
+    /// Creates a cell for the attribute `name` of the element, whose value is `parse` applied to the attribute's
+    /// value, or to `None` while the attribute is absent. It starts out with the attribute's current value.
+    pub fn new(
+        this: &HtmlElement,
+        name: &str,
+        parse: impl Fn(Option<&str>) -> T + 'static,
+    ) -> Self {
+        let host = host(this);
+        let raw = host.get_attribute(name);
+        let cells = cells_for(&host, name);
+        let state = Rc::new_cyclic(|weak: &Weak<AttributeState<T>>| {
+            // the element only holds on to the cell weakly, so that it is freed along with the component
+            let weak = weak.clone();
+            let update = Closure::wrap(Box::new(move |raw: Option<String>| {
+                if let Some(state) = weak.upgrade() {
+                    state.update(raw.as_deref());
+                }
+            }) as Box<dyn Fn(Option<String>)>)
+            .into_js_value();
+            cells.push(&update);
+            AttributeState {
+                name: name.to_string(),
+                value: RefCell::new(parse(raw.as_deref())),
+                raw: RefCell::new(raw),
+                parse: Box::new(parse),
+                subscribers: RefCell::new(Vec::new()),
+                cells,
+                update,
+            }
+        });
+
+        Self { state }
+    }
+
+    /// The name of the attribute.
+    pub fn name(&self) -> &str {
+        &self.state.name
+    }
+
+    /// The attribute's current value, unparsed, or `None` if it is absent.
+    pub fn raw(&self) -> Option<String> {
+        self.state.raw.borrow().clone()
+    }
+
+    /// Calls `f` with a reference to the current value.
+    pub fn with<U>(&self, f: impl FnOnce(&T) -> U) -> U {
+        f(&self.state.value.borrow())
+    }
+
+    /// Calls `f` with the new value each time the attribute changes, for as long as the cell exists.
+    pub fn subscribe(&self, f: impl Fn(&T) + 'static) {
+        self.state.subscribers.borrow_mut().push(Rc::new(f));
+    }
+}
+
+impl<T: Clone + 'static> Attribute<T> {
+    /// The current value.
+    pub fn get(&self) -> T {
+        self.state.value.borrow().clone()
+    }
+}
+
+impl Attribute<Option<String>> {
+    /// A cell holding the attribute's value as it is, or `None` while it is absent.
+    pub fn string(this: &HtmlElement, name: &str) -> Self {
+        Self::new(this, name, |raw| raw.map(String::from))
+    }
+}
+
+impl Attribute<bool> {
+    /// A cell holding whether the [boolean attribute](crate::attributes) is present.
+    pub fn bool(this: &HtmlElement, name: &str) -> Self {
+        Self::new(this, name, |raw| raw.is_some())
+    }
+}
+
+impl<T: FromStr + Default + 'static> Attribute<T> {
+    /// A cell holding the attribute's value parsed as a `T`, or `T::default()` while it is absent or does not parse.
+    pub fn parsed(this: &HtmlElement, name: &str) -> Self {
+        Self::new(this, name, |raw| {
+            raw.and_then(|raw| raw.trim().parse().ok())
+                .unwrap_or_default()
+        })
+    }
+}
+
+// The update functions of the `Attribute` cells bound to the attribute `name` of `host`, kept on the element in a
+// `Map` from attribute names to arrays of functions.
+fn cells_for(host: &HtmlElement, name: &str) -> js_sys::Array {
+    let key = JsValue::from_str("_attributeCells");
+    let map = match js_sys::Reflect::get(host, &key)
+        .unwrap_throw()
+        .dyn_into::<js_sys::Map>()
+    {
+        Ok(map) => map,
+        Err(_) => {
+            let map = js_sys::Map::new();
+            js_sys::Reflect::set(host, &key, &map).unwrap_throw();
+            map
+        }
+    };
+    let name = JsValue::from_str(name);
+    match map.get(&name).dyn_into::<js_sys::Array>() {
+        Ok(cells) => cells,
+        Err(_) => {
+            let cells = js_sys::Array::new();
+            map.set(&name, &cells);
+            cells
+        }
+    }
+}
+
+// Updates the `Attribute` cells bound to the attribute `name` of `host`, if there are any.
+pub(crate) fn update_cells(host: &HtmlElement, name: &str, value: Option<&str>) {
+    let cells = js_sys::Reflect::get(host, &JsValue::from_str("_attributeCells"))
+        .unwrap_throw()
+        .dyn_into::<js_sys::Map>()
+        .ok()
+        .and_then(|map| {
+            map.get(&JsValue::from_str(name))
+                .dyn_into::<js_sys::Array>()
+                .ok()
+        });
+    let cells = match cells {
+        Some(cells) => cells,
+        None => return,
+    };
+    let value = value.map(JsValue::from_str).unwrap_or(JsValue::NULL);
+    // copied, since subscribers can drop cells, which removes them from the array
+    for cell in cells.slice(0, cells.length()).iter() {
+        cell.unchecked_into::<js_sys::Function>()
+            .call1(&JsValue::NULL, &value)
+            .unwrap_throw();
+    }
+}
+
+#[cfg(all(test, feature = "dev"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_handler_attributes_are_global_only_if_the_handler_exists() {
+        let is_handler = |property: &str| ["onclick", "onselect-item"].contains(&property);
+        assert!(is_global("onclick", is_handler));
+        assert!(is_global("on-select-item", is_handler));
+        assert!(is_global("aria-label", is_handler));
+        assert!(!is_global("one", is_handler));
+        assert!(!is_global("online", is_handler));
+        assert!(!is_global("onchnage", is_handler));
+        assert!(!is_global("on", is_handler));
+    }
+}
@@ -0,0 +1,113 @@
+//! Two-way bindings between form controls inside a custom element and the component's state.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, Event, HtmlElement};
+
+use crate::query;
+
+const BOUND_EVENTS: [&str; 2] = ["input", "change"];
+
+/// A live binding between a form control and some piece of state, created by [bind_input].
+///
+/// The binding's event listeners are removed when it is dropped, so it is usually stored as a field
+/// of the component that created it.
+pub struct InputBinding {
+    control: Element,
+    getter: Box<dyn Fn() -> String>,
+    listener: Closure<dyn FnMut(Event)>,
+}
+
+impl InputBinding {
+    /// Pushes the current state, as returned by the getter, into the form control.
+    /// Call this whenever the state changes from somewhere other than the control itself.
+    pub fn refresh(&self) {
+        let value = (self.getter)();
+        if self.value().as_deref() != Some(value.as_str()) {
+            js_sys::Reflect::set(
+                &self.control,
+                &JsValue::from_str("value"),
+                &JsValue::from_str(&value),
+            )
+            .unwrap_throw();
+        }
+    }
+
+    /// The current `value` of the form control.
+    pub fn value(&self) -> Option<String> {
+        js_sys::Reflect::get(&self.control, &JsValue::from_str("value"))
+            .ok()
+            .and_then(|value| value.as_string())
+    }
+
+    /// The form control this binding is attached to.
+    pub fn control(&self) -> &Element {
+        &self.control
+    }
+}
+
+impl Drop for InputBinding {
+    fn drop(&mut self) {
+        for event_type in BOUND_EVENTS {
+            let _ = self.control.remove_event_listener_with_callback(
+                event_type,
+                self.listener.as_ref().unchecked_ref(),
+            );
+        }
+    }
+}
+
+/// Binds the `value` of the first form control (`<input>`, `<textarea>`, `<select>`, etc.) matching
+/// `selector` in the element's shadow root (or in the element itself, if it has no shadow root)
+/// to a piece of component state.
+///
+/// The control is initialized with the value returned by `getter`, and `setter` is called with the new
+/// value on each `input` or `change` event. Use [InputBinding::refresh] to push later state changes back
+/// into the control.
+///
+/// Returns `None` if no element matches the selector.
+///
+/// ```rust,ignore
+/// fn inject_children(&mut self, this: &HtmlElement) {
+///     this.set_inner_html(r#"<input type="text">"#);
+///     let name = self.name.clone(); // Rc<RefCell<String>>
+///     let name_setter = self.name.clone();
+///     self.binding = bind_input(
+///         this,
+///         "input",
+///         move || name.borrow().clone(),
+///         move |value| *name_setter.borrow_mut() = value,
+///     );
+/// }
+/// ```
+pub fn bind_input(
+    this: &HtmlElement,
+    selector: &str,
+    getter: impl Fn() -> String + 'static,
+    mut setter: impl FnMut(String) + 'static,
+) -> Option<InputBinding> {
+    let control: Element = query(this, selector)?;
+
+    let listener = Closure::wrap(Box::new(move |event: Event| {
+        let value = event
+            .current_target()
+            .and_then(|target| js_sys::Reflect::get(&target, &JsValue::from_str("value")).ok())
+            .and_then(|value| value.as_string());
+        if let Some(value) = value {
+            setter(value);
+        }
+    }) as Box<dyn FnMut(Event)>);
+    for event_type in BOUND_EVENTS {
+        control
+            .add_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+    }
+
+    let binding = InputBinding {
+        control,
+        getter: Box::new(getter),
+        listener,
+    };
+    binding.refresh();
+    Some(binding)
+}
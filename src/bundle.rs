@@ -0,0 +1,125 @@
+//! Several WebAssembly bundles on one page.
+//!
+//! Micro-frontend pages can load more than one bundle built with this crate, and two of them may both define the same
+//! tag, like a shared `x-button`. The custom element registry only accepts the first definition, so by default the
+//! second [define](crate::CustomElement::define) throws. A component's
+//! [coexistence](crate::CustomElement::coexistence) policy can instead reuse the class already defined:
+//!
+//! ```rust,ignore
+//! impl CustomElement for Button {
+//!     fn coexistence() -> Coexistence {
+//!         Coexistence::SkipIfDefined
+//!     }
+//! }
+//! ```
+//!
+//! Each bundle has a [name], recorded on the classes it defines, so that [owner] can tell which bundle defined a tag.
+//! Give each bundle a recognizable name with [set_name] before it defines any elements.
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+
+/// What [define](crate::CustomElement::define) does when the tag has already been defined, by another bundle or by
+/// JavaScript. See the [module documentation](self).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Coexistence {
+    /// Define the element anyway, so that the registry throws. This is the default, since two different components
+    /// under one tag name are usually a mistake.
+    #[default]
+    ErrorIfDefined,
+    /// Leave the existing definition in place, and use its class as this component's.
+    SkipIfDefined,
+    /// Like `SkipIfDefined`, but writes a warning, naming the bundle that defined the tag, to the console.
+    WarnAndSkip,
+}
+
+thread_local! {
+    static NAME: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// the static, on the classes this crate defines, that holds the name of the bundle that defined them
+const OWNER: &str = "__rustBundle";
+
+/// Names this bundle. Elements defined before it is called keep the name they were defined with.
+pub fn set_name(name: impl Into<String>) {
+    NAME.with(|n| *n.borrow_mut() = Some(name.into()));
+}
+
+/// This bundle's name: the one given to [set_name], or a random one like `bundle-3fa2c1`.
+pub fn name() -> String {
+    NAME.with(|name| {
+        name.borrow_mut()
+            .get_or_insert_with(|| {
+                format!(
+                    "bundle-{:06x}",
+                    (js_sys::Math::random() * 16_777_216.0) as u32
+                )
+            })
+            .clone()
+    })
+}
+
+/// The name of the bundle that defined `tag_name` in the document's custom element registry, or `None` if it has not
+/// been defined, or was defined by something other than this crate.
+pub fn owner(tag_name: &str) -> Option<String> {
+    owner_in(&registry(&js_sys::global())?, tag_name)
+}
+
+// The `customElements` registry of `global`, if it has one.
+pub(crate) fn registry(global: &JsValue) -> Option<JsValue> {
+    js_sys::Reflect::get(global, &JsValue::from_str("customElements"))
+        .ok()
+        .filter(|registry| registry.is_object())
+}
+
+// The class already defined for `tag_name` in `registry`, if any.
+pub(crate) fn existing(registry: &JsValue, tag_name: &str) -> Option<js_sys::Function> {
+    let get: js_sys::Function = js_sys::Reflect::get(registry, &JsValue::from_str("get"))
+        .ok()?
+        .dyn_into()
+        .ok()?;
+    get.call1(registry, &JsValue::from_str(tag_name))
+        .ok()?
+        .dyn_into()
+        .ok()
+}
+
+fn owner_in(registry: &JsValue, tag_name: &str) -> Option<String> {
+    let existing = existing(registry, tag_name)?;
+    js_sys::Reflect::get(&existing, &JsValue::from_str(OWNER))
+        .ok()?
+        .as_string()
+}
+
+// Records this bundle as the owner of a class it defined.
+pub(crate) fn claim(constructor: &js_sys::Function) {
+    js_sys::Reflect::set(
+        constructor,
+        &JsValue::from_str(OWNER),
+        &JsValue::from(name()),
+    )
+    .unwrap_throw();
+}
+
+// The class to use instead of defining `tag_name`, if the policy says to skip an existing definition.
+pub(crate) fn reuse(
+    registry: &JsValue,
+    tag_name: &str,
+    coexistence: Coexistence,
+) -> Option<js_sys::Function> {
+    if coexistence == Coexistence::ErrorIfDefined {
+        return None;
+    }
+    let existing = existing(registry, tag_name)?;
+    if coexistence == Coexistence::WarnAndSkip {
+        let owner = owner_in(registry, tag_name).unwrap_or_else(|| "JavaScript".to_string());
+        crate::logging::warn(&format!(
+            "<{}> was already defined by {}; {} is using that definition",
+            tag_name,
+            owner,
+            name()
+        ));
+    }
+    Some(existing)
+}
@@ -0,0 +1,265 @@
+//! A base for custom elements that render into a `<canvas>`. Requires the `canvas` feature.
+//!
+//! Implement [CanvasElement] for your renderer and define it with [Canvas]:
+//! ```rust,ignore
+//! #[derive(Default)]
+//! struct Chart { data: Vec<f64> }
+//!
+//! impl CanvasElement for Chart {
+//!     type Context = CanvasRenderingContext2d;
+//!
+//!     fn draw(&mut self, ctx: &CanvasRenderingContext2d, size: CanvasSize) {
+//!         ctx.clear_rect(0.0, 0.0, size.width, size.height);
+//!         /* ... */
+//!     }
+//! }
+//!
+//! #[wasm_bindgen]
+//! pub fn define_elements() {
+//!     Canvas::<Chart>::define("my-chart");
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    CanvasRenderingContext2d, HtmlCanvasElement, HtmlElement, MediaQueryList, ResizeObserver,
+    Window,
+};
+
+use crate::animation::AnimationLoop;
+use crate::{document_of, inject_style, window_of, CustomElement};
+
+/// The size of the canvas when it is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CanvasSize {
+    /// The width of the canvas, in CSS pixels.
+    pub width: f64,
+    /// The height of the canvas, in CSS pixels.
+    pub height: f64,
+    /// The number of device pixels per CSS pixel. The canvas's backing store is `width * pixel_ratio` by
+    /// `height * pixel_ratio` device pixels, so that it stays sharp on high-density displays.
+    pub pixel_ratio: f64,
+}
+
+/// A component that renders into a `<canvas>` that fills a custom element, defined with [Canvas].
+pub trait CanvasElement: Default + 'static {
+    /// The type of rendering context passed to [draw](CanvasElement::draw), like
+    /// [CanvasRenderingContext2d] or `web_sys::WebGl2RenderingContext`.
+    type Context: JsCast;
+
+    /// The context ID passed to `getContext()`. This must match [Context](CanvasElement::Context).
+    ///
+    /// Defaults to `"2d"`.
+    fn context_id() -> &'static str {
+        "2d"
+    }
+
+    /// Draws the component. This is invoked when the element is first connected, each time it is resized, and
+    /// after each attribute change, or on every animation frame if [animated](CanvasElement::animated) is `true`.
+    ///
+    /// A 2D context is scaled so that drawing can use CSS pixels; other contexts are passed as they are,
+    /// and should use the device pixel size in `size` (for example, for their viewport).
+    fn draw(&mut self, ctx: &Self::Context, size: CanvasSize);
+
+    /// Whether the component should be redrawn on every animation frame while it is connected.
+    ///
+    /// Defaults to `false`.
+    fn animated() -> bool {
+        false
+    }
+
+    /// The names of the attributes whose changes should be observed, like
+    /// [CustomElement::observed_attributes].
+    fn observed_attributes() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Invoked each time one of the observed attributes changes, before the component is redrawn.
+    fn attribute_changed_callback(
+        &mut self,
+        _name: String,
+        _old_value: Option<String>,
+        _new_value: Option<String>,
+    ) {
+    }
+}
+
+struct CanvasState<T: CanvasElement> {
+    component: T,
+    canvas: Option<HtmlCanvasElement>,
+    context: Option<T::Context>,
+    size: CanvasSize,
+}
+
+impl<T: CanvasElement> CanvasState<T> {
+    fn resize(&mut self) {
+        let canvas = match &self.canvas {
+            Some(canvas) => canvas,
+            None => return,
+        };
+        let rect = canvas.get_bounding_client_rect();
+        let pixel_ratio = window_of(canvas).device_pixel_ratio();
+        canvas.set_width((rect.width() * pixel_ratio).round() as u32);
+        canvas.set_height((rect.height() * pixel_ratio).round() as u32);
+        self.size = CanvasSize {
+            width: rect.width(),
+            height: rect.height(),
+            pixel_ratio,
+        };
+    }
+
+    fn draw(&mut self) {
+        if let Some(context) = &self.context {
+            if let Some(ctx) = context.dyn_ref::<CanvasRenderingContext2d>() {
+                let ratio = self.size.pixel_ratio;
+                ctx.set_transform(ratio, 0.0, 0.0, ratio, 0.0, 0.0)
+                    .unwrap_throw();
+            }
+            self.component.draw(context, self.size);
+        }
+    }
+}
+
+type PixelRatioListener = (MediaQueryList, Closure<dyn FnMut()>);
+
+// Resizes and redraws the canvas when the device's pixel ratio changes, for example when the window is zoomed or
+// moved to another display, which doesn't resize the element. The media query only matches the ratio it was created
+// for, so a new one replaces it after each change.
+fn watch_pixel_ratio<T: CanvasElement>(
+    window: &Window,
+    state: &Rc<RefCell<CanvasState<T>>>,
+    slot: &Rc<RefCell<Option<PixelRatioListener>>>,
+) {
+    unwatch_pixel_ratio(slot);
+    let query = format!("(resolution: {}dppx)", window.device_pixel_ratio());
+    let list = match window.match_media(&query).unwrap_throw() {
+        Some(list) => list,
+        None => return,
+    };
+    let listener = Closure::wrap(Box::new({
+        let (window, state, slot) = (window.clone(), state.clone(), slot.clone());
+        move || {
+            {
+                let mut state = state.borrow_mut();
+                state.resize();
+                state.draw();
+            }
+            // dropping this closure while it runs is safe: wasm-bindgen frees it once it returns
+            watch_pixel_ratio(&window, &state, &slot);
+        }
+    }) as Box<dyn FnMut()>);
+    list.add_event_listener_with_callback("change", listener.as_ref().unchecked_ref())
+        .unwrap_throw();
+    *slot.borrow_mut() = Some((list, listener));
+}
+
+fn unwatch_pixel_ratio(slot: &RefCell<Option<PixelRatioListener>>) {
+    let previous = slot.borrow_mut().take();
+    if let Some((list, listener)) = previous {
+        list.remove_event_listener_with_callback("change", listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+    }
+}
+
+/// A custom element that wraps a [CanvasElement] in a `<canvas>` inside its shadow root, resizing the canvas
+/// with the element (using a `ResizeObserver`) and when the device's pixel ratio changes (using a `resolution` media
+/// query).
+pub struct Canvas<T: CanvasElement> {
+    state: Rc<RefCell<CanvasState<T>>>,
+    resize_observer: Option<(ResizeObserver, Closure<dyn FnMut()>)>,
+    pixel_ratio_listener: Rc<RefCell<Option<PixelRatioListener>>>,
+    animation: Option<AnimationLoop>,
+}
+
+impl<T: CanvasElement> Default for Canvas<T> {
+    fn default() -> Self {
+        Self {
+            state: Rc::new(RefCell::new(CanvasState {
+                component: T::default(),
+                canvas: None,
+                context: None,
+                size: CanvasSize::default(),
+            })),
+            resize_observer: None,
+            pixel_ratio_listener: Rc::new(RefCell::new(None)),
+            animation: None,
+        }
+    }
+}
+
+impl<T: CanvasElement> CustomElement for Canvas<T> {
+    fn inject_children(&mut self, this: &HtmlElement) {
+        inject_style(
+            this,
+            ":host { display: block; } canvas { display: block; width: 100%; height: 100%; }",
+        );
+        let canvas: HtmlCanvasElement = document_of(this)
+            .create_element("canvas")
+            .unwrap_throw()
+            .unchecked_into();
+        this.append_child(&canvas).unwrap_throw();
+        let context = canvas
+            .get_context(T::context_id())
+            .unwrap_throw()
+            .map(|context| context.unchecked_into());
+
+        let mut state = self.state.borrow_mut();
+        state.canvas = Some(canvas);
+        state.context = context;
+    }
+
+    fn observed_attributes() -> &'static [&'static str] {
+        T::observed_attributes()
+    }
+
+    fn connected_callback(&mut self, this: &HtmlElement) {
+        let redraw = Closure::wrap(Box::new({
+            let state = self.state.clone();
+            move || {
+                let mut state = state.borrow_mut();
+                state.resize();
+                state.draw();
+            }
+        }) as Box<dyn FnMut()>);
+        let resize_observer = ResizeObserver::new(redraw.as_ref().unchecked_ref()).unwrap_throw();
+        resize_observer.observe(this);
+        self.resize_observer = Some((resize_observer, redraw));
+        watch_pixel_ratio(&window_of(this), &self.state, &self.pixel_ratio_listener);
+
+        // the loop pauses while the element is disconnected, so it is only started once
+        if T::animated() && self.animation.is_none() {
+            let state = self.state.clone();
+            self.animation = Some(AnimationLoop::start(this, move |_| {
+                state.borrow_mut().draw()
+            }));
+        }
+    }
+
+    fn disconnected_callback(&mut self, _this: &HtmlElement) {
+        if let Some((resize_observer, _)) = self.resize_observer.take() {
+            resize_observer.disconnect();
+        }
+        // the listener holds its own slot, so it is only dropped once removed
+        unwatch_pixel_ratio(&self.pixel_ratio_listener);
+    }
+
+    fn attribute_changed_callback(
+        &mut self,
+        _this: &HtmlElement,
+        name: String,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    ) {
+        let mut state = self.state.borrow_mut();
+        state
+            .component
+            .attribute_changed_callback(name, old_value, new_value);
+        if !T::animated() {
+            state.draw();
+        }
+    }
+}
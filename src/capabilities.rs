@@ -0,0 +1,107 @@
+//! What the browser supports of the web platform features the crate builds on.
+//!
+//! Browsers differ in what they implement: Safari has never supported customized built-in elements, and scoped
+//! registries are new everywhere. [capabilities] checks for each of these once, so that components and the crate's
+//! own fallbacks can branch on the result:
+//!
+//! ```rust,ignore
+//! if capabilities().customized_built_ins {
+//!     FancyButton::define("fancy-button");
+//! } else {
+//!     FancyButtonWrapper::define("fancy-button");
+//! }
+//! ```
+//!
+//! The crate includes the report in the errors and warnings it gives when an element asks for something the browser
+//! does not support.
+
+use std::cell::Cell;
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// The features of the browser, as found by [capabilities].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Whether there is a `customElements` registry at all, which some embedded web views lack.
+    pub custom_elements: bool,
+    /// Whether elements can extend built-in elements other than `HTMLElement`, like `<button is="fancy-button">`
+    /// (see [superclass](crate::CustomElement::superclass)).
+    pub customized_built_ins: bool,
+    /// Whether elements can have `ElementInternals`, which [form-associated](crate::CustomElement::form_associated)
+    /// elements and custom states need.
+    pub element_internals: bool,
+    /// Whether shadow roots can share constructable stylesheets through `adoptedStyleSheets`, as
+    /// [register_global](crate::styles::register_global) does.
+    pub adopted_style_sheets: bool,
+    /// Whether the HTML parser creates shadow roots from `<template shadowrootmode>`, as the output of
+    /// [serialize](crate::serialize()) needs.
+    pub declarative_shadow_dom: bool,
+    /// Whether new `CustomElementRegistry` objects can be created, for registries scoped to a shadow root.
+    pub scoped_registries: bool,
+}
+
+thread_local! {
+    static CAPABILITIES: Cell<Option<Capabilities>> = const { Cell::new(None) };
+}
+
+/// The features of the current window, which are checked the first time this is called.
+pub fn capabilities() -> Capabilities {
+    CAPABILITIES.with(|capabilities| match capabilities.get() {
+        Some(known) => known,
+        None => {
+            let known = capabilities_in(&js_sys::global());
+            capabilities.set(Some(known));
+            known
+        }
+    })
+}
+
+// The features of `global`, a window or other global object.
+pub(crate) fn capabilities_in(global: &JsValue) -> Capabilities {
+    let get = |target: &JsValue, name: &str| {
+        js_sys::Reflect::get(target, &JsValue::from_str(name)).unwrap_or(JsValue::UNDEFINED)
+    };
+    let has = |target: &JsValue, name: &str| {
+        target.is_object()
+            && js_sys::Reflect::has(target, &JsValue::from_str(name)).unwrap_or(false)
+    };
+    let prototype = |class: &str| get(&get(global, class), "prototype");
+
+    let custom_elements = get(global, "customElements").is_object();
+    let registry_class = get(global, "CustomElementRegistry");
+    Capabilities {
+        custom_elements,
+        customized_built_ins: custom_elements && supports_customized_built_ins(global),
+        element_internals: has(&prototype("HTMLElement"), "attachInternals"),
+        adopted_style_sheets: has(&prototype("Document"), "adoptedStyleSheets")
+            && has(&prototype("CSSStyleSheet"), "replaceSync"),
+        declarative_shadow_dom: has(&prototype("HTMLTemplateElement"), "shadowRootMode"),
+        scoped_registries: registry_class
+            .dyn_ref::<js_sys::Function>()
+            .is_some_and(|class| js_sys::Reflect::construct(class, &js_sys::Array::new()).is_ok()),
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let yes_no = |supported: bool| if supported { "yes" } else { "no" };
+        write!(
+            f,
+            "custom elements: {}, customized built-ins: {}, ElementInternals: {}, adoptedStyleSheets: {}, \
+             declarative shadow DOM: {}, scoped registries: {}",
+            yes_no(self.custom_elements),
+            yes_no(self.customized_built_ins),
+            yes_no(self.element_internals),
+            yes_no(self.adopted_style_sheets),
+            yes_no(self.declarative_shadow_dom),
+            yes_no(self.scoped_registries),
+        )
+    }
+}
+
+#[wasm_bindgen(module = "/src/make_custom_element.js")]
+extern "C" {
+    fn supports_customized_built_ins(global: &JsValue) -> bool;
+}
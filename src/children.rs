@@ -0,0 +1,167 @@
+//! Keeping track of a container element's children, in order.
+//!
+//! Containers like carousels and lists work with the children the page gives them, which can be added, removed and
+//! reordered at any time. [track] keeps the ordered list of the host's children that match a selector, and reports
+//! each change to it as a list of [ChildChange]s, until the element is disconnected:
+//!
+//! ```rust,ignore
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     let slides = self.slides.clone();
+//!     children::track(this, "carousel-slide", move |current, changes| {
+//!         for change in changes {
+//!             if let ChildChange::Added { element, .. } = change {
+//!                 element.set_attribute("role", "group").unwrap_throw();
+//!             }
+//!         }
+//!         slides.replace(current.to_vec());
+//!     });
+//! }
+//! ```
+//!
+//! The children are those in the light DOM, which a component with a shadow root usually shows through a `<slot>`.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement, MutationObserver, MutationObserverInit, Node};
+
+use crate::host;
+use crate::scope::Scope;
+
+/// A change to the list of children kept by [track]. Indexes of removed elements are in the list before the change,
+/// and indexes of added elements in the list after it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChildChange {
+    /// The element was added at `index`.
+    Added { index: usize, element: Element },
+    /// The element at `index` was removed, or no longer matches.
+    Removed { index: usize, element: Element },
+    /// The element moved from index `from` to index `to`. Elements that only shifted because others were added or
+    /// removed before them are not reported as moved.
+    Moved {
+        from: usize,
+        to: usize,
+        element: Element,
+    },
+}
+
+/// Calls `callback` with the host's children that match `selector`, like `"li"` or `"[slot=item]"`, in order, and the
+/// changes since it was last called, until the element is disconnected. It is called right away, with every child as
+/// [added](ChildChange::Added), and then after each batch of changes to the host's children. `this` may be either the
+/// element or its shadow root.
+///
+/// Changes to the children's attributes are not observed, so a child that starts or stops matching `selector` is only
+/// noticed the next time children are added, removed or reordered.
+pub fn track(
+    this: &HtmlElement,
+    selector: &str,
+    mut callback: impl FnMut(&[Element], &[ChildChange]) + 'static,
+) {
+    let host = host(this);
+    let selector = selector.to_string();
+    let mut current = matching_children(&host, &selector);
+    let added = current
+        .iter()
+        .enumerate()
+        .map(|(index, element)| ChildChange::Added {
+            index,
+            element: element.clone(),
+        })
+        .collect::<Vec<_>>();
+    callback(&current, &added);
+
+    let observed = host.clone();
+    let observer_callback = Closure::wrap(Box::new(move |_records: js_sys::Array| {
+        let next = matching_children(&observed, &selector);
+        let changes = diff(&current, &next);
+        current = next;
+        if !changes.is_empty() {
+            callback(&current, &changes);
+        }
+    }) as Box<dyn FnMut(js_sys::Array)>);
+    let observer = MutationObserver::new(observer_callback.as_ref().unchecked_ref()).unwrap_throw();
+    let options = MutationObserverInit::new();
+    options.set_child_list(true);
+    observer
+        .observe_with_options(&host, &options)
+        .unwrap_throw();
+    Scope::new(&host).on_cleanup(move || {
+        observer.disconnect();
+        drop(observer_callback);
+    });
+}
+
+fn matching_children(host: &HtmlElement, selector: &str) -> Vec<Element> {
+    let children = host.child_nodes();
+    (0..children.length())
+        .filter_map(|index| children.item(index))
+        .filter(|child| child.node_type() == Node::ELEMENT_NODE)
+        .map(|child| child.unchecked_into::<Element>())
+        .filter(|child| child.matches(selector).unwrap_throw())
+        .collect()
+}
+
+// The changes that turn `old` into `new`. The elements in both lists that keep their relative order are the longest
+// increasing subsequence of their old indexes, in new order; the rest are reported as moved.
+fn diff(old: &[Element], new: &[Element]) -> Vec<ChildChange> {
+    let mut changes = Vec::new();
+    let old_index = |element: &Element| old.iter().position(|other| other == element);
+    let new_index = |element: &Element| new.iter().position(|other| other == element);
+
+    for (index, element) in old.iter().enumerate() {
+        if new_index(element).is_none() {
+            changes.push(ChildChange::Removed {
+                index,
+                element: element.clone(),
+            });
+        }
+    }
+
+    // (old index, new index) of the elements in both lists, in new order
+    let mut kept = Vec::new();
+    for (index, element) in new.iter().enumerate() {
+        match old_index(element) {
+            Some(old) => kept.push((old, index)),
+            None => changes.push(ChildChange::Added {
+                index,
+                element: element.clone(),
+            }),
+        }
+    }
+    let in_order = longest_increasing(&kept.iter().map(|(old, _)| *old).collect::<Vec<_>>());
+    for (position, (from, to)) in kept.iter().enumerate() {
+        if !in_order.contains(&position) {
+            changes.push(ChildChange::Moved {
+                from: *from,
+                to: *to,
+                element: new[*to].clone(),
+            });
+        }
+    }
+    changes
+}
+
+// The positions in `values` of one of its longest strictly increasing subsequences.
+fn longest_increasing(values: &[usize]) -> Vec<usize> {
+    // `tails[length]` is the position of the smallest value that ends an increasing subsequence of `length + 1`
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; values.len()];
+    for (position, value) in values.iter().enumerate() {
+        let length = tails.partition_point(|tail| values[*tail] < *value);
+        if length > 0 {
+            previous[position] = Some(tails[length - 1]);
+        }
+        if length == tails.len() {
+            tails.push(position);
+        } else {
+            tails[length] = position;
+        }
+    }
+    let mut sequence = Vec::with_capacity(tails.len());
+    let mut position = tails.last().copied();
+    while let Some(current) = position {
+        sequence.push(current);
+        position = previous[current];
+    }
+    sequence.reverse();
+    sequence
+}
@@ -0,0 +1,114 @@
+//! Coordination between a parent component and the child components inside it, like a tab list and its tabs.
+//!
+//! A child registers with the nearest element around it of the parent's type, usually when it is connected. The
+//! parent's component then has [child_attached](crate::CustomElement::child_attached) called with a [Child] handle,
+//! and [child_detached](crate::CustomElement::child_detached) once the child is disconnected:
+//!
+//! ```rust,ignore
+//! impl CustomElement for Tab {
+//!     fn connected_callback(&mut self, this: &HtmlElement) {
+//!         composition::register_with_parent::<Tabs>(this);
+//!     }
+//! }
+//!
+//! impl CustomElement for Tabs {
+//!     fn child_attached(&mut self, _this: &HtmlElement, child: Child) {
+//!         if child.is::<Tab>() {
+//!             self.tabs.push(child);
+//!         }
+//!     }
+//!
+//!     fn child_detached(&mut self, _this: &HtmlElement, child: Child) {
+//!         self.tabs.retain(|tab| *tab != child);
+//!     }
+//! }
+//! ```
+//!
+//! The parent is found through shadow roots, so a child can be slotted into the parent, or rendered inside its
+//! shadow root. Both elements have to be defined with this crate.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlElement, Node, ShadowRoot};
+
+use crate::definition::is_instance;
+use crate::instances::dispatch;
+use crate::scope::Scope;
+use crate::{defined_tag_name, host, CustomElement};
+
+/// A child element registered with its parent. See the [module documentation](self).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Child {
+    element: HtmlElement,
+}
+
+impl Child {
+    /// The child element.
+    pub fn element(&self) -> &HtmlElement {
+        &self.element
+    }
+
+    /// The child element's tag name, or the value of its `is` attribute for a customized built-in element.
+    pub fn tag_name(&self) -> String {
+        defined_tag_name(&self.element)
+    }
+
+    /// Whether the child is an element of type `C`.
+    pub fn is<C: CustomElement>(&self) -> bool {
+        is_instance::<C>(&self.element)
+    }
+}
+
+/// Registers the element with the nearest element around it of type `P`, whose component has
+/// [child_attached](crate::CustomElement::child_attached) called with the element, and
+/// [child_detached](crate::CustomElement::child_detached) the next time the element is disconnected. Returns the
+/// parent element, or `None` if there is none, in which case nothing happens. `this` may be either the element or its
+/// shadow root.
+///
+/// Registrations are undone when the element is disconnected, so they are usually made in
+/// [connected_callback](crate::CustomElement::connected_callback).
+pub fn register_with_parent<P: CustomElement>(this: &HtmlElement) -> Option<HtmlElement> {
+    let child = host(this);
+    let parent = parent_of::<P>(&child)?;
+    notify(&parent, &child, true);
+    Scope::new(&child).on_cleanup({
+        let (parent, child) = (parent.clone(), child.clone());
+        move || notify(&parent, &child, false)
+    });
+    Some(parent)
+}
+
+/// The nearest element of type `P` around the element, looking through shadow roots, if any. `this` may be either
+/// the element or its shadow root.
+pub fn parent_of<P: CustomElement>(this: &HtmlElement) -> Option<HtmlElement> {
+    let mut node = composed_parent(&host(this).into());
+    while let Some(current) = node {
+        if is_instance::<P>(&current) {
+            return current.dyn_into().ok();
+        }
+        node = composed_parent(&current);
+    }
+    None
+}
+
+fn composed_parent(node: &Node) -> Option<Node> {
+    let parent = node.parent_node()?;
+    match parent.dyn_ref::<ShadowRoot>() {
+        Some(shadow_root) => Some(shadow_root.host().into()),
+        None => Some(parent),
+    }
+}
+
+// Calls the parent's `childCallback` handler, which every element defined with this crate has.
+fn notify(parent: &HtmlElement, child: &HtmlElement, attached: bool) {
+    dispatch(
+        parent,
+        "childCallback",
+        &[child, &JsValue::from_bool(attached)],
+    );
+}
+
+// The handle passed to the parent's component.
+pub(crate) fn child(element: HtmlElement) -> Child {
+    Child { element }
+}
@@ -0,0 +1,76 @@
+//! Support for pages whose Content Security Policy only allows styles with a nonce, like
+//! `style-src 'nonce-r4nd0m'`.
+//!
+//! The `<style>` and `<link>` elements the crate creates, with [inject_style](crate::inject_style),
+//! [inject_stylesheet](crate::inject_stylesheet) and the [styles](crate::styles) helpers, are given the page's
+//! nonce, so that the browser applies them. The nonce is either set by the application with [set_nonce], or found
+//! in the page: in a `<meta property="csp-nonce">` element, as some bundlers add, or else on the first `<script>` or
+//! `<style>` element that has one.
+//!
+//! ```rust,ignore
+//! #[wasm_bindgen(start)]
+//! pub fn start() {
+//!     csp::set_nonce(&nonce_from_server);
+//!     MyButton::define("my-button");
+//! }
+//! ```
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{window, Element};
+
+thread_local! {
+    static NONCE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Uses `nonce` for the elements the crate creates from now on, instead of looking for one in the page.
+pub fn set_nonce(nonce: &str) {
+    NONCE.with(|current| *current.borrow_mut() = Some(nonce.to_string()));
+}
+
+/// The nonce given to [set_nonce], or else the one found in the page, if any. See the
+/// [module documentation](self).
+pub fn nonce() -> Option<String> {
+    if let Some(nonce) = NONCE.with(|nonce| nonce.borrow().clone()) {
+        return Some(nonce);
+    }
+    let nonce = discover()?;
+    NONCE.with(|current| *current.borrow_mut() = Some(nonce.clone()));
+    Some(nonce)
+}
+
+// Sets the nonce, if there is one, on an element the crate is about to add to the document.
+pub(crate) fn apply(element: &Element) {
+    if let Some(nonce) = nonce() {
+        element.set_attribute("nonce", &nonce).unwrap_throw();
+    }
+}
+
+fn discover() -> Option<String> {
+    let document = window()?.document()?;
+    // browsers hide the `nonce` attribute of elements the page was loaded with, so read the property instead
+    let property = |element: &Element| {
+        js_sys::Reflect::get(element, &JsValue::from_str("nonce"))
+            .ok()
+            .and_then(|nonce| nonce.as_string())
+            .filter(|nonce| !nonce.is_empty())
+    };
+    if let Some(meta) = document
+        .query_selector("meta[property=csp-nonce]")
+        .unwrap_throw()
+    {
+        let content = meta
+            .get_attribute("content")
+            .filter(|nonce| !nonce.is_empty());
+        if let Some(nonce) = property(&meta).or(content) {
+            return Some(nonce);
+        }
+    }
+    let elements = document
+        .query_selector_all("script[nonce], style[nonce]")
+        .unwrap_throw();
+    (0..elements.length())
+        .filter_map(|index| elements.item(index))
+        .find_map(|node| property(node.unchecked_ref()))
+}
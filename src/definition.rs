@@ -0,0 +1,1225 @@
+use std::any::TypeId;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Document, FocusEvent, HtmlElement, Node};
+
+#[cfg(feature = "dev")]
+use crate::a11y;
+use crate::attributes::{self, AttributeTiming, AttributeType, DelayedAttributes, HostSetup};
+use crate::capabilities::capabilities_in;
+use crate::composition::{self, Child};
+#[cfg(feature = "devtools")]
+use crate::devtools;
+use crate::instance::Instance;
+use crate::instances::{self, Entry};
+use crate::internals::{self, ValidityReport};
+#[cfg(feature = "metrics")]
+use crate::metrics;
+#[cfg(feature = "router")]
+use crate::router;
+use crate::{
+    bundle, events, fallback, focus, locale, logging, media, styles, visibility, CustomElement,
+    CustomElementFactory,
+};
+
+/// An object-safe version of [CustomElement], containing only its per-instance callbacks, so that components can be
+/// stored and defined as `Box<dyn DynCustomElement>`, for example by plugin systems that discover components at runtime.
+/// See [define_boxed].
+///
+/// Every [CustomElement] is also a `DynCustomElement`. The class-level configuration that `CustomElement` provides
+/// through associated functions, like [observed_attributes](CustomElement::observed_attributes), is given to
+/// [define_boxed] as [ElementOptions] instead.
+///
+/// Each method has the same meaning as the [CustomElement] method of the same name.
+pub trait DynCustomElement: 'static {
+    fn inject_children(&mut self, _this: &HtmlElement) {}
+
+    fn try_inject_children(&mut self, this: &HtmlElement) -> Result<(), JsValue> {
+        self.inject_children(this);
+        Ok(())
+    }
+
+    fn error_callback(&mut self, _this: &HtmlElement, error: JsValue) {
+        logging::error("error rendering custom element", &error);
+    }
+
+    fn error_fallback(&mut self, _this: &HtmlElement) -> Option<Node> {
+        None
+    }
+
+    fn constructor(&mut self, _this: &HtmlElement) {}
+
+    fn connected_callback(&mut self, _this: &HtmlElement) {}
+
+    fn disconnected_callback(&mut self, _this: &HtmlElement) {}
+
+    fn moved_callback(&mut self, _this: &HtmlElement) {}
+
+    fn adopted_callback(
+        &mut self,
+        _this: &HtmlElement,
+        _old_document: Document,
+        _new_document: Document,
+    ) {
+    }
+
+    fn leave_animation(&mut self, _this: &HtmlElement) -> Option<js_sys::Promise> {
+        None
+    }
+
+    fn scheduled_update(&mut self, _this: &HtmlElement) {}
+
+    fn attribute_changed_callback(
+        &mut self,
+        _this: &HtmlElement,
+        _name: String,
+        _old_value: Option<String>,
+        _new_value: Option<String>,
+    ) {
+    }
+
+    fn bool_attribute_changed_callback(
+        &mut self,
+        _this: &HtmlElement,
+        _name: String,
+        _value: bool,
+    ) {
+    }
+
+    fn host_class_changed(&mut self, _this: &HtmlElement, _class: String) {}
+
+    fn host_style_changed(&mut self, _this: &HtmlElement, _style: String) {}
+
+    fn locale_changed_callback(&mut self, _this: &HtmlElement, _locale: String) {}
+
+    fn document_visibility_changed(&mut self, _this: &HtmlElement, _visible: bool) {}
+
+    fn focus_callback(&mut self, _this: &HtmlElement) {}
+
+    fn blur_callback(&mut self, _this: &HtmlElement) {}
+
+    fn media_query_changed_callback(
+        &mut self,
+        _this: &HtmlElement,
+        _query: String,
+        _matches: bool,
+    ) {
+    }
+
+    #[cfg(feature = "router")]
+    fn route_changed_callback(&mut self, _this: &HtmlElement, _url: String) {}
+
+    fn child_attached(&mut self, _this: &HtmlElement, _child: Child) {}
+
+    fn child_detached(&mut self, _this: &HtmlElement, _child: Child) {}
+
+    fn validate(&mut self) -> ValidityReport {
+        ValidityReport::valid()
+    }
+
+    fn inspect(&self) -> Option<String> {
+        None
+    }
+}
+
+// Implements every method of `DynCustomElement` by handing it to `$forward!`, a macro that is called with `self`, the
+// name of the method and its arguments, and returns the method's result. All the forwarding implementations use
+// this, so that a method added to the trait cannot be left out of one of them and silently fall back to the default.
+macro_rules! forward_dyn_custom_element {
+    ($self:ident, $forward:ident) => {
+        fn inject_children(&mut $self, this: &::web_sys::HtmlElement) {
+            $forward!($self, inject_children, this)
+        }
+
+        fn try_inject_children(
+            &mut $self,
+            this: &::web_sys::HtmlElement,
+        ) -> Result<(), ::wasm_bindgen::JsValue> {
+            $forward!($self, try_inject_children, this)
+        }
+
+        fn error_callback(
+            &mut $self,
+            this: &::web_sys::HtmlElement,
+            error: ::wasm_bindgen::JsValue,
+        ) {
+            $forward!($self, error_callback, this, error)
+        }
+
+        fn error_fallback(&mut $self, this: &::web_sys::HtmlElement) -> Option<::web_sys::Node> {
+            $forward!($self, error_fallback, this)
+        }
+
+        fn constructor(&mut $self, this: &::web_sys::HtmlElement) {
+            $forward!($self, constructor, this)
+        }
+
+        fn connected_callback(&mut $self, this: &::web_sys::HtmlElement) {
+            $forward!($self, connected_callback, this)
+        }
+
+        fn disconnected_callback(&mut $self, this: &::web_sys::HtmlElement) {
+            $forward!($self, disconnected_callback, this)
+        }
+
+        fn moved_callback(&mut $self, this: &::web_sys::HtmlElement) {
+            $forward!($self, moved_callback, this)
+        }
+
+        fn adopted_callback(
+            &mut $self,
+            this: &::web_sys::HtmlElement,
+            old_document: ::web_sys::Document,
+            new_document: ::web_sys::Document,
+        ) {
+            $forward!($self, adopted_callback, this, old_document, new_document)
+        }
+
+        fn leave_animation(&mut $self, this: &::web_sys::HtmlElement) -> Option<::js_sys::Promise> {
+            $forward!($self, leave_animation, this)
+        }
+
+        fn scheduled_update(&mut $self, this: &::web_sys::HtmlElement) {
+            $forward!($self, scheduled_update, this)
+        }
+
+        fn attribute_changed_callback(
+            &mut $self,
+            this: &::web_sys::HtmlElement,
+            name: String,
+            old_value: Option<String>,
+            new_value: Option<String>,
+        ) {
+            $forward!($self, attribute_changed_callback, this, name, old_value, new_value)
+        }
+
+        fn bool_attribute_changed_callback(
+            &mut $self,
+            this: &::web_sys::HtmlElement,
+            name: String,
+            value: bool,
+        ) {
+            $forward!($self, bool_attribute_changed_callback, this, name, value)
+        }
+
+        fn host_class_changed(&mut $self, this: &::web_sys::HtmlElement, class: String) {
+            $forward!($self, host_class_changed, this, class)
+        }
+
+        fn host_style_changed(&mut $self, this: &::web_sys::HtmlElement, style: String) {
+            $forward!($self, host_style_changed, this, style)
+        }
+
+        fn locale_changed_callback(&mut $self, this: &::web_sys::HtmlElement, locale: String) {
+            $forward!($self, locale_changed_callback, this, locale)
+        }
+
+        fn document_visibility_changed(&mut $self, this: &::web_sys::HtmlElement, visible: bool) {
+            $forward!($self, document_visibility_changed, this, visible)
+        }
+
+        fn focus_callback(&mut $self, this: &::web_sys::HtmlElement) {
+            $forward!($self, focus_callback, this)
+        }
+
+        fn blur_callback(&mut $self, this: &::web_sys::HtmlElement) {
+            $forward!($self, blur_callback, this)
+        }
+
+        fn media_query_changed_callback(
+            &mut $self,
+            this: &::web_sys::HtmlElement,
+            query: String,
+            matches: bool,
+        ) {
+            $forward!($self, media_query_changed_callback, this, query, matches)
+        }
+
+        #[cfg(feature = "router")]
+        fn route_changed_callback(&mut $self, this: &::web_sys::HtmlElement, url: String) {
+            $forward!($self, route_changed_callback, this, url)
+        }
+
+        fn child_attached(
+            &mut $self,
+            this: &::web_sys::HtmlElement,
+            child: $crate::composition::Child,
+        ) {
+            $forward!($self, child_attached, this, child)
+        }
+
+        fn child_detached(
+            &mut $self,
+            this: &::web_sys::HtmlElement,
+            child: $crate::composition::Child,
+        ) {
+            $forward!($self, child_detached, this, child)
+        }
+
+        fn validate(&mut $self) -> $crate::internals::ValidityReport {
+            $forward!($self, validate)
+        }
+
+        fn inspect(&$self) -> Option<String> {
+            $forward!($self, inspect)
+        }
+    };
+}
+
+pub(crate) use forward_dyn_custom_element;
+
+// Calls the `CustomElement` method of the same name, which the trait has to be named for, since `T` implements both.
+macro_rules! to_custom_element {
+    ($self:ident, $method:ident $(, $arg:ident)*) => {
+        CustomElement::$method($self $(, $arg)*)
+    };
+}
+
+impl<T: CustomElement> DynCustomElement for T {
+    forward_dyn_custom_element!(self, to_custom_element);
+}
+
+macro_rules! to_boxed {
+    ($self:ident, $method:ident $(, $arg:ident)*) => {
+        (**$self).$method($($arg),*)
+    };
+}
+
+impl DynCustomElement for Box<dyn DynCustomElement> {
+    forward_dyn_custom_element!(self, to_boxed);
+}
+
+/// The class-level configuration of a custom element, which [CustomElement] provides through associated functions.
+/// Each field has the same meaning as the [CustomElement] method of the same name.
+///
+/// The default options match the defaults of those methods.
+#[derive(Clone, Debug)]
+pub struct ElementOptions {
+    pub shadow: bool,
+    pub observed_attributes: Vec<String>,
+    pub observed_bool_attributes: Vec<String>,
+    pub observe_all_attributes: bool,
+    pub observed_media_queries: Vec<String>,
+    pub observe_host_class: bool,
+    pub observe_host_style: bool,
+    pub observe_locale: bool,
+    pub observe_visibility: bool,
+    pub observe_focus: bool,
+    #[cfg(feature = "router")]
+    pub observe_route: bool,
+    pub coalesce_moves: bool,
+    pub form_associated: bool,
+    pub disableable: bool,
+    pub lazy_init: bool,
+    pub coexistence: bundle::Coexistence,
+    pub events: Vec<events::EventDescriptor>,
+    pub host_setup: HostSetup,
+    pub disabled_features: Vec<String>,
+    pub class_statics: Vec<(String, JsValue)>,
+    pub attribute_schema: Vec<(String, AttributeType)>,
+    pub attribute_timing: Vec<(String, AttributeTiming)>,
+    /// The tag name of the built-in element this element extends, if any (see [CustomElement::superclass]).
+    pub superclass_tag: Option<String>,
+    /// The constructor of the class this element extends (see [CustomElement::superclass]).
+    pub superclass: js_sys::Function,
+}
+
+impl Default for ElementOptions {
+    #[allow(deprecated)]
+    fn default() -> Self {
+        Self {
+            shadow: true,
+            observed_attributes: Vec::new(),
+            observed_bool_attributes: Vec::new(),
+            observe_all_attributes: false,
+            observed_media_queries: Vec::new(),
+            observe_host_class: false,
+            observe_host_style: false,
+            observe_locale: false,
+            observe_visibility: false,
+            observe_focus: false,
+            #[cfg(feature = "router")]
+            observe_route: false,
+            coalesce_moves: false,
+            form_associated: false,
+            disableable: false,
+            lazy_init: false,
+            coexistence: bundle::Coexistence::ErrorIfDefined,
+            events: Vec::new(),
+            host_setup: HostSetup::new(),
+            disabled_features: Vec::new(),
+            class_statics: Vec::new(),
+            attribute_schema: Vec::new(),
+            attribute_timing: Vec::new(),
+            superclass_tag: None,
+            superclass: crate::HtmlElementConstructor.clone(),
+        }
+    }
+}
+
+impl ElementOptions {
+    /// The options given by a [CustomElement] implementation.
+    pub fn of<T: CustomElement>() -> Self {
+        let strings = |strs: &[&str]| strs.iter().map(|s| s.to_string()).collect();
+        let (superclass_tag, superclass) = T::superclass();
+        Self {
+            shadow: T::shadow(),
+            observed_attributes: strings(T::observed_attributes()),
+            observed_bool_attributes: strings(T::observed_bool_attributes()),
+            observe_all_attributes: T::observe_all_attributes(),
+            observed_media_queries: strings(T::observed_media_queries()),
+            observe_host_class: T::observe_host_class(),
+            observe_host_style: T::observe_host_style(),
+            observe_locale: T::observe_locale(),
+            observe_visibility: T::observe_visibility(),
+            observe_focus: T::observe_focus(),
+            #[cfg(feature = "router")]
+            observe_route: T::observe_route(),
+            coalesce_moves: T::coalesce_moves(),
+            form_associated: T::form_associated(),
+            disableable: T::disableable(),
+            lazy_init: T::lazy_init(),
+            coexistence: T::coexistence(),
+            events: T::events().to_vec(),
+            host_setup: T::host_setup(),
+            disabled_features: strings(T::disabled_features()),
+            class_statics: T::class_statics()
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+            attribute_schema: T::attribute_schema()
+                .iter()
+                .map(|(name, ty)| (name.to_string(), *ty))
+                .collect(),
+            attribute_timing: T::attribute_timing()
+                .iter()
+                .map(|(name, timing)| (name.to_string(), *timing))
+                .collect(),
+            superclass_tag: superclass_tag.map(String::from),
+            superclass: superclass.clone(),
+        }
+    }
+}
+
+/// Defines a custom element whose instances are created at runtime by `factory`, rather than being of a type known
+/// at compile time.
+///
+/// ```rust,ignore
+/// let plugin = registry.load("chart");
+/// define_boxed("plugin-chart", plugin.options(), Box::new(move || plugin.create()));
+/// ```
+pub fn define_boxed(
+    tag_name: &str,
+    options: ElementOptions,
+    factory: Box<dyn Fn() -> Box<dyn DynCustomElement>>,
+) {
+    define_element(&js_sys::global(), tag_name, options, factory);
+}
+
+// The class to extend in `global`. A class can only extend a constructor from its own window, so built-in
+// superclasses like `HTMLElement` are replaced by the class of the same name from `global`. Where `globalThis` has
+// no `HTMLElement` at all, as in Node with a DOM implementation passed to `define_in`, the default superclass is
+// missing too, and `global`'s `HTMLElement` is used.
+fn superclass_in(global: &JsValue, superclass: &js_sys::Function) -> js_sys::Function {
+    let name = if superclass.is_function() {
+        superclass.name()
+    } else {
+        js_sys::JsString::from("HTMLElement")
+    };
+    let is_builtin = !superclass.is_function()
+        || js_sys::Reflect::get(&js_sys::global(), &name)
+            .map(|builtin| builtin == JsValue::from(superclass))
+            .unwrap_or(false);
+    if !is_builtin {
+        return superclass.clone();
+    }
+    js_sys::Reflect::get(global, &name)
+        .ok()
+        .and_then(|constructor| constructor.dyn_into().ok())
+        .unwrap_or_else(|| superclass.clone())
+}
+
+thread_local! {
+    // the classes generated for each `CustomElement` type, by `define` and `define_with_factory`,
+    // with the window each was defined in, in the order they were defined
+    static CONSTRUCTORS: RefCell<HashMap<TypeId, Vec<(JsValue, js_sys::Function)>>> =
+        RefCell::new(HashMap::new());
+}
+
+pub(crate) fn register_constructor<T: 'static>(global: &JsValue, constructor: js_sys::Function) {
+    CONSTRUCTORS.with(|constructors| {
+        constructors
+            .borrow_mut()
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push((global.clone(), constructor))
+    });
+}
+
+// The first class defined for `T`, in any window.
+pub(crate) fn registered_constructor<T: 'static>() -> Option<js_sys::Function> {
+    CONSTRUCTORS.with(|constructors| {
+        let constructors = constructors.borrow();
+        let (_, constructor) = constructors.get(&TypeId::of::<T>())?.first()?;
+        Some(constructor.clone())
+    })
+}
+
+// The class defined for `T` in `global`, if any.
+pub(crate) fn registered_constructor_in<T: 'static>(global: &JsValue) -> Option<js_sys::Function> {
+    CONSTRUCTORS.with(|constructors| {
+        constructors
+            .borrow()
+            .get(&TypeId::of::<T>())?
+            .iter()
+            .find(|(window, _)| window == global)
+            .map(|(_, constructor)| constructor.clone())
+    })
+}
+
+// Whether `value` is an instance of a class defined for `T`, in any window.
+pub(crate) fn is_instance<T: 'static>(value: &JsValue) -> bool {
+    let object = match value.dyn_ref::<js_sys::Object>() {
+        Some(object) => object,
+        None => return false,
+    };
+    CONSTRUCTORS.with(|constructors| {
+        constructors
+            .borrow()
+            .get(&TypeId::of::<T>())
+            .map(|constructors| {
+                constructors.iter().any(|(_, constructor)| {
+                    js_sys::Reflect::get(constructor, &JsValue::from_str("prototype"))
+                        .ok()
+                        .and_then(|prototype| prototype.dyn_into::<js_sys::Object>().ok())
+                        .map(|prototype| prototype.is_prototype_of(object))
+                        .unwrap_or(false)
+                })
+            })
+            .unwrap_or(false)
+    })
+}
+
+// Defines `tag_name` in the custom element registry of `global`, a window or other global object, as a custom element whose instances are
+// each backed by a component created by `factory`, and returns the generated class.
+pub(crate) fn define_element<C: DynCustomElement>(
+    global: &JsValue,
+    tag_name: &str,
+    options: ElementOptions,
+    factory: impl Fn() -> C + 'static,
+) -> js_sys::Function {
+    let registry = fallback::registry(global, tag_name);
+    if let Some(existing) = bundle::reuse(&registry, tag_name, options.coexistence) {
+        return existing;
+    }
+    let definition = Definition::new(global, tag_name, options, Box::new(factory));
+    let options = definition.options.clone();
+
+    // constructor function will be called for each new instance of the component
+    let constructor = Closure::wrap(
+        Box::new(move |this: HtmlElement| construct(this, &definition))
+            as Box<dyn FnMut(HtmlElement) -> u32>,
+    );
+
+    // call out to JS to define the Custom Element
+    let class = make_custom_element(
+        &registry,
+        &class_definition(global, tag_name, &options, constructor.into_js_value()),
+    );
+    defined(global, tag_name, &class);
+    class
+}
+
+/// An element to define with [define_batch]: its tag name, and the component behind it.
+#[derive(Clone)]
+pub struct ElementSpec {
+    tag_name: String,
+    options: ElementOptions,
+    factory: Rc<dyn Fn() -> Box<dyn DynCustomElement>>,
+    register: Option<fn(&JsValue, js_sys::Function)>,
+}
+
+impl ElementSpec {
+    /// The element `tag_name`, backed by the component `T`, as [define](CustomElement::define) would define it.
+    pub fn new<T: CustomElement + CustomElementFactory>(tag_name: &str) -> Self {
+        Self {
+            tag_name: tag_name.to_string(),
+            options: ElementOptions::of::<T>(),
+            factory: Rc::new(|| Box::new(T::create())),
+            register: Some(register_constructor::<T>),
+        }
+    }
+
+    /// The element `tag_name`, whose instances are created at runtime by `factory`, as [define_boxed] would define
+    /// it.
+    pub fn boxed(
+        tag_name: &str,
+        options: ElementOptions,
+        factory: Box<dyn Fn() -> Box<dyn DynCustomElement>>,
+    ) -> Self {
+        Self {
+            tag_name: tag_name.to_string(),
+            options,
+            factory: factory.into(),
+            register: None,
+        }
+    }
+
+    /// The tag name the element is defined with.
+    pub fn tag_name(&self) -> &str {
+        &self.tag_name
+    }
+
+    // Records the class the element was defined with, so that `create_element` can use it.
+    fn registered(&self, global: &JsValue, class: js_sys::Function) {
+        if let Some(register) = self.register {
+            register(global, class);
+        }
+    }
+}
+
+/// Defines each of `specs`, like calling [define](CustomElement::define) or [define_boxed] for each of them, but with a
+/// single call into JavaScript for the whole batch, and a single constructor function shared by its elements, which
+/// makes defining a large component library faster. Each element is defined in the registry it would be defined in
+/// on its own, with one call for each registry if they differ.
+///
+/// ```rust,ignore
+/// #[wasm_bindgen]
+/// pub fn define_elements() {
+///     define_batch(&[
+///         ElementSpec::new::<Button>("ui-button"),
+///         ElementSpec::new::<Dialog>("ui-dialog"),
+///         ElementSpec::new::<Tabs>("ui-tabs"),
+///     ]);
+/// }
+/// ```
+pub fn define_batch(specs: &[ElementSpec]) {
+    let global = js_sys::global();
+    // the specs to define, grouped by the registry each is defined in, which is usually the same for all of them
+    let mut groups: Vec<(JsValue, Vec<&ElementSpec>)> = Vec::new();
+    for spec in specs {
+        let registry = fallback::registry(&global, &spec.tag_name);
+        if let Some(existing) = bundle::reuse(&registry, &spec.tag_name, spec.options.coexistence) {
+            spec.registered(&global, existing);
+            continue;
+        }
+        match groups
+            .iter_mut()
+            .find(|(group_registry, _)| *group_registry == registry)
+        {
+            Some((_, group)) => group.push(spec),
+            None => groups.push((registry, vec![spec])),
+        }
+    }
+    if groups.is_empty() {
+        return;
+    }
+    let batch = groups
+        .iter()
+        .flat_map(|(_, group)| group.iter().copied())
+        .collect::<Vec<_>>();
+
+    let definitions = batch
+        .iter()
+        .map(|spec| {
+            let factory = spec.factory.clone();
+            Definition::new(
+                &global,
+                &spec.tag_name,
+                spec.options.clone(),
+                Box::new(move || factory()),
+            )
+        })
+        .collect::<Vec<_>>();
+    let options = definitions
+        .iter()
+        .map(|definition| definition.options.clone())
+        .collect::<Vec<_>>();
+
+    // one constructor function for the batch, which each class calls with its index
+    let constructor = Closure::wrap(Box::new(move |index: usize, this: HtmlElement| {
+        construct(this, &definitions[index])
+    }) as Box<dyn FnMut(usize, HtmlElement) -> u32>);
+    let constructor: js_sys::Function = constructor.into_js_value().unchecked_into();
+    let mut classes = batch
+        .iter()
+        .zip(&options)
+        .enumerate()
+        .map(|(index, (spec, options))| {
+            let setup = constructor.bind1(&JsValue::NULL, &JsValue::from(index));
+            class_definition(&global, &spec.tag_name, options, setup.into())
+        });
+
+    // one call into JavaScript for each registry
+    for (registry, group) in &groups {
+        let group_classes = classes
+            .by_ref()
+            .take(group.len())
+            .collect::<js_sys::Array>();
+        let defined_classes = make_custom_elements(registry, &group_classes);
+        for (spec, class) in group.iter().zip(defined_classes.iter()) {
+            let class: js_sys::Function = class.unchecked_into();
+            defined(&global, &spec.tag_name, &class);
+            spec.registered(&global, class);
+        }
+    }
+}
+
+// What each instance of a defined element is set up from.
+struct Definition<C> {
+    tag: Rc<str>,
+    options: Rc<ElementOptions>,
+    timing: Rc<[(String, AttributeTiming)]>,
+    #[cfg(feature = "dev")]
+    schema: Rc<[(String, AttributeType)]>,
+    factory: Box<dyn Fn() -> C>,
+}
+
+impl<C> Definition<C> {
+    fn new(
+        global: &JsValue,
+        tag_name: &str,
+        options: ElementOptions,
+        factory: Box<dyn Fn() -> C>,
+    ) -> Self {
+        debug_assert!(
+            !(options.shadow && options.disabled_features.iter().any(|f| f == "shadow")),
+            "{} disables the shadow feature but uses a shadow root",
+            tag_name
+        );
+        if options.superclass_tag.is_some() || options.form_associated {
+            let capabilities = capabilities_in(global);
+            // without a registry at all, the fallback policy decides what happens instead
+            if options.superclass_tag.is_some()
+                && capabilities.custom_elements
+                && !capabilities.customized_built_ins
+            {
+                logging::warn(&format!(
+                    "{} extends a built-in element, which this browser does not support, so it will not be upgraded ({})",
+                    tag_name, capabilities
+                ));
+            }
+            if options.form_associated && !capabilities.element_internals {
+                logging::warn(&format!(
+                    "{} is form-associated, but this browser has no ElementInternals, so it will not take part in forms ({})",
+                    tag_name, capabilities
+                ));
+            }
+        }
+        let mut options = options;
+        if options.disableable
+            && !options
+                .observed_bool_attributes
+                .iter()
+                .any(|a| a == "disabled")
+        {
+            options
+                .observed_bool_attributes
+                .push("disabled".to_string());
+        }
+        Self {
+            tag: tag_name.into(),
+            timing: options.attribute_timing.clone().into(),
+            #[cfg(feature = "dev")]
+            schema: options.attribute_schema.clone().into(),
+            options: Rc::new(options),
+            factory,
+        }
+    }
+}
+
+// Creates the component for a newly constructed element, and the handlers for the callbacks its class calls, and
+// returns the element's handle.
+fn construct<C: DynCustomElement>(this: HtmlElement, definition: &Definition<C>) -> u32 {
+    let Definition {
+        tag,
+        options: opts,
+        timing,
+        factory,
+        ..
+    } = definition;
+    #[cfg(feature = "dev")]
+    let schema = &definition.schema;
+    let component = Rc::new(Instance::new(tag.clone(), factory()));
+    let mut handlers = Entry::new(tag.clone());
+    handlers.on_reset({
+        let cmp = component.clone();
+        move || cmp.reset()
+    });
+    if opts.disableable {
+        attributes::block_events_when_disabled(&this);
+    }
+
+    // constructor
+    let cmp = component.clone();
+    handlers.on("constructor", move |el, _| {
+        cmp.with("constructor", move |component| component.constructor(&el));
+        JsValue::UNDEFINED
+    });
+
+    // inject_children
+    let cmp = component.clone();
+    handlers.on("injectChildren", move |el, _| {
+        cmp.with("inject_children", move |component| {
+            match component.try_inject_children(&el) {
+                #[cfg(feature = "dev")]
+                Ok(()) => a11y::audit(&el, &crate::defined_tag_name(&el)),
+                #[cfg(not(feature = "dev"))]
+                Ok(()) => {}
+                Err(error) => {
+                    events::report_error(&el, "inject_children", &error);
+                    component.error_callback(&el, error);
+                    if let Some(fallback) = component.error_fallback(&el) {
+                        el.set_text_content(None);
+                        el.append_child(&fallback).unwrap_throw();
+                    }
+                }
+            }
+        });
+        JsValue::UNDEFINED
+    });
+
+    // connectedCallback
+    let cmp = component.clone();
+    let options = opts.clone();
+    #[cfg(feature = "dev")]
+    let (tag, schema) = (tag.clone(), schema.clone());
+    let set_up = Cell::new(false);
+    handlers.on("connectedCallback", move |el, _| {
+        if !set_up.replace(true) {
+            options.host_setup.apply(&el);
+        }
+
+        #[cfg(feature = "dev")]
+        attributes::check_schema(&el, &tag, &schema);
+
+        let form_associated = options.form_associated;
+        cmp.with("connected_callback", {
+            let el = el.clone();
+            move |component| {
+                component.connected_callback(&el);
+                if form_associated {
+                    internals::apply_report(&el, component.validate());
+                }
+            }
+        });
+
+        if options.observe_locale {
+            locale::subscribe(&el);
+        }
+        if options.observe_visibility {
+            visibility::subscribe(&el);
+        }
+        media::subscribe(&el, &options.observed_media_queries);
+
+        #[cfg(feature = "router")]
+        if options.observe_route {
+            router::subscribe(&el);
+        }
+
+        let host_attributes = [
+            ("class", options.observe_host_class),
+            ("style", options.observe_host_style),
+        ]
+        .iter()
+        .filter(|(_, observed)| *observed)
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>();
+        if !host_attributes.is_empty() {
+            let cmp = cmp.clone();
+            attributes::observe_host(&el, &host_attributes, move |el, name, value| {
+                let el = el.clone();
+                if name == "class" {
+                    cmp.with("host_class_changed", move |component| {
+                        component.host_class_changed(&el, value)
+                    });
+                } else {
+                    cmp.with("host_style_changed", move |component| {
+                        component.host_style_changed(&el, value)
+                    });
+                }
+            });
+        }
+        JsValue::UNDEFINED
+    });
+
+    // attribute changes, which are delivered through `attributes` so that they can be debounced or throttled
+    let cmp = component.clone();
+    let options = opts.clone();
+    let attributes = DelayedAttributes::new(
+        timing.clone(),
+        move |el: &HtmlElement, name, old_value: Option<String>, new_value: Option<String>| {
+            let el = el.clone();
+            let options = options.clone();
+            cmp.with("attribute_changed_callback", move |component| {
+                attributes::update_cells(&el, &name, new_value.as_deref());
+                if options.disableable && name == "disabled" {
+                    attributes::apply_disabled(&el, new_value.is_some());
+                }
+                let is_bool = options.observed_bool_attributes.iter().any(|attr| {
+                    match attr.strip_suffix('*') {
+                        Some(prefix) => name.starts_with(prefix),
+                        None => *attr == name,
+                    }
+                });
+                if is_bool {
+                    if old_value.is_some() != new_value.is_some() {
+                        component.bool_attribute_changed_callback(&el, name, new_value.is_some());
+                    }
+                } else {
+                    component.attribute_changed_callback(&el, name, old_value, new_value);
+                }
+                if options.form_associated {
+                    internals::apply_report(&el, component.validate());
+                }
+            });
+        },
+    );
+
+    // disconnectedCallback
+    let cmp = component.clone();
+    let options = opts.clone();
+    let pending_attributes = attributes.clone();
+    handlers.on("disconnectedCallback", move |el, _| {
+        // the element should not be left with stale attributes if it is reconnected
+        pending_attributes.flush();
+
+        if options.observe_locale {
+            locale::unsubscribe(&el);
+        }
+        if options.observe_visibility {
+            visibility::unsubscribe(&el);
+        }
+        media::unsubscribe(&el, &options.observed_media_queries);
+        #[cfg(feature = "router")]
+        if options.observe_route {
+            router::unsubscribe(&el);
+        }
+
+        cmp.with("disconnected_callback", move |component| {
+            component.disconnected_callback(&el)
+        });
+        JsValue::UNDEFINED
+    });
+
+    // movedCallback
+    if opts.coalesce_moves {
+        let cmp = component.clone();
+        handlers.on("movedCallback", move |el, _| {
+            cmp.with("moved_callback", move |component| {
+                component.moved_callback(&el)
+            });
+            JsValue::UNDEFINED
+        });
+    }
+
+    // adoptedCallback
+    let cmp = component.clone();
+    handlers.on("adoptedCallback", move |el, args| {
+        let (old_document, new_document): (Document, Document) =
+            (args.get(1).unchecked_into(), args.get(2).unchecked_into());
+        cmp.with("adopted_callback", move |component| {
+            component.adopted_callback(&el, old_document, new_document)
+        });
+        JsValue::UNDEFINED
+    });
+
+    // leaveAnimation, which resolves with the component's animation, once the component is free to start it
+    let cmp = component.clone();
+    handlers.on("leaveAnimation", move |el, _| {
+        let cmp = cmp.clone();
+        js_sys::Promise::new(&mut move |resolve, _reject| {
+            let el = el.clone();
+            cmp.with("leave_animation", move |component| {
+                let animation = component.leave_animation(&el);
+                resolve
+                    .call1(&JsValue::NULL, &JsValue::from(animation))
+                    .unwrap_throw();
+            });
+        })
+        .into()
+    });
+
+    // localeChangedCallback
+    if opts.observe_locale {
+        let cmp = component.clone();
+        handlers.on("localeChangedCallback", move |el, args| {
+            let locale = args.get(1).as_string().unwrap_or_default();
+            cmp.with("locale_changed_callback", move |component| {
+                component.locale_changed_callback(&el, locale)
+            });
+            JsValue::UNDEFINED
+        });
+    }
+
+    // scheduledUpdate, run by `render::request_update`
+    let cmp = component.clone();
+    handlers.on("scheduledUpdate", move |el, _| {
+        cmp.with("scheduled_update", move |component| {
+            component.scheduled_update(&el)
+        });
+        JsValue::UNDEFINED
+    });
+
+    // visibilityChangedCallback
+    if opts.observe_visibility {
+        let cmp = component.clone();
+        handlers.on("visibilityChangedCallback", move |el, args| {
+            let visible = args.get(1).is_truthy();
+            cmp.with("document_visibility_changed", move |component| {
+                component.document_visibility_changed(&el, visible)
+            });
+            JsValue::UNDEFINED
+        });
+    }
+
+    // focusCallback and blurCallback, for focus entering or leaving the element as a whole; `focusin` and
+    // `focusout` are composed, so the host also hears about focus moving inside its shadow root
+    if opts.observe_focus {
+        for (event_type, focused) in [("focusin", true), ("focusout", false)].iter().copied() {
+            let cmp = component.clone();
+            let listener = Closure::wrap(Box::new(move |event: FocusEvent| {
+                let el: HtmlElement = event.current_target().unwrap_throw().unchecked_into();
+                let from_inside = event
+                    .related_target()
+                    .and_then(|related| related.dyn_into::<Node>().ok())
+                    .map(|related| focus::contains(&el, &related))
+                    .unwrap_or(false);
+                if from_inside {
+                    return;
+                }
+                if focused {
+                    cmp.with("focus_callback", move |component| {
+                        component.focus_callback(&el)
+                    });
+                } else {
+                    cmp.with("blur_callback", move |component| {
+                        component.blur_callback(&el)
+                    });
+                }
+            }) as Box<dyn FnMut(FocusEvent)>);
+            this.add_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref())
+                .unwrap_throw();
+            handlers.keep(listener);
+        }
+    }
+
+    // mediaQueryChangedCallback
+    if !opts.observed_media_queries.is_empty() {
+        let cmp = component.clone();
+        handlers.on("mediaQueryChangedCallback", move |el, args| {
+            let query = args.get(1).as_string().unwrap_or_default();
+            let matches = args.get(2).is_truthy();
+            cmp.with("media_query_changed_callback", move |component| {
+                component.media_query_changed_callback(&el, query, matches)
+            });
+            JsValue::UNDEFINED
+        });
+    }
+
+    // routeChangedCallback
+    #[cfg(feature = "router")]
+    if opts.observe_route {
+        let cmp = component.clone();
+        handlers.on("routeChangedCallback", move |el, args| {
+            let url = args.get(1).as_string().unwrap_or_default();
+            cmp.with("route_changed_callback", move |component| {
+                component.route_changed_callback(&el, url)
+            });
+            JsValue::UNDEFINED
+        });
+    }
+
+    // childCallback, run by `composition::register_with_parent` when a child registers with this element, and
+    // when it is disconnected
+    let cmp = component.clone();
+    handlers.on("childCallback", move |el, args| {
+        let child = composition::child(args.get(1).unchecked_into());
+        if args.get(2).is_truthy() {
+            cmp.with("child_attached", move |component| {
+                component.child_attached(&el, child)
+            });
+        } else {
+            cmp.with("child_detached", move |component| {
+                component.child_detached(&el, child)
+            });
+        }
+        JsValue::UNDEFINED
+    });
+
+    // validate
+    if opts.form_associated {
+        let cmp = component.clone();
+        handlers.on("validate", move |el, _| {
+            cmp.with("validate", move |component| {
+                internals::apply_report(&el, component.validate())
+            });
+            JsValue::UNDEFINED
+        });
+    }
+
+    // debug, which reports the element's recent attribute changes in development builds
+    #[cfg(feature = "dev")]
+    let journal = attributes::AttributeJournal::default();
+    #[cfg(feature = "dev")]
+    {
+        let journal = journal.clone();
+        handlers.on("debug", move |el, _| {
+            journal.report(&el, &crate::defined_tag_name(&el))
+        });
+    }
+
+    // attributeChangedCallback
+    handlers.on("attributeChangedCallback", move |el, args| {
+        let name = args.get(1).as_string().unwrap_or_default();
+        let (old_value, new_value) = (args.get(2).as_string(), args.get(3).as_string());
+        #[cfg(feature = "dev")]
+        journal.record(&name, old_value.as_deref(), new_value.as_deref());
+        attributes.change(&el, name, old_value, new_value);
+        JsValue::UNDEFINED
+    });
+
+    // inspect, and the element's place in the devtools' count
+    #[cfg(feature = "devtools")]
+    {
+        let cmp = component.clone();
+        handlers.on("inspect", move |_el, _| {
+            let snapshot = Rc::new(RefCell::new(None));
+            let output = snapshot.clone();
+            cmp.with("inspect", move |component| {
+                *output.borrow_mut() = component.inspect()
+            });
+            let snapshot = snapshot.borrow_mut().take();
+            JsValue::from(snapshot)
+        });
+        handlers.keep(devtools::track(&crate::defined_tag_name(&this)));
+    }
+
+    #[cfg(feature = "metrics")]
+    handlers.keep(metrics::track(&crate::defined_tag_name(&this)));
+
+    let handle = instances::insert(&this, handlers);
+
+    // __ceDebug, bound to the element in JavaScript, since a Rust reference to it would keep it from being collected
+    #[cfg(feature = "dev")]
+    {
+        let debug = js_sys::Reflect::get(&this, &JsValue::from_str("_dispatch"))
+            .unwrap_throw()
+            .unchecked_ref::<js_sys::Function>()
+            .bind2(&this, &JsValue::from_str("debug"), &this);
+        js_sys::Reflect::set(&this, &JsValue::from_str("__ceDebug"), &debug).unwrap_throw();
+    }
+    handle
+}
+
+// The definition of an element's class that the shim takes, with `constructor` called for each new instance.
+fn class_definition(
+    global: &JsValue,
+    tag_name: &str,
+    options: &ElementOptions,
+    constructor: JsValue,
+) -> js_sys::Object {
+    // observedAttributes is static and needs to be known when the class is defined; wildcards like `data-*` are
+    // passed separately, as prefixes, and observed by the shim
+    let (wildcards, observed): (Vec<&String>, Vec<&String>) = options
+        .observed_attributes
+        .iter()
+        .chain(&options.observed_bool_attributes)
+        .partition(|attr| attr.ends_with('*'));
+    let observed_attributes = JsValue::from(
+        observed
+            .into_iter()
+            .map(|attr| JsValue::from_str(attr))
+            .collect::<js_sys::Array>(),
+    );
+    let wildcards = wildcards
+        .into_iter()
+        .map(|attr| JsValue::from_str(attr.trim_end_matches('*')))
+        .collect::<js_sys::Array>();
+    if options.observe_all_attributes {
+        wildcards.push(&JsValue::from_str(""));
+    }
+
+    let statics = js_sys::Object::new();
+    if options.form_associated {
+        js_sys::Reflect::set(
+            &statics,
+            &JsValue::from_str("formAssociated"),
+            &JsValue::TRUE,
+        )
+        .unwrap_throw();
+    }
+    if !options.disabled_features.is_empty() {
+        let disabled_features = options
+            .disabled_features
+            .iter()
+            .map(|feature| JsValue::from_str(feature))
+            .collect::<js_sys::Array>();
+        js_sys::Reflect::set(
+            &statics,
+            &JsValue::from_str("disabledFeatures"),
+            &disabled_features,
+        )
+        .unwrap_throw();
+    }
+    for (name, value) in &options.class_statics {
+        js_sys::Reflect::set(&statics, &JsValue::from_str(name), value).unwrap_throw();
+    }
+
+    let event_names = options
+        .events
+        .iter()
+        .map(|event| JsValue::from_str(event.name))
+        .collect::<js_sys::Array>();
+
+    let definition = js_sys::Object::new();
+    let fields: [(&str, JsValue); 12] = [
+        (
+            "superclass",
+            superclass_in(global, &options.superclass).into(),
+        ),
+        ("tagName", JsValue::from_str(tag_name)),
+        ("shadow", JsValue::from_bool(options.shadow)),
+        ("setup", constructor),
+        ("dispatch", instances::dispatcher()),
+        ("observedAttributes", observed_attributes),
+        ("wildcards", wildcards.into()),
+        (
+            "superclassTag",
+            options
+                .superclass_tag
+                .as_deref()
+                .map_or(JsValue::UNDEFINED, JsValue::from_str),
+        ),
+        ("coalesceMoves", JsValue::from_bool(options.coalesce_moves)),
+        ("statics", statics.into()),
+        ("eventNames", event_names.into()),
+        ("lazyInit", JsValue::from_bool(options.lazy_init)),
+    ];
+    for (name, value) in fields.iter() {
+        js_sys::Reflect::set(&definition, &JsValue::from_str(name), value).unwrap_throw();
+    }
+    definition
+}
+
+// Records an element the crate has defined in `global`.
+fn defined(global: &JsValue, tag_name: &str, class: &js_sys::Function) {
+    bundle::claim(class);
+    styles::defined(tag_name, global);
+    #[cfg(feature = "devtools")]
+    devtools::defined(tag_name);
+    #[cfg(feature = "metrics")]
+    metrics::defined(tag_name);
+}
+
+// JavaScript shim
+#[wasm_bindgen(module = "/src/make_custom_element.js")]
+extern "C" {
+    fn make_custom_element(registry: &JsValue, definition: &js_sys::Object) -> js_sys::Function;
+
+    fn make_custom_elements(registry: &JsValue, definitions: &js_sys::Array) -> js_sys::Array;
+}
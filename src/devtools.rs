@@ -0,0 +1,87 @@
+//! A hook for inspecting the page's custom elements from the browser console or a browser extension. Requires the
+//! `devtools` feature.
+//!
+//! Once an element has been defined, `window.__RUST_CUSTOM_ELEMENTS__` has two methods:
+//!
+//! - `elements()` returns an array of `{ tag, instances }`, with each tag name defined with this crate and how many
+//!   of its elements currently exist. Elements are counted until they have been garbage collected, so detached
+//!   elements still count for a while.
+//! - `inspect(el)` returns a snapshot of the state of the element's component, as returned by its
+//!   [inspect](crate::CustomElement::inspect) method, or `undefined` if it has none.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlElement;
+
+use crate::instances::dispatch;
+
+thread_local! {
+    // each defined tag name, with the number of its elements that exist
+    static TAGS: RefCell<Vec<(String, Rc<Cell<usize>>)>> = const { RefCell::new(Vec::new()) };
+}
+
+// Lists a newly defined tag name, installing the hook the first time.
+pub(crate) fn defined(tag_name: &str) {
+    let first = TAGS.with(|tags| {
+        let mut tags = tags.borrow_mut();
+        tags.push((tag_name.to_string(), Rc::new(Cell::new(0))));
+        tags.len() == 1
+    });
+    if first {
+        install();
+    }
+}
+
+// Counts an element of the given tag name for as long as the returned value is kept, which is as long as the
+// element's component exists.
+pub(crate) fn track(tag_name: &str) -> Tracked {
+    let count = TAGS.with(|tags| {
+        tags.borrow()
+            .iter()
+            .rev()
+            .find(|(tag, _)| tag == tag_name)
+            .map(|(_, count)| count.clone())
+            .unwrap_or_default()
+    });
+    count.set(count.get() + 1);
+    Tracked(count)
+}
+
+pub(crate) struct Tracked(Rc<Cell<usize>>);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
+fn install() {
+    let elements = Closure::wrap(Box::new(|| {
+        TAGS.with(|tags| {
+            tags.borrow()
+                .iter()
+                .map(|(tag, count)| {
+                    let entry = js_sys::Object::new();
+                    js_sys::Reflect::set(&entry, &"tag".into(), &JsValue::from_str(tag))
+                        .unwrap_throw();
+                    js_sys::Reflect::set(&entry, &"instances".into(), &(count.get() as u32).into())
+                        .unwrap_throw();
+                    JsValue::from(entry)
+                })
+                .collect::<js_sys::Array>()
+        })
+    }) as Box<dyn FnMut() -> js_sys::Array>);
+    let inspect = Closure::wrap(Box::new(|el: JsValue| match el.dyn_ref::<HtmlElement>() {
+        Some(el) => dispatch(el, "inspect", &[]).unwrap_or(JsValue::UNDEFINED),
+        None => JsValue::UNDEFINED,
+    }) as Box<dyn FnMut(JsValue) -> JsValue>);
+
+    let hook = js_sys::Object::new();
+    js_sys::Reflect::set(&hook, &"elements".into(), &elements.into_js_value()).unwrap_throw();
+    js_sys::Reflect::set(&hook, &"inspect".into(), &inspect.into_js_value()).unwrap_throw();
+    js_sys::Reflect::set(&js_sys::global(), &"__RUST_CUSTOM_ELEMENTS__".into(), &hook)
+        .unwrap_throw();
+}
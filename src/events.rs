@@ -0,0 +1,265 @@
+//! Helpers for events that originate inside a custom element's shadow tree.
+//!
+//! Many events (like `change`) are not `composed`, so they never escape the shadow root, and those that do
+//! escape are retargeted to the host, losing any information about which internal element they came from.
+//! These helpers re-dispatch such events from the host element as `composed` events, and map their internal
+//! targets to the element's [exported parts](https://developer.mozilla.org/en-US/docs/Web/CSS/::part).
+//!
+//! Components can also declare the events they fire with [events](crate::CustomElement::events), which makes their
+//! contract explicit: [manifest] describes them for documentation or typings, and [emit_declared] fires them.
+//!
+//! When a component fails, the crate fires a `ce-error` event from its element, which bubbles and is composed, so
+//! that page-level error monitoring can pick up failures from every component in one listener:
+//!
+//! ```js
+//! document.addEventListener("ce-error", (event) => {
+//!   const { tag, phase, message, error } = event.detail;
+//!   Sentry.captureException(error, { tags: { tag, phase } });
+//! });
+//! ```
+//!
+//! `phase` is the callback that failed, like `"connected_callback"`. This covers exceptions thrown and panics in the
+//! lifecycle callbacks, errors returned by [try_inject_children](crate::CustomElement::try_inject_children), and
+//! content that `suspense::inject_async` failed to load. Components can report their own failures with [report_error].
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{CustomEvent, CustomEventInit, Element, Event, EventTarget, HtmlElement};
+
+use crate::scope::Scope;
+use crate::{host, CustomElement};
+
+/// Returns the value of the `part` attribute of the innermost element in the event's path
+/// that has one, stopping at the host element. This is the part a consumer could have styled
+/// with `my-component::part(...)`.
+///
+/// `this` may be either the custom element or its shadow root.
+pub fn exported_part(this: &HtmlElement, event: &Event) -> Option<String> {
+    let host = host(this);
+    for target in event.composed_path().iter() {
+        if target == JsValue::from(&host) {
+            break;
+        }
+        if let Some(part) = target
+            .dyn_ref::<Element>()
+            .and_then(|el| el.get_attribute("part"))
+        {
+            return Some(part);
+        }
+    }
+    None
+}
+
+/// Re-dispatches an event from the host element as a `composed` [CustomEvent] of the same type,
+/// and stops the propagation of the original event.
+///
+/// The new event keeps the `bubbles` and `cancelable` flags and, if the original was a [CustomEvent],
+/// its `detail`. If the original target was inside an exported part, the new event has a `part` property
+/// with the name of that part (see [exported_part]). If the new event is canceled, the original is too.
+///
+/// Returns `false` if the new event was canceled, as `dispatchEvent` does.
+pub fn redispatch(this: &HtmlElement, event: &Event) -> bool {
+    let host = host(this);
+
+    let init = CustomEventInit::new();
+    init.set_bubbles(event.bubbles());
+    init.set_cancelable(event.cancelable());
+    init.set_composed(true);
+    if let Some(custom_event) = event.dyn_ref::<CustomEvent>() {
+        init.set_detail(&custom_event.detail());
+    }
+    let retargeted = CustomEvent::new_with_event_init_dict(&event.type_(), &init).unwrap_throw();
+    if let Some(part) = exported_part(&host, event) {
+        js_sys::Reflect::set(
+            &retargeted,
+            &JsValue::from_str("part"),
+            &JsValue::from_str(&part),
+        )
+        .unwrap_throw();
+    }
+
+    event.stop_propagation();
+    let not_canceled = host.dispatch_event(&retargeted).unwrap_throw();
+    if !not_canceled {
+        event.prevent_default();
+    }
+    not_canceled
+}
+
+/// Listens for each of the given event types on the element's shadow root, and [redispatches](redispatch)
+/// them from the host element, so that consumers listening on the custom element see them.
+///
+/// The shadow root must already be attached, so this is typically called from
+/// [inject_children](crate::CustomElement::inject_children). It does nothing if the element has no shadow root.
+/// Every element shares one listener, which goes away with the shadow root, and adding it again for the same event
+/// type does nothing, so calling this from [connected_callback](crate::CustomElement::connected_callback) is fine too.
+pub fn retarget_events(this: &HtmlElement, event_types: &[&str]) {
+    let shadow_root = match host(this).shadow_root() {
+        Some(shadow_root) => shadow_root,
+        None => return,
+    };
+    RETARGET.with(|listener| {
+        for event_type in event_types {
+            shadow_root
+                .add_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref())
+                .unwrap_throw();
+        }
+    });
+}
+
+thread_local! {
+    static RETARGET: Closure<dyn FnMut(Event)> = Closure::wrap(Box::new(|event: Event| {
+        // the shadow root, which `redispatch` maps to its host
+        let shadow_root: HtmlElement = event.current_target().unwrap_throw().unchecked_into();
+        redispatch(&shadow_root, &event);
+    }) as Box<dyn FnMut(Event)>);
+}
+
+/// Listens for each of the given event types on `inner`, an element inside the component, and
+/// [redispatches](redispatch) them from the host element, until the element is disconnected. This is the counterpart
+/// of [forward_attributes](crate::attributes::forward_attributes) for components that wrap a native element, and is
+/// usually called in [connected_callback](crate::CustomElement::connected_callback) along with it:
+///
+/// ```rust,ignore
+/// fn connected_callback(&mut self, this: &HtmlElement) {
+///     attributes::forward_attributes(this, &self.input, &["id"]);
+///     events::forward_events(&self.input, this, &["change", "input"]);
+/// }
+/// ```
+pub fn forward_events(inner: &EventTarget, this: &HtmlElement, event_types: &[&str]) {
+    let host = host(this);
+    let scope = Scope::new(&host);
+    for event_type in event_types {
+        let listener = Closure::wrap(Box::new({
+            let host = host.clone();
+            move |event: Event| {
+                redispatch(&host, &event);
+            }
+        }) as Box<dyn FnMut(Event)>);
+        inner
+            .add_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+        let (inner, event_type) = (inner.clone(), event_type.to_string());
+        scope.on_cleanup(move || {
+            inner
+                .remove_event_listener_with_callback(&event_type, listener.as_ref().unchecked_ref())
+                .unwrap_throw();
+        });
+    }
+}
+
+/// Describes an event that a component fires. See [events](crate::CustomElement::events).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventDescriptor {
+    /// The event type, like `"change"`.
+    pub name: &'static str,
+    /// What the event means, for documentation.
+    pub description: &'static str,
+    /// The type of the event's `detail`, for documentation or typings, like `"{ value: string }"`.
+    pub detail: &'static str,
+    pub bubbles: bool,
+    pub composed: bool,
+    pub cancelable: bool,
+}
+
+impl EventDescriptor {
+    /// An event that bubbles and is composed, so that it reaches listeners outside the shadow root, and that
+    /// cannot be canceled. The other fields can be set with struct update syntax.
+    ///
+    /// ```rust,ignore
+    /// const SELECT: EventDescriptor = EventDescriptor {
+    ///     description: "An item was selected.",
+    ///     cancelable: true,
+    ///     ..EventDescriptor::new("select")
+    /// };
+    /// ```
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            description: "",
+            detail: "",
+            bubbles: true,
+            composed: true,
+            cancelable: false,
+        }
+    }
+}
+
+/// Fires the event declared in `T`'s [events](crate::CustomElement::events) with the given name from the host
+/// element, as a [CustomEvent] with the declared flags and the given `detail`. `event` can be a string, or an enum of
+/// the component's events that implements `AsRef<str>`.
+///
+/// Firing an event that is not declared panics in debug builds; in release builds, it is fired with the flags of
+/// [EventDescriptor::new].
+///
+/// Returns `false` if the event was canceled, as `dispatchEvent` does. For a
+/// [disableable](crate::CustomElement::disableable) element that is disabled, nothing is fired, and it returns `false`.
+///
+/// ```rust,ignore
+/// emit_declared::<Self>(this, EventKind::Change, &JsValue::from_str(&self.value));
+/// ```
+pub fn emit_declared<T: CustomElement>(
+    this: &HtmlElement,
+    event: impl AsRef<str>,
+    detail: &JsValue,
+) -> bool {
+    if T::disableable() && host(this).has_attribute("disabled") {
+        return false;
+    }
+    let name = event.as_ref();
+    let descriptor = T::events()
+        .iter()
+        .find(|descriptor| descriptor.name == name);
+    debug_assert!(
+        descriptor.is_some(),
+        "event \"{}\" is not declared in the component's events()",
+        name
+    );
+    let descriptor = descriptor
+        .copied()
+        .unwrap_or_else(|| EventDescriptor::new(""));
+
+    let init = CustomEventInit::new();
+    init.set_bubbles(descriptor.bubbles);
+    init.set_composed(descriptor.composed);
+    init.set_cancelable(descriptor.cancelable);
+    init.set_detail(detail);
+    let event = CustomEvent::new_with_event_init_dict(name, &init).unwrap_throw();
+    host(this).dispatch_event(&event).unwrap_throw()
+}
+
+/// Describes the events declared by `T` as an array of plain objects, with the same fields as [EventDescriptor],
+/// for generating documentation, a custom elements manifest, or TypeScript typings.
+pub fn manifest<T: CustomElement>() -> js_sys::Array {
+    T::events()
+        .iter()
+        .map(|descriptor| {
+            let object = js_sys::Object::new();
+            let fields = [
+                ("name", JsValue::from_str(descriptor.name)),
+                ("description", JsValue::from_str(descriptor.description)),
+                ("detail", JsValue::from_str(descriptor.detail)),
+                ("bubbles", JsValue::from_bool(descriptor.bubbles)),
+                ("composed", JsValue::from_bool(descriptor.composed)),
+                ("cancelable", JsValue::from_bool(descriptor.cancelable)),
+            ];
+            for (name, value) in fields {
+                js_sys::Reflect::set(&object, &JsValue::from_str(name), &value).unwrap_throw();
+            }
+            object
+        })
+        .collect()
+}
+
+/// Fires a `ce-error` event from the host element, with `phase` and the `error`'s message in its detail,
+/// as the crate does when a component's callback fails. See the [module documentation](self).
+///
+/// `this` may be either the custom element or its shadow root.
+pub fn report_error(this: &HtmlElement, phase: &str, error: &JsValue) {
+    dispatch_error(&host(this), phase, error);
+}
+
+#[wasm_bindgen(module = "/src/make_custom_element.js")]
+extern "C" {
+    fn dispatch_error(el: &HtmlElement, phase: &str, error: &JsValue);
+}
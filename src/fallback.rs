@@ -0,0 +1,79 @@
+//! What [define](crate::CustomElement::define) does in environments without a custom element registry.
+//!
+//! Some embedded web views have no `window.customElements`, so elements cannot be defined there, and by default
+//! `define` throws. A [Fallback] policy, set before any elements are defined, can instead let the page keep working:
+//!
+//! ```rust,ignore
+//! fallback::set_policy(Fallback::Enhance);
+//! MyComponent::define("my-component");
+//! ```
+//!
+//! With [Fallback::Enhance], the crate upgrades the elements itself: it finds the defined tags in the document, and
+//! in the shadow roots of the elements it has upgraded, with a `MutationObserver`, and calls their lifecycle callbacks
+//! as they are added, removed, and have their observed attributes changed. This covers the markup of a page, and
+//! elements created with `document.createElement` once they are inserted, but not elements constructed from their
+//! class, like with [create_element](crate::CustomElement::create_element), which throws, or inside shadow roots
+//! created by anything else.
+
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+
+use crate::capabilities::capabilities_in;
+use crate::{bundle, logging};
+
+/// What [define](crate::CustomElement::define) does when there is no `customElements` registry. See the
+/// [module documentation](self).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Fallback {
+    /// Throw an error, describing the browser's [capabilities](crate::capabilities()). This is the default.
+    #[default]
+    Error,
+    /// Write a warning to the console, and leave the elements as they are, with only the content the page gives them.
+    Warn,
+    /// Upgrade the elements in the document without the registry, and call their lifecycle callbacks.
+    Enhance,
+}
+
+thread_local! {
+    static POLICY: Cell<Fallback> = const { Cell::new(Fallback::Error) };
+}
+
+/// Sets what happens when elements are defined in an environment without a custom element registry. Elements defined
+/// before it is called keep the policy they were defined with.
+pub fn set_policy(policy: Fallback) {
+    POLICY.with(|p| p.set(policy));
+}
+
+/// The current [Fallback] policy.
+pub fn policy() -> Fallback {
+    POLICY.with(Cell::get)
+}
+
+// The `customElements` registry of `global`, or if it has none, a stand-in that follows the policy, for defining
+// `tag_name`.
+pub(crate) fn registry(global: &JsValue, tag_name: &str) -> JsValue {
+    if let Some(registry) = bundle::registry(global) {
+        return registry;
+    }
+    match policy() {
+        Fallback::Error => wasm_bindgen::throw_str(&format!(
+            "cannot define {}: no customElements registry in this environment ({})",
+            tag_name,
+            capabilities_in(global)
+        )),
+        Fallback::Warn => {
+            logging::warn(&format!(
+                "{} was not defined, since there is no customElements registry in this environment",
+                tag_name
+            ));
+            fallback_registry(global, false)
+        }
+        Fallback::Enhance => fallback_registry(global, true),
+    }
+}
+
+#[wasm_bindgen(module = "/src/make_custom_element.js")]
+extern "C" {
+    fn fallback_registry(global: &JsValue, enhance: bool) -> JsValue;
+}
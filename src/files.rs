@@ -0,0 +1,175 @@
+//! Files dropped on a component or chosen with a file input. Requires the `files` feature.
+//!
+//! [on_drop] makes the custom element a drop target for files, and [on_input] and [pick] get the files chosen with a
+//! file input, which can be in the element's shadow root. Their listeners are removed when the element is
+//! disconnected, so they are usually added in [connected_callback](crate::CustomElement::connected_callback). The
+//! files can then be read, all at once with [read_bytes] or in chunks with [read_chunks]:
+//!
+//! ```rust,ignore
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     files::on_drop(this, |files| {
+//!         for file in files {
+//!             spawn_local(async move {
+//!                 let bytes = files::read_bytes(&file).await.unwrap_throw();
+//!                 upload(file.name(), bytes).await;
+//!             });
+//!         }
+//!     });
+//! }
+//! ```
+//!
+//! While files are dragged over the element, it has a `data-dragover` attribute, for styling it as a drop target.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use futures_util::stream::{self, Stream};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    window, Blob, DragEvent, Event, File, FileList, HtmlElement, HtmlInputElement,
+    ReadableStreamDefaultReader,
+};
+
+use crate::host;
+use crate::scope::Scope;
+
+/// Calls `handler` with the files dropped on the element, until it is disconnected. Dragging anything other than
+/// files over the element is ignored. `this` may be either the element or its shadow root.
+pub fn on_drop(this: &HtmlElement, mut handler: impl FnMut(Vec<File>) + 'static) {
+    let host = host(this);
+    // `dragenter` and `dragleave` fire for each descendant the pointer crosses, so count them
+    let depth = Rc::new(Cell::new(0));
+    let listener = Closure::wrap(Box::new({
+        let host = host.clone();
+        move |event: DragEvent| {
+            let transfer = match event.data_transfer() {
+                Some(transfer) if transfer.types().includes(&"Files".into(), 0) => transfer,
+                _ => return,
+            };
+            match event.type_().as_str() {
+                "dragenter" => {
+                    event.prevent_default();
+                    depth.set(depth.get() + 1);
+                    host.set_attribute("data-dragover", "").unwrap_throw();
+                }
+                "dragover" => {
+                    event.prevent_default();
+                    transfer.set_drop_effect("copy");
+                }
+                "dragleave" => {
+                    depth.set(depth.get().max(1) - 1);
+                    if depth.get() == 0 {
+                        host.remove_attribute("data-dragover").unwrap_throw();
+                    }
+                }
+                _ => {
+                    event.prevent_default();
+                    depth.set(0);
+                    host.remove_attribute("data-dragover").unwrap_throw();
+                    handler(file_list(transfer.files()));
+                }
+            }
+        }
+    }) as Box<dyn FnMut(DragEvent)>);
+    const EVENTS: [&str; 4] = ["dragenter", "dragover", "dragleave", "drop"];
+    for event_type in EVENTS {
+        host.add_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+    }
+    Scope::new(&host).on_cleanup({
+        let host = host.clone();
+        move || {
+            for event_type in EVENTS {
+                host.remove_event_listener_with_callback(
+                    event_type,
+                    listener.as_ref().unchecked_ref(),
+                )
+                .unwrap_throw();
+            }
+            host.remove_attribute("data-dragover").unwrap_throw();
+        }
+    });
+}
+
+/// Calls `handler` with the files chosen with `input`, an `<input type="file">` that can be anywhere inside the
+/// element, each time the choice changes, until the element is disconnected. `this` may be either the element or its
+/// shadow root.
+pub fn on_input(
+    this: &HtmlElement,
+    input: &HtmlInputElement,
+    mut handler: impl FnMut(Vec<File>) + 'static,
+) {
+    let listener = Closure::wrap(Box::new({
+        let input = input.clone();
+        move |_: Event| handler(file_list(input.files()))
+    }) as Box<dyn FnMut(Event)>);
+    input
+        .add_event_listener_with_callback("change", listener.as_ref().unchecked_ref())
+        .unwrap_throw();
+    let input = input.clone();
+    Scope::new(this).on_cleanup(move || {
+        input
+            .remove_event_listener_with_callback("change", listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+    });
+}
+
+/// Opens the browser's file picker, as clicking a file input would, and calls `handler` with the files chosen.
+/// `accept` limits the files offered, like the input's `accept` attribute (`"image/*,.pdf"`); pass `""` to offer any.
+/// `handler` is not called if the picker is canceled.
+///
+/// Browsers only open the picker in response to a user action, like a click.
+pub fn pick(accept: &str, multiple: bool, handler: impl FnOnce(Vec<File>) + 'static) {
+    let document = window().unwrap_throw().document().unwrap_throw();
+    let input: HtmlInputElement = document
+        .create_element("input")
+        .unwrap_throw()
+        .unchecked_into();
+    input.set_type("file");
+    input.set_accept(accept);
+    input.set_multiple(multiple);
+    let listener = Closure::once_into_js({
+        let input = input.clone();
+        move |_: Event| handler(file_list(input.files()))
+    });
+    input
+        .add_event_listener_with_callback("change", listener.unchecked_ref())
+        .unwrap_throw();
+    input.click();
+}
+
+/// Reads the whole of a file, or any other [Blob], into memory.
+pub async fn read_bytes(blob: &Blob) -> Result<Vec<u8>, JsValue> {
+    let buffer = JsFuture::from(blob.array_buffer()).await?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+/// Reads a file, or any other [Blob], a chunk at a time, so that large files need not fit in memory. The chunk sizes
+/// are chosen by the browser.
+pub fn read_chunks(blob: &Blob) -> impl Stream<Item = Result<Vec<u8>, JsValue>> {
+    let reader: ReadableStreamDefaultReader = blob.stream().get_reader().unchecked_into();
+    stream::unfold(Some(reader), |reader| async move {
+        let reader = reader?;
+        let result = match JsFuture::from(reader.read()).await {
+            Ok(result) => result,
+            Err(error) => return Some((Err(error), None)),
+        };
+        let done = js_sys::Reflect::get(&result, &"done".into())
+            .unwrap_throw()
+            .is_truthy();
+        if done {
+            return None;
+        }
+        let value = js_sys::Reflect::get(&result, &"value".into()).unwrap_throw();
+        let chunk = value.unchecked_into::<js_sys::Uint8Array>().to_vec();
+        Some((Ok(chunk), Some(reader)))
+    })
+}
+
+fn file_list(files: Option<FileList>) -> Vec<File> {
+    files
+        .map(|files| (0..files.length()).filter_map(|i| files.get(i)).collect())
+        .unwrap_or_default()
+}
@@ -0,0 +1,142 @@
+//! Locale-aware number and date formatting, with the `Intl` formatters shared by every component.
+//!
+//! Creating an `Intl.NumberFormat` or `Intl.DateTimeFormat` is expensive, compared to formatting with one, which
+//! adds up for a table that formats each of its cells on each render. The formatters here are created once for each
+//! locale and set of options, and cached for every component that asks for the same ones:
+//!
+//! ```rust,ignore
+//! fn render(&self) -> String {
+//!     let price = fmt::number(self.price, &[("style", "currency"), ("currency", "EUR")]);
+//!     let date = fmt::date(&self.updated, &[("dateStyle", "medium")]);
+//!     format!("{} (updated {})", price, date)
+//! }
+//! ```
+//!
+//! Options are given as `(name, value)` pairs, with the names and values of the `Intl` constructors' options.
+//! Numbers are written as strings, like `("maximumFractionDigits", "2")`, which `Intl` converts, and `"true"` and
+//! `"false"` are passed as booleans.
+//!
+//! [number] and [date] use the document's [current locale](crate::locale::current_locale); components that
+//! [observe the locale](crate::CustomElement::observe_locale) re-render in its `locale_changed_callback`, and get
+//! formatters for the new locale.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use js_sys::{Array, Date, Intl, Object};
+use wasm_bindgen::prelude::*;
+
+use crate::locale::current_locale;
+
+// a locale and its options
+type Key = (String, Vec<(String, String)>);
+
+thread_local! {
+    static NUMBER_FORMATS: RefCell<HashMap<Key, Intl::NumberFormat>> = RefCell::new(HashMap::new());
+    static DATE_TIME_FORMATS: RefCell<HashMap<Key, Intl::DateTimeFormat>> = RefCell::new(HashMap::new());
+}
+
+/// Formats `value` in the document's current locale with the given `Intl.NumberFormat` options.
+pub fn number(value: f64, options: &[(&str, &str)]) -> String {
+    call_format(
+        &number_format(&current_locale(), options).format(),
+        &JsValue::from_f64(value),
+    )
+}
+
+/// Formats `date` in the document's current locale with the given `Intl.DateTimeFormat` options.
+pub fn date(date: &Date, options: &[(&str, &str)]) -> String {
+    call_format(&date_time_format(&current_locale(), options).format(), date)
+}
+
+/// The shared `Intl.NumberFormat` for `locale` and `options`, created the first time it is needed.
+pub fn number_format(locale: &str, options: &[(&str, &str)]) -> Intl::NumberFormat {
+    let key = key(locale, options);
+    NUMBER_FORMATS.with(|formats| {
+        formats
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(|| Intl::NumberFormat::new(&locales(locale), &to_object(options)))
+            .clone()
+    })
+}
+
+/// The shared `Intl.DateTimeFormat` for `locale` and `options`, created the first time it is needed.
+pub fn date_time_format(locale: &str, options: &[(&str, &str)]) -> Intl::DateTimeFormat {
+    let key = key(locale, options);
+    DATE_TIME_FORMATS.with(|formats| {
+        formats
+            .borrow_mut()
+            .entry(key)
+            .or_insert_with(|| Intl::DateTimeFormat::new(&locales(locale), &to_object(options)))
+            .clone()
+    })
+}
+
+/// Drops the cached formatters, which are otherwise kept for as long as the page is open.
+pub fn clear() {
+    NUMBER_FORMATS.with(|formats| formats.borrow_mut().clear());
+    DATE_TIME_FORMATS.with(|formats| formats.borrow_mut().clear());
+}
+
+fn key(locale: &str, options: &[(&str, &str)]) -> Key {
+    let options = options
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    (locale.to_string(), options)
+}
+
+// The locale as the list of locales the constructors take; an empty one for the browser's default.
+fn locales(locale: &str) -> Array {
+    let locales = Array::new();
+    if !locale.is_empty() {
+        locales.push(&JsValue::from_str(locale));
+    }
+    locales
+}
+
+fn to_object(options: &[(&str, &str)]) -> Object {
+    let object = Object::new();
+    for (name, value) in options {
+        let value = match *value {
+            "true" => JsValue::TRUE,
+            "false" => JsValue::FALSE,
+            value => JsValue::from_str(value),
+        };
+        js_sys::Reflect::set(&object, &JsValue::from_str(name), &value).unwrap_throw();
+    }
+    object
+}
+
+fn call_format(format: &js_sys::Function, value: &JsValue) -> String {
+    format
+        .call1(&JsValue::UNDEFINED, value)
+        .unwrap_throw()
+        .as_string()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_by_locale_and_options() {
+        let currency = [("style", "currency"), ("currency", "EUR")];
+        assert_eq!(key("de-DE", &currency), key("de-DE", &currency));
+        assert_ne!(key("de-DE", &currency), key("fr-FR", &currency));
+        assert_ne!(key("de-DE", &currency), key("de-DE", &currency[..1]));
+        assert_ne!(
+            key("de-DE", &[("currency", "EUR")]),
+            key("de-DE", &[("currency", "USD")])
+        );
+        assert_eq!(
+            key("", &[("maximumFractionDigits", "2")]),
+            (
+                String::new(),
+                vec![("maximumFractionDigits".to_string(), "2".to_string())]
+            )
+        );
+    }
+}
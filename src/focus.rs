@@ -0,0 +1,207 @@
+//! Keeping keyboard focus inside a component, as a modal dialog must.
+//!
+//! While a [FocusTrap] is active, Tab and Shift+Tab cycle through the focusable elements inside the custom element,
+//! and focus that leaves it some other way, like a click elsewhere, is brought back. Deactivating the trap returns
+//! focus to the element that had it before, and the trap deactivates itself when the custom element is disconnected:
+//!
+//! ```rust,ignore
+//! fn open(&mut self, this: &HtmlElement) {
+//!     this.set_attribute("open", "").unwrap_throw();
+//!     self.trap = Some(FocusTrap::activate(this));
+//! }
+//!
+//! fn close(&mut self, this: &HtmlElement) {
+//!     this.remove_attribute("open").unwrap_throw();
+//!     if let Some(trap) = self.trap.take() {
+//!         trap.deactivate();
+//!     }
+//! }
+//! ```
+//!
+//! The focusable elements are found as [query_all](crate::query_all) would, so those in the element's shadow root if it
+//! has one, in document order. Elements slotted into it, or inside nested shadow roots, are skipped.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Document, Element, Event, HtmlElement, KeyboardEvent, Node};
+
+use crate::scope::Scope;
+use crate::{host, query_all};
+
+const FOCUSABLE: &str = "a[href], area[href], button:not([disabled]), input:not([disabled]):not([type=hidden]), \
+    select:not([disabled]), textarea:not([disabled]), iframe, summary, [contenteditable]:not([contenteditable=false]), \
+    [tabindex]";
+
+type Listeners = (Closure<dyn FnMut(KeyboardEvent)>, Closure<dyn FnMut(Event)>);
+
+struct Trap {
+    host: HtmlElement,
+    document: Document,
+    previous: Option<HtmlElement>,
+    active: Cell<bool>,
+    listeners: RefCell<Option<Listeners>>,
+}
+
+impl Trap {
+    fn deactivate(&self) {
+        if !self.active.replace(false) {
+            return;
+        }
+        if let Some((keydown, focusin)) = self.listeners.borrow_mut().take() {
+            self.host
+                .remove_event_listener_with_callback("keydown", keydown.as_ref().unchecked_ref())
+                .unwrap_throw();
+            self.document
+                .remove_event_listener_with_callback("focusin", focusin.as_ref().unchecked_ref())
+                .unwrap_throw();
+        }
+        if let Some(previous) = &self.previous {
+            if previous.is_connected() {
+                let _ = previous.focus();
+            }
+        }
+    }
+}
+
+/// Confines keyboard focus to a custom element while it is active. See the [module documentation](self).
+pub struct FocusTrap {
+    trap: Rc<Trap>,
+}
+
+impl FocusTrap {
+    /// Starts trapping focus inside the element, and moves focus to its first focusable element, unless focus is
+    /// already inside it. `this` may be either the element or its shadow root.
+    pub fn activate(this: &HtmlElement) -> Self {
+        let host = host(this);
+        let document = window().unwrap_throw().document().unwrap_throw();
+        let trap = Rc::new(Trap {
+            previous: active_element(&document),
+            host: host.clone(),
+            document: document.clone(),
+            active: Cell::new(true),
+            listeners: RefCell::new(None),
+        });
+
+        let keydown = Closure::wrap(Box::new({
+            let host = host.clone();
+            let document = document.clone();
+            move |event: KeyboardEvent| {
+                if event.key() != "Tab" {
+                    return;
+                }
+                let focusable = focusable(&host);
+                let (first, last) = match (focusable.first(), focusable.last()) {
+                    (Some(first), Some(last)) => (first, last),
+                    _ => {
+                        event.prevent_default();
+                        return;
+                    }
+                };
+                let current = active_element(&document);
+                let position = current
+                    .as_ref()
+                    .and_then(|current| focusable.iter().position(|element| element == current));
+                let wrap_to = match (event.shift_key(), position) {
+                    (true, Some(0)) | (true, None) => Some(last),
+                    (false, Some(i)) if i == focusable.len() - 1 => Some(first),
+                    (false, None) => Some(first),
+                    _ => None,
+                };
+                if let Some(element) = wrap_to {
+                    event.prevent_default();
+                    let _ = element.focus();
+                }
+            }
+        }) as Box<dyn FnMut(KeyboardEvent)>);
+        let focusin = Closure::wrap(Box::new({
+            let host = host.clone();
+            move |event: Event| {
+                let target = event.composed_path().get(0).dyn_into::<Node>().ok();
+                let inside = target
+                    .as_ref()
+                    .map(|target| contains(&host, target))
+                    .unwrap_or(false);
+                if !inside {
+                    if let Some(first) = focusable(&host).first() {
+                        let _ = first.focus();
+                    }
+                }
+            }
+        }) as Box<dyn FnMut(Event)>);
+        host.add_event_listener_with_callback("keydown", keydown.as_ref().unchecked_ref())
+            .unwrap_throw();
+        document
+            .add_event_listener_with_callback("focusin", focusin.as_ref().unchecked_ref())
+            .unwrap_throw();
+        trap.listeners.replace(Some((keydown, focusin)));
+
+        let inside = active_element(&document)
+            .map(|active| contains(&host, &active))
+            .unwrap_or(false);
+        if !inside {
+            if let Some(first) = focusable(&host).first() {
+                let _ = first.focus();
+            }
+        }
+
+        Scope::new(&host).on_cleanup({
+            let trap = Rc::downgrade(&trap);
+            move || {
+                if let Some(trap) = trap.upgrade() {
+                    trap.deactivate();
+                }
+            }
+        });
+        FocusTrap { trap }
+    }
+
+    /// Stops trapping focus, and returns focus to the element that had it when the trap was activated, if it is still
+    /// in the document. Does nothing if the trap is no longer active.
+    pub fn deactivate(&self) {
+        self.trap.deactivate();
+    }
+
+    /// Whether the trap is still active: it has not been deactivated, and the element has not been disconnected.
+    pub fn is_active(&self) -> bool {
+        self.trap.active.get()
+    }
+}
+
+// The focusable elements inside the component, in document order, skipping those that are not rendered.
+fn focusable(host: &HtmlElement) -> Vec<HtmlElement> {
+    query_all::<HtmlElement>(host, FOCUSABLE)
+        .into_iter()
+        .filter(|element| element.tab_index() >= 0 && element.get_client_rects().length() > 0)
+        .collect()
+}
+
+// The focused element, looking inside shadow roots rather than stopping at their hosts.
+fn active_element(document: &Document) -> Option<HtmlElement> {
+    let mut active: Element = document.active_element()?;
+    while let Some(inner) = active
+        .shadow_root()
+        .and_then(|shadow_root| shadow_root.active_element())
+    {
+        active = inner;
+    }
+    active.dyn_into().ok()
+}
+
+// Whether `node` is the element or inside it, including inside its shadow root.
+pub(crate) fn contains(host: &HtmlElement, node: &Node) -> bool {
+    let mut node = Some(node.clone());
+    while let Some(current) = node {
+        if host.is_same_node(Some(&current)) {
+            return true;
+        }
+        node = current.parent_node().or_else(|| {
+            current
+                .dyn_ref::<web_sys::ShadowRoot>()
+                .map(|shadow_root| shadow_root.host().into())
+        });
+    }
+    false
+}
@@ -0,0 +1,317 @@
+//! Recognizing taps, long presses, swipes, and pinches from pointer events. Requires the `gestures` feature.
+//!
+//! [on] listens for the pointer events on the custom element, including those from inside its shadow root, and calls
+//! its handler with each [Gesture] they make up, until the element is disconnected:
+//!
+//! ```rust,ignore
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     let carousel = self.carousel.clone();
+//!     gestures::on(this, move |gesture| match gesture {
+//!         Gesture::Swipe { direction: Direction::Left, .. } => carousel.next(),
+//!         Gesture::Swipe { direction: Direction::Right, .. } => carousel.previous(),
+//!         _ => {}
+//!     });
+//! }
+//! ```
+//!
+//! Mouse, pen and touch input are all handled the same way. On touch screens the browser scrolls and zooms the page
+//! in response to the same movements, and stops sending pointer events when it does, so elements that recognize
+//! swipes or pinches should turn that off with CSS, like `:host { touch-action: none; }`, or `pan-y` to keep vertical
+//! scrolling.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, HtmlElement, PointerEvent};
+
+use crate::host;
+use crate::scope::Scope;
+
+const EVENT_TYPES: &[&str] = &["pointerdown", "pointermove", "pointerup", "pointercancel"];
+
+/// The direction of a [Gesture::Swipe].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// A gesture recognized by [on]. Positions are in CSS pixels, relative to the viewport.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Gesture {
+    /// A pointer was pressed and released without moving.
+    Tap { x: f64, y: f64 },
+    /// A pointer was pressed and held without moving, for [long_press_ms](GestureOptions::long_press_ms). Reported
+    /// while it is still held; releasing it afterwards is not a tap.
+    LongPress { x: f64, y: f64 },
+    /// A pointer was pressed, moved quickly in one direction, and released. `distance` is in pixels, and `velocity`
+    /// in pixels per millisecond.
+    Swipe {
+        direction: Direction,
+        distance: f64,
+        velocity: f64,
+    },
+    /// Two pointers are moving apart or together. Reported each time one of them moves, with `scale` relative to their
+    /// distance when the second was pressed, and the point between them.
+    Pinch { scale: f64, x: f64, y: f64 },
+}
+
+/// The thresholds [on_with_options] uses to tell gestures apart.
+#[derive(Clone, Debug)]
+pub struct GestureOptions {
+    /// How far a pointer can move, in pixels, and still make a tap or long press. Defaults to 10.
+    pub tap_distance: f64,
+    /// How long a pointer has to be held to make a long press, in milliseconds. Defaults to 500.
+    pub long_press_ms: i32,
+    /// How far a pointer has to move to make a swipe, in pixels. Defaults to 40.
+    pub swipe_distance: f64,
+    /// How long a swipe can take at most, in milliseconds. Defaults to 600.
+    pub swipe_ms: f64,
+}
+
+impl Default for GestureOptions {
+    fn default() -> Self {
+        Self {
+            tap_distance: 10.0,
+            long_press_ms: 500,
+            swipe_distance: 40.0,
+            swipe_ms: 600.0,
+        }
+    }
+}
+
+// a pointer that is down, with its position when it was pressed and now
+#[derive(Clone, Copy)]
+struct Pointer {
+    start: (f64, f64),
+    position: (f64, f64),
+}
+
+// the pointers that are down, and what they have done so far
+#[derive(Default)]
+struct State {
+    pointers: HashMap<i32, Pointer>,
+    started_at: f64,
+    // whether the current gesture can still be a tap, long press or swipe, which it cannot once it has had two
+    // pointers or made a long press
+    single: bool,
+    pinch_distance: Option<f64>,
+    long_press_timer: Option<i32>,
+}
+
+impl State {
+    fn cancel_long_press(&mut self) {
+        if let Some(timer) = self.long_press_timer.take() {
+            window().unwrap_throw().clear_timeout_with_handle(timer);
+        }
+    }
+
+    // the distance between the first two pointers, and the point between them
+    fn pinch(&self) -> Option<(f64, (f64, f64))> {
+        let mut positions = self.pointers.values().map(|pointer| pointer.position);
+        let (a, b) = (positions.next()?, positions.next()?);
+        let distance = (a.0 - b.0).hypot(a.1 - b.1);
+        Some((distance, ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)))
+    }
+}
+
+type Handler = Rc<RefCell<dyn FnMut(&Gesture)>>;
+
+/// Calls `handler` with each gesture made on the element, with the default [GestureOptions], until the element is
+/// disconnected. `this` may be either the element or its shadow root.
+pub fn on(this: &HtmlElement, handler: impl FnMut(&Gesture) + 'static) {
+    on_with_options(this, GestureOptions::default(), handler);
+}
+
+/// Like [on], with the given thresholds.
+pub fn on_with_options(
+    this: &HtmlElement,
+    options: GestureOptions,
+    handler: impl FnMut(&Gesture) + 'static,
+) {
+    let host = host(this);
+    let state = Rc::new(RefCell::new(State::default()));
+    let handler: Handler = Rc::new(RefCell::new(handler));
+
+    let long_press = Closure::wrap(Box::new({
+        let (state, handler) = (state.clone(), handler.clone());
+        move || {
+            let position = {
+                let mut state = state.borrow_mut();
+                state.long_press_timer = None;
+                state.single = false;
+                state
+                    .pointers
+                    .values()
+                    .next()
+                    .map(|pointer| pointer.position)
+            };
+            if let Some((x, y)) = position {
+                (handler.borrow_mut())(&Gesture::LongPress { x, y });
+            }
+        }
+    }) as Box<dyn FnMut()>);
+
+    let listener = Closure::wrap(Box::new({
+        let state = state.clone();
+        let long_press = long_press.as_ref().clone();
+        move |event: PointerEvent| {
+            let id = event.pointer_id();
+            let position = (event.client_x() as f64, event.client_y() as f64);
+            let gesture = {
+                let mut state = state.borrow_mut();
+                match event.type_().as_str() {
+                    "pointerdown" => {
+                        pointer_down(&mut state, &event, &options, &long_press);
+                        None
+                    }
+                    "pointermove" => pointer_move(&mut state, id, position, &options),
+                    "pointerup" => pointer_up(&mut state, &event, &options),
+                    _ => {
+                        state.cancel_long_press();
+                        state.pointers.clear();
+                        None
+                    }
+                }
+            };
+            if let Some(gesture) = gesture {
+                (handler.borrow_mut())(&gesture);
+            }
+        }
+    }) as Box<dyn FnMut(PointerEvent)>);
+
+    for event_type in EVENT_TYPES {
+        host.add_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+    }
+    Scope::new(&host).on_cleanup({
+        let host = host.clone();
+        move || {
+            for event_type in EVENT_TYPES {
+                host.remove_event_listener_with_callback(
+                    event_type,
+                    listener.as_ref().unchecked_ref(),
+                )
+                .unwrap_throw();
+            }
+            state.borrow_mut().cancel_long_press();
+            drop(long_press);
+        }
+    });
+}
+
+fn pointer_down(
+    state: &mut State,
+    event: &PointerEvent,
+    options: &GestureOptions,
+    long_press: &JsValue,
+) {
+    let position = (event.client_x() as f64, event.client_y() as f64);
+    // keep receiving the pointer's events if it leaves the element, so that a swipe can end outside it
+    if let Some(target) = event
+        .current_target()
+        .and_then(|target| target.dyn_into::<HtmlElement>().ok())
+    {
+        let _ = target.set_pointer_capture(event.pointer_id());
+    }
+    state.pointers.insert(
+        event.pointer_id(),
+        Pointer {
+            start: position,
+            position,
+        },
+    );
+    match state.pointers.len() {
+        1 => {
+            state.started_at = event.time_stamp();
+            state.single = true;
+            state.pinch_distance = None;
+            state.cancel_long_press();
+            state.long_press_timer = Some(
+                window()
+                    .unwrap_throw()
+                    .set_timeout_with_callback_and_timeout_and_arguments_0(
+                        long_press.unchecked_ref(),
+                        options.long_press_ms,
+                    )
+                    .unwrap_throw(),
+            );
+        }
+        _ => {
+            state.single = false;
+            state.cancel_long_press();
+            state.pinch_distance = state.pinch().map(|(distance, _)| distance);
+        }
+    }
+}
+
+fn pointer_move(
+    state: &mut State,
+    id: i32,
+    position: (f64, f64),
+    options: &GestureOptions,
+) -> Option<Gesture> {
+    let start = {
+        let pointer = state.pointers.get_mut(&id)?;
+        pointer.position = position;
+        pointer.start
+    };
+    if (position.0 - start.0).hypot(position.1 - start.1) > options.tap_distance {
+        state.cancel_long_press();
+    }
+    let start_distance = state.pinch_distance.filter(|distance| *distance > 0.0)?;
+    let (distance, (x, y)) = state.pinch()?;
+    Some(Gesture::Pinch {
+        scale: distance / start_distance,
+        x,
+        y,
+    })
+}
+
+fn pointer_up(
+    state: &mut State,
+    event: &PointerEvent,
+    options: &GestureOptions,
+) -> Option<Gesture> {
+    let start = state.pointers.remove(&event.pointer_id())?.start;
+    state.cancel_long_press();
+    if !state.pointers.is_empty() {
+        // the pinch is over, but the other pointer cannot start a new gesture until it is released too
+        state.pinch_distance = None;
+        return None;
+    }
+    if !state.single {
+        return None;
+    }
+    let (x, y) = (event.client_x() as f64, event.client_y() as f64);
+    let (dx, dy) = (x - start.0, y - start.1);
+    let distance = dx.hypot(dy);
+    let elapsed = event.time_stamp() - state.started_at;
+    if distance <= options.tap_distance {
+        Some(Gesture::Tap { x, y })
+    } else if distance >= options.swipe_distance && elapsed <= options.swipe_ms {
+        let direction = if dx.abs() > dy.abs() {
+            if dx > 0.0 {
+                Direction::Right
+            } else {
+                Direction::Left
+            }
+        } else if dy > 0.0 {
+            Direction::Down
+        } else {
+            Direction::Up
+        };
+        Some(Gesture::Swipe {
+            direction,
+            distance,
+            velocity: distance / elapsed.max(1.0),
+        })
+    } else {
+        None
+    }
+}
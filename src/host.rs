@@ -0,0 +1,95 @@
+//! The operations components most often perform on their element, behind a trait, so that components can run
+//! somewhere other than a browser.
+//!
+//! [CustomElement](crate::CustomElement) is generic over the element its lifecycle callbacks receive, which is an
+//! `HtmlElement` unless the implementation says otherwise. A component that only uses its element through [Host]
+//! can implement the trait for every host, and then works with the `HtmlElement` in a browser and with a
+//! [MockHost](crate::mock::MockHost) in native tests:
+//!
+//! ```rust,ignore
+//! impl<H: Host> CustomElement<H> for Toggle {
+//!     fn connected_callback(&mut self, this: &H) {
+//!         this.set_attr("aria-pressed", if self.pressed { "true" } else { "false" });
+//!         this.emit("toggle", Some(&self.pressed.to_string()));
+//!     }
+//! }
+//!
+//! // the browser's host is the default, but has to be named when the implementation is generic
+//! <Toggle as CustomElement>::define("my-toggle");
+//! ```
+
+use wasm_bindgen::prelude::*;
+use web_sys::{CustomEvent, CustomEventInit, HtmlElement};
+
+use crate::host;
+
+/// A custom element, as seen by component code. See the [module documentation](self).
+pub trait Host {
+    /// The value of an attribute of the element, if it has it.
+    fn attr(&self, name: &str) -> Option<String>;
+
+    /// Sets an attribute of the element.
+    fn set_attr(&self, name: &str, value: &str);
+
+    /// Removes an attribute of the element.
+    fn remove_attr(&self, name: &str);
+
+    /// Whether the element has an attribute, whatever its value.
+    fn has_attr(&self, name: &str) -> bool {
+        self.attr(name).is_some()
+    }
+
+    /// Replaces the element's content, which is its shadow root if it has one, with the given text.
+    fn set_text(&self, text: &str);
+
+    /// Appends text to the element's content, which is its shadow root if it has one.
+    fn append_text(&self, text: &str);
+
+    /// Fires an event from the element, which bubbles and is composed, with `detail` as its detail if it is given.
+    /// Returns `false` if the event was canceled.
+    fn emit(&self, event_type: &str, detail: Option<&str>) -> bool;
+}
+
+// `this` may be the element or its shadow root, as in the lifecycle callbacks
+impl Host for HtmlElement {
+    fn attr(&self, name: &str) -> Option<String> {
+        host(self).get_attribute(name)
+    }
+
+    fn set_attr(&self, name: &str, value: &str) {
+        host(self).set_attribute(name, value).unwrap_throw();
+    }
+
+    fn remove_attr(&self, name: &str) {
+        host(self).remove_attribute(name).unwrap_throw();
+    }
+
+    fn set_text(&self, text: &str) {
+        let host = host(self);
+        match host.shadow_root() {
+            Some(shadow_root) => shadow_root.set_text_content(Some(text)),
+            None => host.set_text_content(Some(text)),
+        }
+    }
+
+    fn append_text(&self, text: &str) {
+        let host = host(self);
+        match host.shadow_root() {
+            Some(shadow_root) => shadow_root.append_with_str_1(text),
+            None => host.append_with_str_1(text),
+        }
+        .unwrap_throw();
+    }
+
+    fn emit(&self, event_type: &str, detail: Option<&str>) -> bool {
+        let init = CustomEventInit::new();
+        init.set_bubbles(true);
+        init.set_composed(true);
+        init.set_cancelable(true);
+        if let Some(detail) = detail {
+            init.set_detail(&JsValue::from_str(detail));
+        }
+        let event = CustomEvent::new_with_event_init_dict(event_type, &init).unwrap_throw();
+        host(self).dispatch_event(&event).unwrap_throw()
+    }
+}
@@ -0,0 +1,95 @@
+use std::cell::{Cell, RefCell, UnsafeCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use crate::{logging, panic};
+
+type Deferred<T> = Box<dyn FnOnce(&mut T)>;
+
+// A component shared by the callbacks of one custom element instance.
+//
+// Callbacks can be re-entered: for example, if a component's constructor sets one of its own observed attributes,
+// the browser invokes attributeChangedCallback synchronously, while the constructor is still running. Rather than
+// using the component a second time, a callback delivered while the component is busy is queued, and the callback
+// that is using it runs the queue, in order, once it has finished.
+//
+// WebAssembly doesn't unwind, so a callback that panics or throws never returns here, and nothing it holds is
+// dropped; a lock, or a `RefCell` borrow, would be held forever, and every later callback queued. The component is
+// only marked busy instead, and the JavaScript that called the callback calls `reset` when it fails.
+pub(crate) struct Instance<T> {
+    tag_name: Rc<str>,
+    component: UnsafeCell<T>,
+    busy: Cell<bool>,
+    queue: RefCell<VecDeque<(&'static str, Deferred<T>)>>,
+}
+
+impl<T: 'static> Instance<T> {
+    pub(crate) fn new(tag_name: Rc<str>, component: T) -> Self {
+        Self {
+            tag_name,
+            component: UnsafeCell::new(component),
+            busy: Cell::new(false),
+            queue: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    // Runs `callback`, named `name` in the crate's logs, with the component.
+    pub(crate) fn with(&self, name: &'static str, callback: impl FnOnce(&mut T) + 'static) {
+        if self.busy.replace(true) {
+            logging::queued(&self.tag_name, name);
+            self.queue
+                .borrow_mut()
+                .push_back((name, Box::new(callback)));
+            return;
+        }
+        // SAFETY: the busy flag makes this the only reference to the component until it is cleared below; `reset`
+        // only clears it once the callback that set it has failed, and its frame is gone
+        let component = unsafe { &mut *self.component.get() };
+        {
+            let _span = logging::callback(&self.tag_name, name);
+            let _phase = panic::enter(&self.tag_name, name);
+            #[cfg(feature = "metrics")]
+            let _timer = metrics::callback(&self.tag_name, name);
+            callback(component);
+        }
+        loop {
+            // the queue must not be borrowed while each deferred callback runs, since it may queue more
+            let deferred = self.queue.borrow_mut().pop_front();
+            match deferred {
+                Some((name, deferred)) => {
+                    let _span = logging::callback(&self.tag_name, name);
+                    let _phase = panic::enter(&self.tag_name, name);
+                    #[cfg(feature = "metrics")]
+                    let _timer = metrics::callback(&self.tag_name, name);
+                    deferred(component);
+                }
+                None => break,
+            }
+        }
+        self.busy.set(false);
+    }
+
+    // Makes the component usable again after one of its callbacks has panicked or thrown, dropping the callbacks
+    // queued behind it, which may depend on the state it left half updated.
+    pub(crate) fn reset(&self) {
+        self.busy.set(false);
+        let dropped = std::mem::take(&mut *self.queue.borrow_mut());
+        drop(dropped);
+    }
+
+    // The component, while none of its callbacks is running.
+    #[cfg(feature = "mock")]
+    pub(crate) fn get(&self) -> &T {
+        assert!(!self.busy.get(), "<{}> is in use", self.tag_name);
+        // SAFETY: no callback has the component, and none can start while `&self` is borrowed by the caller, since
+        // the callbacks that mutate it are only started through `&mut` access in the mock
+        unsafe { &*self.component.get() }
+    }
+
+    #[cfg(feature = "mock")]
+    pub(crate) fn get_mut(&mut self) -> &mut T {
+        self.component.get_mut()
+    }
+}
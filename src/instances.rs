@@ -0,0 +1,308 @@
+//! The live elements defined with this crate.
+//!
+//! Each element's component, and the handlers for its callbacks, live in a table kept by the crate, and the element
+//! only holds a number, its handle in the table. Nothing in the table refers to the element strongly, so the element
+//! can be garbage collected as usual, which also drops its component; [destroy] drops it sooner. A component that
+//! holds on to its own element, directly or through a closure it keeps, keeps it alive, and is never dropped.
+//!
+//! The table can be listed, to see how many of each element a page has, or to find them:
+//!
+//! ```rust,ignore
+//! for (tag_name, count) in instances::counts() {
+//!     log::info!("{}: {}", tag_name, count);
+//! }
+//! ```
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlElement;
+
+use crate::{host, panic};
+
+// Handles are an index into the table in their low bits, and in their high bits the generation of the slot, which
+// changes each time it is reused, so that the handle of a destroyed element never finds the entry that replaced it.
+const INDEX_BITS: u32 = 24;
+const INDEX_MASK: u32 = (1 << INDEX_BITS) - 1;
+const MAX_GENERATION: u32 = (1 << (32 - INDEX_BITS)) - 1;
+
+type Handler = Box<dyn Fn(HtmlElement, &js_sys::Array) -> JsValue>;
+
+// The handlers for one element's callbacks, by kind, like `"connectedCallback"`, and anything else that has to live
+// as long as the element.
+pub(crate) struct Entry {
+    tag_name: Rc<str>,
+    element: JsValue,
+    handlers: Vec<(&'static str, Handler)>,
+    resets: Vec<Box<dyn Fn()>>,
+    owned: Vec<Box<dyn Any>>,
+}
+
+impl Entry {
+    pub(crate) fn new(tag_name: Rc<str>) -> Self {
+        Self {
+            tag_name,
+            element: JsValue::UNDEFINED,
+            handlers: Vec::new(),
+            resets: Vec::new(),
+            owned: Vec::new(),
+        }
+    }
+
+    pub(crate) fn on(
+        &mut self,
+        kind: &'static str,
+        handler: impl Fn(HtmlElement, &js_sys::Array) -> JsValue + 'static,
+    ) {
+        self.handlers.push((kind, Box::new(handler)));
+    }
+
+    // Calls `reset` each time one of the element's callbacks panics or throws, to undo what it left half done.
+    pub(crate) fn on_reset(&mut self, reset: impl Fn() + 'static) {
+        self.resets.push(Box::new(reset));
+    }
+
+    // Keeps `value`, like an event listener's closure, until the element is collected or destroyed.
+    pub(crate) fn keep(&mut self, value: impl Any) {
+        self.owned.push(Box::new(value));
+    }
+
+    // Calls the handler for `kind`, with the element first in `args`.
+    fn dispatch(&self, kind: &str, args: &js_sys::Array) -> JsValue {
+        match self.handlers.iter().find(|(handled, _)| *handled == kind) {
+            Some((_, handler)) => handler(args.get(0).unchecked_into(), args),
+            None => JsValue::UNDEFINED,
+        }
+    }
+
+    fn element(&self) -> Option<HtmlElement> {
+        deref(&self.element).dyn_into().ok()
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    generation: u32,
+    entry: Option<Rc<Entry>>,
+}
+
+#[derive(Default)]
+struct Table {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+}
+
+impl Table {
+    fn slot(&mut self, handle: u32) -> Option<&mut Slot> {
+        let slot = self.slots.get_mut((handle & INDEX_MASK) as usize)?;
+        if slot.generation == handle >> INDEX_BITS {
+            Some(slot)
+        } else {
+            None
+        }
+    }
+}
+
+thread_local! {
+    static TABLE: RefCell<Table> = RefCell::new(Table::default());
+    // the panic hook's depth when each dispatch still running started, innermost last, which a dispatch that fails
+    // is reset to
+    static DISPATCHING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+    // the function the classes call with an element's handle, the kind of callback, and its arguments
+    static DISPATCH: JsValue = {
+        set_release(
+            Closure::wrap(Box::new(|handle| {
+                release(handle);
+            }) as Box<dyn Fn(u32)>)
+            .into_js_value(),
+        );
+        set_reset(
+            Closure::wrap(Box::new(|handle| {
+                if let Some(depth) = DISPATCHING.with(|dispatching| dispatching.borrow_mut().pop()) {
+                    panic::truncate(depth);
+                }
+                if let Some(entry) = get(handle) {
+                    for reset in &entry.resets {
+                        reset();
+                    }
+                }
+            }) as Box<dyn Fn(u32)>)
+            .into_js_value(),
+        );
+        Closure::wrap(Box::new(|handle: u32, kind: String, args: js_sys::Array| {
+            DISPATCHING.with(|dispatching| dispatching.borrow_mut().push(panic::depth()));
+            let result = match get(handle) {
+                Some(entry) => entry.dispatch(&kind, &args),
+                None => JsValue::UNDEFINED,
+            };
+            DISPATCHING.with(|dispatching| dispatching.borrow_mut().pop());
+            result
+        }) as Box<dyn Fn(u32, String, js_sys::Array) -> JsValue>)
+        .into_js_value()
+    };
+}
+
+// Adds the entry for `el`, and returns its handle, which is released once `el` has been garbage collected.
+pub(crate) fn insert(el: &HtmlElement, mut entry: Entry) -> u32 {
+    entry.element = weak_ref(el);
+    let handle = TABLE.with(|table| {
+        let mut table = table.borrow_mut();
+        let index = match table.free.pop() {
+            Some(index) => index,
+            None => {
+                let index = table.slots.len() as u32;
+                if index > INDEX_MASK {
+                    wasm_bindgen::throw_str(&format!(
+                        "cannot create more than {} elements defined with custom-elements",
+                        INDEX_MASK + 1
+                    ));
+                }
+                table.slots.push(Slot::default());
+                index
+            }
+        };
+        let slot = &mut table.slots[index as usize];
+        slot.entry = Some(Rc::new(entry));
+        index | (slot.generation << INDEX_BITS)
+    });
+    DISPATCH.with(|_| release_when_collected(el, handle));
+    handle
+}
+
+fn get(handle: u32) -> Option<Rc<Entry>> {
+    // cloned out of the table, which handlers may add to while they run
+    TABLE.with(|table| table.borrow_mut().slot(handle)?.entry.clone())
+}
+
+// Removes the entry for `handle`, if it is still in the table.
+fn release(handle: u32) -> bool {
+    let entry = TABLE.with(|table| {
+        let mut table = table.borrow_mut();
+        let slot = table.slot(handle)?;
+        let entry = slot.entry.take()?;
+        // a slot whose generation has run out is retired rather than reused, since its next generation would be
+        // the same as its first, and old handles would find the new entry
+        if slot.generation < MAX_GENERATION {
+            slot.generation += 1;
+            table.free.push(handle & INDEX_MASK);
+        }
+        Some(entry)
+    });
+    // dropped after the table is unlocked, since dropping a component can run arbitrary code
+    entry.is_some()
+}
+
+fn handle_of(el: &HtmlElement) -> Option<u32> {
+    js_sys::Reflect::get(el, &JsValue::from_str("_handle"))
+        .ok()?
+        .as_f64()
+        .map(|handle| handle as u32)
+}
+
+// The function the classes call with an element's handle, the kind of callback, and its arguments.
+pub(crate) fn dispatcher() -> JsValue {
+    DISPATCH.with(JsValue::clone)
+}
+
+// Calls the element's handler for `kind` with the element and `args`, and returns what it returns, or `None` if the
+// element was not defined with this crate, has not been initialized yet, or has been destroyed. The call goes
+// through the element's `_dispatch`, like the browser's calls to its callbacks, so that it is reset if it fails.
+pub(crate) fn dispatch(el: &HtmlElement, kind: &str, args: &[&JsValue]) -> Option<JsValue> {
+    get(handle_of(el)?)?;
+    let call_args = js_sys::Array::of2(&JsValue::from_str(kind), el);
+    for arg in args {
+        call_args.push(arg);
+    }
+    let dispatch: js_sys::Function = js_sys::Reflect::get(el, &JsValue::from_str("_dispatch"))
+        .unwrap_throw()
+        .unchecked_into();
+    match dispatch.apply(el, &call_args) {
+        Ok(value) => Some(value),
+        Err(error) => wasm_bindgen::throw_val(error),
+    }
+}
+
+/// Drops the element's component now, rather than when the element is garbage collected, and returns whether it had
+/// one. The element's callbacks do nothing afterwards, and it is not created again, so this is for elements that are
+/// done with, like the ones a virtualized list has removed for good. Remove the element from the document first, so
+/// that its [disconnected_callback](crate::CustomElement::disconnected_callback) runs and its scope is cleaned up.
+/// `this` may be either the element or its shadow root.
+pub fn destroy(this: &HtmlElement) -> bool {
+    let host = host(this);
+    let handle = match handle_of(&host) {
+        Some(handle) => handle,
+        None => return false,
+    };
+    forget(&host);
+    js_sys::Reflect::delete_property(&host, &JsValue::from_str("_handle")).unwrap_throw();
+    release(handle)
+}
+
+/// The number of elements defined with this crate that exist, in the document or not, and have not been garbage
+/// collected or [destroyed](destroy). An element that is no longer referenced is counted until the browser collects
+/// it.
+pub fn count() -> usize {
+    TABLE.with(|table| {
+        table
+            .borrow()
+            .slots
+            .iter()
+            .filter(|slot| slot.entry.is_some())
+            .count()
+    })
+}
+
+/// Like [count], for each tag name, in order of tag name.
+pub fn counts() -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    TABLE.with(|table| {
+        for entry in table
+            .borrow()
+            .slots
+            .iter()
+            .filter_map(|slot| slot.entry.as_ref())
+        {
+            match counts
+                .iter_mut()
+                .find(|(tag_name, _)| **tag_name == *entry.tag_name)
+            {
+                Some((_, count)) => *count += 1,
+                None => counts.push((entry.tag_name.to_string(), 1)),
+            }
+        }
+    });
+    counts.sort();
+    counts
+}
+
+/// The elements that [count] counts, whether or not they are in the document. Listing them keeps them from being
+/// garbage collected for as long as the list is kept.
+pub fn elements() -> Vec<HtmlElement> {
+    let entries = TABLE.with(|table| {
+        table
+            .borrow()
+            .slots
+            .iter()
+            .filter_map(|slot| slot.entry.clone())
+            .collect::<Vec<_>>()
+    });
+    entries.iter().filter_map(|entry| entry.element()).collect()
+}
+
+#[wasm_bindgen(module = "/src/make_custom_element.js")]
+extern "C" {
+    fn set_release(release: JsValue);
+
+    fn set_reset(reset: JsValue);
+
+    fn release_when_collected(el: &HtmlElement, handle: u32);
+
+    fn forget(el: &HtmlElement);
+
+    fn weak_ref(value: &JsValue) -> JsValue;
+
+    fn deref(weak: &JsValue) -> JsValue;
+}
@@ -0,0 +1,233 @@
+//! Access to a custom element's [ElementInternals](https://developer.mozilla.org/en-US/docs/Web/API/ElementInternals),
+//! and the features built on it, like [custom states](https://developer.mozilla.org/en-US/docs/Web/API/CustomStateSet)
+//! and constraint validation for [form-associated](crate::CustomElement::form_associated) elements.
+//!
+//! `attachInternals()` can only be called once per element, so this crate calls it the first time the internals are
+//! needed and keeps them on the element; use [internals] rather than calling it yourself.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlElement;
+
+use crate::instances::dispatch;
+use crate::{host, logging};
+
+#[wasm_bindgen]
+extern "C" {
+    /// The internals of a custom element, as returned by `attachInternals()`.
+    #[wasm_bindgen(extends = js_sys::Object)]
+    #[derive(Clone, Debug, PartialEq)]
+    pub type ElementInternals;
+
+    #[wasm_bindgen(method, getter)]
+    fn states(this: &ElementInternals) -> Option<CustomStateSet>;
+
+    #[wasm_bindgen(method, catch, js_name = setValidity)]
+    fn set_validity(
+        this: &ElementInternals,
+        flags: &js_sys::Object,
+        message: &str,
+        anchor: Option<&HtmlElement>,
+    ) -> Result<(), JsValue>;
+
+    #[wasm_bindgen(method, js_name = checkValidity)]
+    fn check_validity(this: &ElementInternals) -> bool;
+
+    #[wasm_bindgen(method, js_name = reportValidity)]
+    fn report_validity(this: &ElementInternals) -> bool;
+
+    #[wasm_bindgen(extends = js_sys::Object)]
+    type CustomStateSet;
+
+    #[wasm_bindgen(method, catch)]
+    fn add(this: &CustomStateSet, state: &str) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(method)]
+    fn delete(this: &CustomStateSet, state: &str) -> bool;
+
+    #[wasm_bindgen(method)]
+    fn has(this: &CustomStateSet, state: &str) -> bool;
+}
+
+/// The element's internals, attaching them on first use.
+///
+/// Returns `None` if the browser does not support `ElementInternals`, or if they cannot be attached,
+/// for example because the `"internals"` feature is [disabled](crate::CustomElement::disabled_features).
+pub fn internals(this: &HtmlElement) -> Option<ElementInternals> {
+    let host = host(this);
+    let key = JsValue::from_str("_internals");
+    let internals = js_sys::Reflect::get(&host, &key).unwrap_throw();
+    if !internals.is_undefined() {
+        return (!internals.is_null()).then(|| internals.unchecked_into());
+    }
+    let internals = js_sys::Reflect::get(&host, &JsValue::from_str("attachInternals"))
+        .unwrap_throw()
+        .dyn_into::<js_sys::Function>()
+        .ok()
+        .and_then(|attach_internals| attach_internals.call0(&host).ok())
+        .map(|internals| internals.unchecked_into::<ElementInternals>());
+    let stored = internals
+        .as_ref()
+        .map(JsValue::from)
+        .unwrap_or(JsValue::NULL);
+    js_sys::Reflect::set(&host, &key, &stored).unwrap_throw();
+    internals
+}
+
+/// Adds or removes a custom state, which CSS can target with the `:state()` pseudo-class,
+/// like `my-component:state(loading)`.
+///
+/// In browsers that do not support custom states, this toggles a `state-<name>` class on the element instead,
+/// so stylesheets that need to support them should also target `my-component.state-loading`.
+pub fn set_state(this: &HtmlElement, state: &str, value: bool) {
+    if let Some(states) = internals(this).and_then(|internals| internals.states()) {
+        let supported = if value {
+            states.add(state).is_ok()
+        } else {
+            states.delete(state);
+            true
+        };
+        if supported {
+            return;
+        }
+    }
+    host(this)
+        .class_list()
+        .toggle_with_force(&format!("state-{}", state), value)
+        .unwrap_throw();
+}
+
+/// Whether the element currently has the given custom state (see [set_state]).
+pub fn has_state(this: &HtmlElement, state: &str) -> bool {
+    internals(this)
+        .and_then(|internals| internals.states())
+        .map(|states| states.has(state))
+        .unwrap_or(false)
+        || host(this)
+            .class_list()
+            .contains(&format!("state-{}", state))
+}
+
+/// Which constraints a form-associated element's value fails, mirroring
+/// [ValidityState](https://developer.mozilla.org/en-US/docs/Web/API/ValidityState).
+/// The default, with every flag `false`, means the value is valid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ValidityFlags {
+    pub value_missing: bool,
+    pub type_mismatch: bool,
+    pub pattern_mismatch: bool,
+    pub too_long: bool,
+    pub too_short: bool,
+    pub range_underflow: bool,
+    pub range_overflow: bool,
+    pub step_mismatch: bool,
+    pub bad_input: bool,
+    pub custom_error: bool,
+}
+
+impl ValidityFlags {
+    /// Whether none of the flags are set.
+    pub fn is_valid(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn to_object(self) -> js_sys::Object {
+        let object = js_sys::Object::new();
+        let flags = [
+            ("valueMissing", self.value_missing),
+            ("typeMismatch", self.type_mismatch),
+            ("patternMismatch", self.pattern_mismatch),
+            ("tooLong", self.too_long),
+            ("tooShort", self.too_short),
+            ("rangeUnderflow", self.range_underflow),
+            ("rangeOverflow", self.range_overflow),
+            ("stepMismatch", self.step_mismatch),
+            ("badInput", self.bad_input),
+            ("customError", self.custom_error),
+        ];
+        for (name, value) in flags {
+            if value {
+                js_sys::Reflect::set(&object, &JsValue::from_str(name), &JsValue::TRUE)
+                    .unwrap_throw();
+            }
+        }
+        object
+    }
+}
+
+/// The result of [validating](crate::CustomElement::validate) a form-associated element.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidityReport {
+    /// The constraints the element's value fails.
+    pub flags: ValidityFlags,
+    /// The message shown to the user when the value is invalid.
+    pub message: String,
+    /// The element, inside the shadow root, that the browser should focus and point to when reporting the problem.
+    pub anchor: Option<HtmlElement>,
+}
+
+impl ValidityReport {
+    /// A report for a valid value.
+    pub fn valid() -> Self {
+        Self::default()
+    }
+
+    /// A report for a value that fails a custom constraint, with the given message.
+    pub fn custom_error(message: impl Into<String>) -> Self {
+        Self {
+            flags: ValidityFlags {
+                custom_error: true,
+                ..ValidityFlags::default()
+            },
+            message: message.into(),
+            anchor: None,
+        }
+    }
+}
+
+/// Sets the validity of a form-associated element. If no flags are set, the element is valid and `message` is ignored.
+///
+/// The browser rejects an invalid value without a `message`, or an `anchor` that isn't inside the element's shadow
+/// root; the error is logged, and the element's validity is left as it was.
+pub fn set_validity(
+    this: &HtmlElement,
+    flags: &ValidityFlags,
+    message: &str,
+    anchor: Option<&HtmlElement>,
+) {
+    if let Some(internals) = internals(this) {
+        let result = if flags.is_valid() {
+            internals.set_validity(&js_sys::Object::new(), "", None)
+        } else {
+            internals.set_validity(&flags.to_object(), message, anchor)
+        };
+        if let Err(error) = result {
+            logging::error("error setting the validity of custom element", &error);
+        }
+    }
+}
+
+/// Whether a form-associated element's value is valid. If it is not, an `invalid` event is fired at the element.
+pub fn check_validity(this: &HtmlElement) -> bool {
+    internals(this)
+        .map(|internals| internals.check_validity())
+        .unwrap_or(true)
+}
+
+/// Like [check_validity], but an invalid value is also reported to the user.
+pub fn report_validity(this: &HtmlElement) -> bool {
+    internals(this)
+        .map(|internals| internals.report_validity())
+        .unwrap_or(true)
+}
+
+/// Re-runs the element's [validate](crate::CustomElement::validate) hook and applies its result. The crate does this
+/// after the element is connected and after each attribute change; call this when the value changes some other way,
+/// like through user input inside the shadow root.
+pub fn revalidate(this: &HtmlElement) {
+    dispatch(&host(this), "validate", &[]);
+}
+
+pub(crate) fn apply_report(this: &HtmlElement, report: ValidityReport) {
+    set_validity(this, &report.flags, &report.message, report.anchor.as_ref());
+}
@@ -0,0 +1,55 @@
+//! Lazily loaded custom elements, for splitting large component libraries into several wasm bundles.
+//!
+//! A lazy element's tag is defined right away with a lightweight placeholder class. The first time one of its
+//! elements is connected, anywhere, including inside a shadow root, its loader runs, and is expected to define the tag
+//! as usual. That definition fills in the placeholder rather than defining a second class, and the elements created
+//! in the meantime are then upgraded, as the browser upgrades undefined elements.
+//!
+//! Since the browser reads a class's `observedAttributes` and statics when it is defined, a lazily defined element's
+//! attributes are watched with a `MutationObserver`, so
+//! [attribute_changed_callback](crate::CustomElement::attribute_changed_callback) runs in a microtask after each
+//! change rather than synchronously, and it can't be [form-associated](crate::CustomElement::form_associated) or a
+//! customized built-in element. The placeholder also matches `:defined`, and `customElements.whenDefined` resolves
+//! before the bundle has loaded; [hide_until_defined](crate::styles::hide_until_defined) still hides the elements
+//! until then.
+
+use wasm_bindgen::prelude::*;
+
+use crate::logging;
+
+/// Defines a placeholder for `tag_name`, and loads the wasm-bindgen module at `module_url` and calls its `define_fn`
+/// export, which should define `tag_name`, the first time an element with that tag is connected.
+///
+/// If the module was built with `wasm-pack --target web`, it is initialized first.
+///
+/// ```rust,ignore
+/// #[wasm_bindgen]
+/// pub fn define_elements() {
+///     MyButton::define("my-button");
+///     define_lazy("my-data-grid", "./data_grid/data_grid.js", "define_data_grid");
+/// }
+/// ```
+pub fn define_lazy(tag_name: &str, module_url: &str, define_fn: &str) {
+    let module_url = module_url.to_string();
+    let define_fn = define_fn.to_string();
+    define_lazy_with(tag_name, move || {
+        let message = format!("failed to load {}", module_url);
+        let on_error = Closure::once(move |error: JsValue| logging::error(&message, &error));
+        let _ = import_and_call(&module_url, &define_fn).catch(&on_error);
+        on_error.forget();
+    });
+}
+
+/// Defines a placeholder for `tag_name`, and calls `loader` the first time an element with that tag is connected.
+/// The loader is responsible for eventually defining the tag.
+pub fn define_lazy_with(tag_name: &str, loader: impl FnOnce() + 'static) {
+    define_lazy_shim(tag_name, Closure::once_into_js(loader));
+}
+
+#[wasm_bindgen(module = "/src/make_custom_element.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = define_lazy)]
+    fn define_lazy_shim(tag_name: &str, loader: JsValue);
+
+    fn import_and_call(module_url: &str, export_name: &str) -> js_sys::Promise;
+}
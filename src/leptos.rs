@@ -0,0 +1,158 @@
+//! An adapter that defines a [Leptos](https://leptos.dev) view as a custom element. Requires the `leptos` feature.
+//!
+//! Each element gets an `RwSignal<Option<String>>` for each of its observed attributes, which the crate sets whenever
+//! the attribute changes, and which are passed to the view function as [AttributeSignals]. The view reads them like
+//! any other signal, so it updates when the attributes do, without any glue code:
+//!
+//! ```rust,ignore
+//! #[wasm_bindgen]
+//! pub fn define_elements() {
+//!     let options = ElementOptions {
+//!         observed_attributes: vec!["name".into()],
+//!         ..Default::default()
+//!     };
+//!     // <hello-name name="world"></hello-name>
+//!     leptos::define("hello-name", options, |attributes| {
+//!         let name = attributes.signal("name");
+//!         view! { <p>"Hello, " {move || name.get().unwrap_or_default()}</p> }
+//!     });
+//! }
+//! ```
+//!
+//! The view is mounted when the element is connected, and unmounted when it is disconnected. The signals belong to
+//! the element rather than the view, so they keep their values while it is disconnected, and the view is created
+//! again with them if it is reconnected.
+
+use std::rc::Rc;
+
+use ::leptos::mount::{mount_to, UnmountHandle};
+use ::leptos::reactive::owner::Owner;
+use ::leptos::reactive::signal::RwSignal;
+use ::leptos::reactive::traits::Set;
+use ::leptos::tachys::view::Render;
+use ::leptos::IntoView;
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlElement;
+
+use crate::{define_boxed, CustomElement, ElementOptions};
+
+/// The signals holding the current values of an element's observed attributes, `None` while an attribute is absent.
+#[derive(Clone, Debug)]
+pub struct AttributeSignals {
+    signals: Vec<(String, RwSignal<Option<String>>)>,
+}
+
+impl AttributeSignals {
+    /// The signal for the observed attribute `name`.
+    ///
+    /// Panics if `name` is not one of the element's observed attributes.
+    pub fn signal(&self, name: &str) -> RwSignal<Option<String>> {
+        self.try_signal(name)
+            .expect_throw("attribute is not observed by this element")
+    }
+
+    /// The signal for the observed attribute `name`, or `None` if the element does not observe it.
+    pub fn try_signal(&self, name: &str) -> Option<RwSignal<Option<String>>> {
+        self.signals
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, signal)| *signal)
+    }
+
+    /// Each observed attribute's name and signal.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, RwSignal<Option<String>>)> {
+        self.signals
+            .iter()
+            .map(|(name, signal)| (name.as_str(), *signal))
+    }
+}
+
+/// Defines a custom element that renders the view returned by `view`, which is called with the element's
+/// [AttributeSignals] each time the element is connected. The attributes in `options.observed_attributes` and
+/// `options.observed_bool_attributes` each get a signal.
+pub fn define<V: IntoView + 'static>(
+    tag_name: &str,
+    options: ElementOptions,
+    view: impl Fn(AttributeSignals) -> V + 'static,
+) {
+    let attributes: Rc<[String]> = options
+        .observed_attributes
+        .iter()
+        .chain(&options.observed_bool_attributes)
+        .cloned()
+        .collect();
+    let view: Rc<dyn Fn(AttributeSignals) -> V> = Rc::new(view);
+    define_boxed(
+        tag_name,
+        options,
+        Box::new(move || Box::new(LeptosElement::new(&attributes, view.clone()))),
+    );
+}
+
+struct LeptosElement<V: IntoView> {
+    view: Rc<dyn Fn(AttributeSignals) -> V>,
+    // owns the attribute signals, for as long as the element exists
+    owner: Owner,
+    signals: AttributeSignals,
+    root: Option<HtmlElement>,
+    mounted: Option<UnmountHandle<<V as Render>::State>>,
+}
+
+impl<V: IntoView + 'static> LeptosElement<V> {
+    fn new(attributes: &[String], view: Rc<dyn Fn(AttributeSignals) -> V>) -> Self {
+        let owner = Owner::new();
+        let signals = owner.with(|| AttributeSignals {
+            signals: attributes
+                .iter()
+                .map(|name| (name.clone(), RwSignal::new(None)))
+                .collect(),
+        });
+        Self {
+            view,
+            owner,
+            signals,
+            root: None,
+            mounted: None,
+        }
+    }
+}
+
+impl<V: IntoView + 'static> CustomElement for LeptosElement<V> {
+    fn inject_children(&mut self, this: &HtmlElement) {
+        // the shadow root, in shadow mode; Leptos only needs to insert nodes into it
+        self.root = Some(this.clone());
+    }
+
+    fn connected_callback(&mut self, _this: &HtmlElement) {
+        if let (None, Some(root)) = (&self.mounted, &self.root) {
+            let view = self.view.clone();
+            let signals = self.signals.clone();
+            self.mounted = Some(
+                self.owner
+                    .with(|| mount_to(root.clone(), move || view(signals))),
+            );
+        }
+    }
+
+    fn disconnected_callback(&mut self, _this: &HtmlElement) {
+        self.mounted = None;
+    }
+
+    fn attribute_changed_callback(
+        &mut self,
+        _this: &HtmlElement,
+        name: String,
+        _old_value: Option<String>,
+        new_value: Option<String>,
+    ) {
+        if let Some(signal) = self.signals.try_signal(&name) {
+            signal.set(new_value);
+        }
+    }
+
+    fn bool_attribute_changed_callback(&mut self, _this: &HtmlElement, name: String, value: bool) {
+        if let Some(signal) = self.signals.try_signal(&name) {
+            signal.set(value.then(String::new));
+        }
+    }
+}
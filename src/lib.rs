@@ -3,7 +3,7 @@
 //! While web_sys exposes the browser’s CustomElementRegistry interface, it can be hard to use. Creating a Custom Element requires calling customElements.define() and passing it an ES2015 class that extends HTMLElement, which is not currently possible to do directly from Rust.
 //!
 //! This crate provides a [CustomElement][CustomElement] trait that, when implemented, allows you to encapsulate any Rust structure as a reusable web component without writing any JavaScript. In theory it should be usable with any Rust front-end framework.
-//! ```rust
+//! ```rust,ignore
 //! impl CustomElement for MyWebComponent {
 //!   fn inject_children(&mut self, this: &HtmlElement) {
 //!       inject_style(&this, "p { color: green; }");
@@ -35,7 +35,7 @@
 //!       log("disconnected");
 //!   }
 //!
-//!   fn adopted_callback(&mut self, _this: &HtmlElement) {
+//!   fn adopted_callback(&mut self, _this: &HtmlElement, _old: Document, _new: Document) {
 //!       log("adopted");
 //!   }
 //! }
@@ -46,22 +46,147 @@
 //! }
 //! ```
 
-use std::sync::{Arc, Mutex};
-
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::UnwrapThrowExt;
-use web_sys::{window, HtmlElement};
+use wasm_bindgen::{JsCast, UnwrapThrowExt};
+use web_sys::{Document, HtmlElement, Node, ShadowRoot, Window};
+
+use crate::host::Host;
+
+pub use capabilities::{capabilities, Capabilities};
+pub use definition::{define_batch, define_boxed, DynCustomElement, ElementOptions, ElementSpec};
+use definition::{
+    define_element, register_constructor, registered_constructor, registered_constructor_in,
+};
+pub use portal::portal;
+pub use sanitize::sanitize_html;
+pub use serialize::serialize;
+
+#[cfg(feature = "dev")]
+mod a11y;
+pub mod animation;
+pub mod attributes;
+pub mod binding;
+pub mod bundle;
+#[cfg(feature = "canvas")]
+pub mod canvas;
+pub mod capabilities;
+pub mod children;
+pub mod composition;
+pub mod csp;
+mod definition;
+#[cfg(feature = "devtools")]
+pub mod devtools;
+pub mod events;
+pub mod fallback;
+#[cfg(feature = "files")]
+pub mod files;
+pub mod fmt;
+pub mod focus;
+#[cfg(feature = "gestures")]
+pub mod gestures;
+pub mod host;
+mod instance;
+pub mod instances;
+pub mod internals;
+pub mod lazy;
+#[cfg(feature = "leptos")]
+pub mod leptos;
+#[cfg(feature = "live")]
+pub mod live;
+pub mod locale;
+mod logging;
+pub mod media;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod panic;
+#[cfg(feature = "persist")]
+pub mod persist;
+#[cfg(feature = "player")]
+pub mod player;
+pub mod pool;
+pub mod popover;
+pub mod portal;
+pub mod positioning;
+pub mod refs;
+pub mod registry;
+pub mod render;
+#[cfg(feature = "router")]
+pub mod router;
+pub mod sanitize;
+pub mod scope;
+pub mod scroll;
+pub mod serialize;
+pub mod shared;
+pub mod shortcuts;
+#[cfg(feature = "signals")]
+pub mod signals;
+pub mod styles;
+mod subscribers;
+#[cfg(feature = "async")]
+pub mod suspense;
+pub mod template;
+#[cfg(feature = "theme")]
+pub mod theme;
+pub mod trusted_types;
+pub mod versions;
+#[cfg(feature = "virtual-list")]
+pub mod virtual_list;
+pub mod visibility;
+#[cfg(feature = "worker")]
+pub mod worker;
+#[cfg(feature = "yew")]
+pub mod yew;
 
 /// A custom DOM element that can be reused via the Web Components/Custom Elements standard.
 ///
-/// Note that your component should implement [Default][std::default::Default], which allows the
-/// browser to initialize a “default” blank component when a new custom element node is created.
-pub trait CustomElement: Default + 'static {
+/// To be defined with [define](CustomElement::define), your component should implement
+/// [Default][std::default::Default], which allows the browser to initialize a “default” blank component when a
+/// new custom element node is created. Components whose construction needs more than that can implement
+/// [CustomElementFactory] instead, or be defined with [define_with_factory](CustomElement::define_with_factory).
+///
+/// The lifecycle callbacks receive the element as an `H`, which is the browser's `HtmlElement` by default. A
+/// component that only uses its element through [Host] can implement `CustomElement<H>` for every `H: Host`, so that
+/// it can also be driven by a [MockElement](crate::mock::MockElement) in native tests; see [host].
+pub trait CustomElement<H: Host = HtmlElement>: Sized + 'static {
     /// Appends children to the root element, either to the shadow root in shadow mode or to the custom element itself.
     /// Per the [Web Components spec](https://html.spec.whatwg.org/multipage/custom-elements.html#custom-element-conformance),
     /// this is deferred to the first invocation of `connectedCallback()`.
     /// It will run before [connected_callback](CustomElement::connected_callback).
-    fn inject_children(&mut self, this: &HtmlElement);
+    ///
+    /// Components whose rendering can fail should implement [try_inject_children](CustomElement::try_inject_children)
+    /// instead.
+    ///
+    /// With the `dev` feature enabled, the crate checks the DOM this creates for common accessibility problems, like
+    /// images without `alt` text or buttons without an accessible name, and logs a warning for each one it finds.
+    fn inject_children(&mut self, _this: &H) {}
+
+    /// A fallible version of [inject_children](CustomElement::inject_children), which it replaces if implemented.
+    /// If it returns an error, the error is passed to [error_callback](CustomElement::error_callback), and the
+    /// [error_fallback](CustomElement::error_fallback) node, if any, replaces whatever was rendered.
+    ///
+    /// Defaults to calling [inject_children](CustomElement::inject_children).
+    fn try_inject_children(&mut self, this: &H) -> Result<(), JsValue> {
+        self.inject_children(this);
+        Ok(())
+    }
+
+    /// Invoked with the error returned by [try_inject_children](CustomElement::try_inject_children).
+    ///
+    /// Defaults to logging the error, to the console or through the `log` or `tracing` crate if the feature of the
+    /// same name is enabled.
+    fn error_callback(&mut self, _this: &H, error: JsValue) {
+        logging::error("error rendering custom element", &error);
+    }
+
+    /// A node to show in place of the component's children when
+    /// [try_inject_children](CustomElement::try_inject_children) fails.
+    ///
+    /// Defaults to `None`, which leaves whatever was rendered before the error.
+    fn error_fallback(&mut self, _this: &H) -> Option<Node> {
+        None
+    }
 
     /// Whether a [Shadow root](https://developer.mozilla.org/en-US/docs/Web/Web_Components/Using_shadow_DOM)
     /// should be attached to the element or not. Shadow DOM encapsulates styles, but makes some DOM manipulation more difficult.
@@ -74,35 +199,224 @@ pub trait CustomElement: Default + 'static {
     /// The names of the attributes whose changes should be observed. If an attribute name is in this list,
     /// [attribute_changed_callback](CustomElement::attribute_changed_callback) will be invoked when it changes.
     /// If it is not, nothing will happen when the DOM attribute changes.
+    ///
+    /// A name ending in `*`, like `data-*`, observes every attribute that starts with what comes before it. The
+    /// browser can't observe those itself, so they are watched with a `MutationObserver`, which means their changes
+    /// are delivered asynchronously, with those made in the same task delivered together, once per attribute.
     fn observed_attributes() -> &'static [&'static str] {
         &[]
     }
 
+    /// The names of the [boolean attributes](attributes) whose changes should be observed. When one of them is
+    /// added or removed, [bool_attribute_changed_callback](CustomElement::bool_attribute_changed_callback) will be
+    /// invoked instead of [attribute_changed_callback](CustomElement::attribute_changed_callback). Changes to their
+    /// value that leave them present are ignored. Names ending in `*` are wildcards, as in
+    /// [observed_attributes](CustomElement::observed_attributes).
+    fn observed_bool_attributes() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Whether every attribute should be observed, as if [observed_attributes](CustomElement::observed_attributes)
+    /// contained the wildcard `*`. Useful for components that don't know their attributes in advance, like one that
+    /// forwards all of its attributes to an inner element. Changes to attributes that aren't listed in
+    /// `observed_attributes` or [observed_bool_attributes](CustomElement::observed_bool_attributes) are delivered
+    /// asynchronously, by a `MutationObserver`.
+    ///
+    /// Defaults to `false`.
+    fn observe_all_attributes() -> bool {
+        false
+    }
+
     /// Invoked when the custom element is instantiated. This can be used to inject any code into the `constructor`,
     /// immediately after it calls `super()`.
-    fn constructor(&mut self, _this: &HtmlElement) {}
+    fn constructor(&mut self, _this: &H) {}
 
     /// Invoked each time the custom element is appended into a document-connected element.
     /// This will happen each time the node is moved, and may happen before the element's contents have been fully parsed.
-    fn connected_callback(&mut self, _this: &HtmlElement) {}
+    fn connected_callback(&mut self, _this: &H) {}
 
     /// Invoked each time the custom element is disconnected from the document's DOM.
-    fn disconnected_callback(&mut self, _this: &HtmlElement) {}
+    fn disconnected_callback(&mut self, _this: &H) {}
 
-    /// Invoked each time the custom element is moved to a new document.
-    fn adopted_callback(&mut self, _this: &HtmlElement) {}
+    /// Whether a disconnection immediately followed by a reconnection, which is what happens when the element is
+    /// moved to a new position in the DOM, should be reported as a single call to
+    /// [moved_callback](CustomElement::moved_callback) instead of calls to
+    /// [disconnected_callback](CustomElement::disconnected_callback) and
+    /// [connected_callback](CustomElement::connected_callback). This lets elements keep expensive resources
+    /// across moves rather than tearing them down and rebuilding them.
+    ///
+    /// When `true`, disconnections are reported asynchronously, once the microtask queue is processed.
+    ///
+    /// Defaults to `false`.
+    fn coalesce_moves() -> bool {
+        false
+    }
+
+    /// Invoked each time the custom element is moved within the DOM,
+    /// if [coalesce_moves](CustomElement::coalesce_moves) is `true`.
+    fn moved_callback(&mut self, _this: &H) {}
+
+    /// Invoked each time the custom element is moved to a new document, with the document it was moved from and the
+    /// one it was moved to, for example to move document-level listeners when it is moved into an iframe.
+    fn adopted_callback(&mut self, _this: &H, _old_document: Document, _new_document: Document) {}
+
+    /// Starts the animation to play before the element is removed with `removeWithTransition()`, a method of the
+    /// generated class that can also be called from Rust with [remove_with_transition]. The element is removed once
+    /// the returned promise settles, like the `finished` promise of an animation started with `this.animate(...)`.
+    ///
+    /// Removing the element any other way, like with `remove()`, skips the animation.
+    ///
+    /// Defaults to `None`, which removes the element immediately.
+    fn leave_animation(&mut self, _this: &H) -> Option<js_sys::Promise> {
+        None
+    }
+
+    /// Invoked once, in a microtask, after one or more calls to [render::request_update], so that several changes
+    /// made in the same task result in a single update. Updates requested while the element is disconnected are
+    /// skipped.
+    fn scheduled_update(&mut self, _this: &H) {}
 
     /// Invoked each time one of the custom element's attributes is added, removed, or changed.
     /// To observe an attribute, include it in [observed_attributes](CustomElement::observed_attributes).
+    ///
+    /// `old_value` is `None` when the attribute has just been added, and `new_value` is `None` when it has just been
+    /// removed. **Breaking change:** versions up to 0.2.1 passed `Some("")` as `old_value` for an added attribute,
+    /// so it could not be told apart from an attribute that had an empty value; code that relied on that should
+    /// use `old_value.unwrap_or_default()`.
     fn attribute_changed_callback(
         &mut self,
-        _this: &HtmlElement,
+        _this: &H,
         _name: String,
         _old_value: Option<String>,
         _new_value: Option<String>,
     ) {
     }
 
+    /// Invoked each time one of the custom element's boolean attributes is added or removed, with whether it is now present.
+    /// To observe a boolean attribute, include it in [observed_bool_attributes](CustomElement::observed_bool_attributes).
+    fn bool_attribute_changed_callback(&mut self, _this: &H, _name: String, _value: bool) {}
+
+    /// Attributes whose changes should be debounced or throttled before they are delivered to
+    /// [attribute_changed_callback](CustomElement::attribute_changed_callback) or
+    /// [bool_attribute_changed_callback](CustomElement::bool_attribute_changed_callback), so that attributes written
+    /// on every keystroke or animation frame do not invoke them each time. Attributes not listed here are delivered
+    /// immediately.
+    ///
+    /// The crate owns the timers: when the element is disconnected, any changes still waiting are delivered
+    /// immediately, before [disconnected_callback](CustomElement::disconnected_callback).
+    ///
+    /// ```rust,ignore
+    /// fn attribute_timing() -> &'static [(&'static str, AttributeTiming)] {
+    ///     &[("search-query", AttributeTiming::Debounce(200))]
+    /// }
+    /// ```
+    fn attribute_timing() -> &'static [(&'static str, attributes::AttributeTiming)] {
+        &[]
+    }
+
+    /// Whether changes to the host's `class` attribute should be delivered to
+    /// [host_class_changed](CustomElement::host_class_changed). Rather than going through `observedAttributes`,
+    /// they are observed with a `MutationObserver` while the element is connected, and batched, so that an element
+    /// whose classes are toggled many times in one task is only notified once.
+    ///
+    /// Defaults to `false`.
+    fn observe_host_class() -> bool {
+        false
+    }
+
+    /// Invoked with the host's `class` attribute when the element is connected, and after each batch of changes to it
+    /// while it is connected, if [observe_host_class](CustomElement::observe_host_class) is `true`.
+    fn host_class_changed(&mut self, _this: &H, _class: String) {}
+
+    /// Like [observe_host_class](CustomElement::observe_host_class), for the host's `style` attribute and
+    /// [host_style_changed](CustomElement::host_style_changed).
+    ///
+    /// Defaults to `false`.
+    fn observe_host_style() -> bool {
+        false
+    }
+
+    /// Invoked with the host's `style` attribute when the element is connected, and after each batch of changes to it
+    /// while it is connected, if [observe_host_style](CustomElement::observe_host_style) is `true`.
+    fn host_style_changed(&mut self, _this: &H, _style: String) {}
+
+    /// Whether instances should be notified of changes to the document's locale through
+    /// [locale_changed_callback](CustomElement::locale_changed_callback).
+    ///
+    /// Defaults to `false`.
+    fn observe_locale() -> bool {
+        false
+    }
+
+    /// Invoked with the document's current locale each time the element is connected, and again each time
+    /// the locale changes while it is connected, if [observe_locale](CustomElement::observe_locale) is `true`.
+    /// See the [locale] module for how the locale is determined.
+    fn locale_changed_callback(&mut self, _this: &H, _locale: String) {}
+
+    /// Whether instances should be notified when the page is hidden or shown through
+    /// [document_visibility_changed](CustomElement::document_visibility_changed).
+    ///
+    /// Defaults to `false`.
+    fn observe_visibility() -> bool {
+        false
+    }
+
+    /// Invoked with whether the page is visible each time the element is connected, and again each time the page is
+    /// hidden or shown while it is connected, if [observe_visibility](CustomElement::observe_visibility) is `true`.
+    /// Media and animation components can use this to pause while the user cannot see them.
+    fn document_visibility_changed(&mut self, _this: &H, _visible: bool) {}
+
+    /// Whether instances should be notified when keyboard focus enters or leaves them through
+    /// [focus_callback](CustomElement::focus_callback) and [blur_callback](CustomElement::blur_callback).
+    ///
+    /// Defaults to `false`.
+    fn observe_focus() -> bool {
+        false
+    }
+
+    /// Invoked when focus moves into the element, or anything inside it, including its shadow root, from outside it,
+    /// if [observe_focus](CustomElement::observe_focus) is `true`. Focus moving between elements inside it is not
+    /// reported.
+    fn focus_callback(&mut self, _this: &H) {}
+
+    /// Invoked when focus leaves the element and everything inside it, if
+    /// [observe_focus](CustomElement::observe_focus) is `true`.
+    fn blur_callback(&mut self, _this: &H) {}
+
+    /// The media queries, like `(prefers-color-scheme: dark)` or `(prefers-reduced-motion)`, whose changes should
+    /// be observed. While an element is connected,
+    /// [media_query_changed_callback](CustomElement::media_query_changed_callback) will be invoked whenever one
+    /// of them starts or stops matching.
+    fn observed_media_queries() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Invoked with each of the [observed_media_queries](CustomElement::observed_media_queries) and whether it
+    /// currently matches each time the element is connected, and again whenever one of them changes while it is connected.
+    fn media_query_changed_callback(&mut self, _this: &H, _query: String, _matches: bool) {}
+
+    /// Whether instances should be notified of changes to the document's URL through
+    /// [route_changed_callback](CustomElement::route_changed_callback). Requires the `router` feature.
+    ///
+    /// Defaults to `false`.
+    #[cfg(feature = "router")]
+    fn observe_route() -> bool {
+        false
+    }
+
+    /// Invoked with the document's current URL each time the element is connected, and again each time
+    /// the URL changes while it is connected, if [observe_route](CustomElement::observe_route) is `true`.
+    /// See the [router] module for the navigation helpers that trigger it.
+    #[cfg(feature = "router")]
+    fn route_changed_callback(&mut self, _this: &H, _url: String) {}
+
+    /// Invoked when a child element registers with this one through
+    /// [register_with_parent](composition::register_with_parent). See the [composition] module.
+    fn child_attached(&mut self, _this: &H, _child: composition::Child) {}
+
+    /// Invoked when a child element that registered with this one is disconnected.
+    fn child_detached(&mut self, _this: &H, _child: composition::Child) {}
+
     /// Specifies the built-in element your element inherits from, if any, by giving its tag name and constructor.
     /// This is only relevant to customized built-in elements, not autonomous custom elements.
     /// [Browser support is inconsistent](https://caniuse.com/custom-elementsv1).
@@ -110,7 +424,7 @@ pub trait CustomElement: Default + 'static {
     /// Defaults to the equivalent of `extends HTMLElement`, which makes for an autonomous custom element.
     ///
     /// To specify your own superclass, import it using `wasm_bindgen`:
-    /// ```
+    /// ```ignore
     /// #[wasm_bindgen]
     /// extern "C" {
     ///     #[wasm_bindgen(js_name = HTMLParagraphElement, js_namespace = window)]
@@ -122,147 +436,315 @@ pub trait CustomElement: Default + 'static {
     ///     }
     /// }
     /// ```
+    #[allow(deprecated)]
     fn superclass() -> (Option<&'static str>, &'static js_sys::Function) {
         (None, &HtmlElementConstructor)
     }
 
+    /// Whether the element is [form-associated](https://html.spec.whatwg.org/multipage/custom-elements.html#form-associated-custom-elements),
+    /// meaning it can participate in forms like a native `<input>`, including in constraint validation through
+    /// [validate](CustomElement::validate).
+    ///
+    /// Defaults to `false`.
+    fn form_associated() -> bool {
+        false
+    }
+
+    /// What [define](CustomElement::define) does if the tag has already been defined, as when two WebAssembly bundles
+    /// on one page both define it. See [bundle].
+    ///
+    /// Defaults to [Coexistence::ErrorIfDefined](bundle::Coexistence::ErrorIfDefined).
+    fn coexistence() -> bundle::Coexistence {
+        bundle::Coexistence::ErrorIfDefined
+    }
+
+    /// Whether the component is only created when the element is first connected, rather than when the element is
+    /// created. Frameworks that clone templates create elements that are never inserted into the document; with lazy
+    /// initialization, those never create a component, or run its [constructor](CustomElement::constructor) and
+    /// [inject_children](CustomElement::inject_children).
+    ///
+    /// Changes to the element's observed attributes before then are not delivered one by one: once the component has
+    /// been created, it gets [attribute_changed_callback](CustomElement::attribute_changed_callback) for each one the
+    /// element has, as if they had just been added.
+    ///
+    /// Defaults to `false`.
+    fn lazy_init() -> bool {
+        false
+    }
+
+    /// Classes, inline styles, and attributes to give the element itself, like a `role` or `display: block`, which
+    /// are applied when it is first connected, before [connected_callback](CustomElement::connected_callback). They
+    /// can't be applied any earlier, since an element must not add attributes to itself in its constructor.
+    /// Attributes and styles that the page already set on the element are left alone.
+    ///
+    /// Defaults to applying nothing.
+    fn host_setup() -> attributes::HostSetup {
+        attributes::HostSetup::new()
+    }
+
+    /// Whether the element has the standard behavior of a disabled control while it has a `disabled` attribute:
+    /// it gets `aria-disabled="true"` and the `disabled` [custom state](internals::set_state), clicks and other
+    /// pointer events on it and inside it are stopped before they reach any listener, and
+    /// [emit_declared](events::emit_declared) fires nothing. `disabled` is observed as a
+    /// [boolean attribute](CustomElement::observed_bool_attributes), so the component is still told when it changes.
+    ///
+    /// Defaults to `false`.
+    fn disableable() -> bool {
+        false
+    }
+
+    /// Checks the value of a [form-associated](CustomElement::form_associated) element against its constraints.
+    /// This is invoked after the element is connected and after each attribute change, and whenever
+    /// [internals::revalidate] is called, and its result becomes the element's validity.
+    ///
+    /// Defaults to always being valid.
+    fn validate(&mut self) -> internals::ValidityReport {
+        internals::ValidityReport::valid()
+    }
+
+    /// A snapshot of the component's state, for debugging, which the [devtools] hook returns when the element is
+    /// inspected. Usually its `Debug` representation: `Some(format!("{:#?}", self))`.
+    ///
+    /// Defaults to `None`.
+    fn inspect(&self) -> Option<String> {
+        None
+    }
+
+    /// The features, `"internals"` and/or `"shadow"`, that should be disabled for this element, as with the
+    /// [`static disabledFeatures`](https://html.spec.whatwg.org/multipage/custom-elements.html#custom-elements-disabled-features)
+    /// class property. Disabling `"internals"` prevents `attachInternals()` from being called on the element, and
+    /// disabling `"shadow"` prevents `attachShadow()` from being called, which also means
+    /// [shadow](CustomElement::shadow) must return `false`.
+    ///
+    /// Defaults to disabling neither.
+    fn disabled_features() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// The events the element fires, which [events::emit_declared] uses to fire them and [events::manifest]
+    /// describes for documentation or typings.
+    ///
+    /// Like built-in elements, the element gets an `on<event>` property for each of these events, and handles
+    /// `on<event>` (or `on-<event>`) attributes, whose value is the body of a handler with an `event` argument:
+    /// `<my-picker onselect-color="console.log(event.detail)">`. Events that the element already has such a property
+    /// for, like `change`, are left to the browser. Handler attributes are compiled like inline scripts, so on pages
+    /// whose Content Security Policy does not allow `unsafe-eval` they are ignored with a warning, and only the
+    /// property works.
+    ///
+    /// ```rust,ignore
+    /// fn events() -> &'static [EventDescriptor] {
+    ///     &[EventDescriptor {
+    ///         description: "The value was committed.",
+    ///         detail: "string",
+    ///         ..EventDescriptor::new("change")
+    ///     }]
+    /// }
+    /// ```
+    fn events() -> &'static [events::EventDescriptor] {
+        &[]
+    }
+
+    /// The attributes the element accepts, and the values each one takes. With the `dev` feature enabled, the crate
+    /// warns when an element is given an attribute that is not listed here (other than global attributes like `id`,
+    /// `aria-*`, or `data-*`) or a value its type does not accept. The schema has no effect otherwise.
+    ///
+    /// ```rust,ignore
+    /// fn attribute_schema() -> &'static [(&'static str, AttributeType)] {
+    ///     &[
+    ///         ("variant", AttributeType::Enum(&["primary", "secondary"])),
+    ///         ("size", AttributeType::Number),
+    ///     ]
+    /// }
+    /// ```
+    fn attribute_schema() -> &'static [(&'static str, attributes::AttributeType)] {
+        &[]
+    }
+
+    /// Static properties to set on the generated class before it is defined, as `(name, value)` pairs.
+    /// This gives access to class-level options that this crate does not otherwise expose, like
+    /// `static formAssociated = true`, including vendor-specific or future ones.
+    ///
+    /// ```rust,ignore
+    /// fn class_statics() -> Vec<(&'static str, JsValue)> {
+    ///     vec![("formAssociated", JsValue::TRUE)]
+    /// }
+    /// ```
+    fn class_statics() -> Vec<(&'static str, JsValue)> {
+        Vec::new()
+    }
+
     /// Must be called somewhere to define the custom element and register it with the DOM Custom Elements Registry.
     ///
     /// Note that custom element names must contain a hyphen.
     ///
-    /// ```rust
+    /// ```rust,ignore
     /// impl CustomElement for MyCustomElement { /* ... */  */}
     /// #[wasm_bindgen]
     /// pub fn define_elements() {
     ///     MyCustomElement::define("my-component");
     /// }
     /// ```
-    fn define(tag_name: &'static str) {
-        // constructor function will be called for each new instance of the component
-        let constructor = Closure::wrap(Box::new(move |this: HtmlElement| {
-            let component = Arc::new(Mutex::new(Self::default()));
-
-            // constructor
-            let cmp = component.clone();
-            let constructor = Closure::wrap(Box::new({
-                move |el| {
-                    let mut lock = cmp.lock().unwrap_throw();
-                    lock.constructor(&el);
-                }
-            }) as Box<dyn FnMut(HtmlElement)>);
-            js_sys::Reflect::set(
-                &this,
-                &JsValue::from_str("_constructor"),
-                &constructor.into_js_value(),
-            )
-            .unwrap_throw();
-
-            // inject_children
-            let cmp = component.clone();
-            let inject_children = Closure::wrap(Box::new({
-                move |el| {
-                    let mut lock = cmp.lock().unwrap_throw();
-                    lock.inject_children(&el);
-                }
-            }) as Box<dyn FnMut(HtmlElement)>);
-            js_sys::Reflect::set(
-                &this,
-                &JsValue::from_str("_injectChildren"),
-                &inject_children.into_js_value(),
-            )
-            .unwrap_throw();
-
-            // connectedCallback
-            let cmp = component.clone();
-            let connected = Closure::wrap(Box::new({
-                move |el| {
-                    let mut lock = cmp.lock().unwrap_throw();
-                    lock.connected_callback(&el);
-                }
-            }) as Box<dyn FnMut(HtmlElement)>);
-            js_sys::Reflect::set(
-                &this,
-                &JsValue::from_str("_connectedCallback"),
-                &connected.into_js_value(),
-            )
-            .unwrap_throw();
-
-            // disconnectedCallback
-            let cmp = component.clone();
-            let disconnected = Closure::wrap(Box::new(move |el| {
-                let mut lock = cmp.lock().unwrap_throw();
-                lock.disconnected_callback(&el);
-            }) as Box<dyn FnMut(HtmlElement)>);
-            js_sys::Reflect::set(
-                &this,
-                &JsValue::from_str("_disconnectedCallback"),
-                &disconnected.into_js_value(),
-            )
-            .unwrap_throw();
-
-            // adoptedCallback
-            let cmp = component.clone();
-            let adopted = Closure::wrap(Box::new(move |el| {
-                let mut lock = cmp.lock().unwrap_throw();
-                lock.adopted_callback(&el);
-            }) as Box<dyn FnMut(HtmlElement)>);
-            js_sys::Reflect::set(
-                &this,
-                &JsValue::from_str("_adoptedCallback"),
-                &adopted.into_js_value(),
-            )
-            .unwrap_throw();
-
-            // attributeChangedCallback
-            let cmp = component;
-            let attribute_changed = Closure::wrap(Box::new(move |el, name, old_value, new_value| {
-                let mut lock = cmp.lock().unwrap_throw();
-                lock.attribute_changed_callback(&el, name, old_value, new_value);
-            })
-                as Box<dyn FnMut(HtmlElement, String, Option<String>, Option<String>)>);
-            js_sys::Reflect::set(
-                &this,
-                &JsValue::from_str("_attributeChangedCallback"),
-                &attribute_changed.into_js_value(),
-            )
-            .unwrap_throw();
-        }) as Box<dyn FnMut(HtmlElement)>);
-
-        // observedAttributes is static and needs to be known when the class is defined
-        let attributes = Self::observed_attributes();
-        let observed_attributes = JsValue::from(
-            attributes
-                .iter()
-                .map(|attr| JsValue::from_str(attr))
-                .collect::<js_sys::Array>(),
-        );
-
-        // call out to JS to define the Custom Element
-        let (super_tag, super_constructor) = Self::superclass();
-        make_custom_element(
-            super_constructor,
-            tag_name,
-            Self::shadow(),
-            constructor.into_js_value(),
-            observed_attributes,
-            super_tag,
-        );
+    fn define(tag_name: &'static str)
+    where
+        Self: CustomElement + CustomElementFactory,
+    {
+        <Self as CustomElement>::define_in(&js_sys::global(), tag_name);
+    }
+
+    /// Like [define](CustomElement::define), but defines the element in the custom element registry of another
+    /// window, like an iframe's `contentWindow`, so that it can be used in that window's document.
+    ///
+    /// The element's class extends that window's `HTMLElement` (or the built-in element of the same name as the
+    /// [superclass](CustomElement::superclass)), since a class can only extend constructors from its own window.
+    /// A component can be defined in several windows; [create_element](CustomElement::create_element) uses the
+    /// class defined in the document's window.
+    ///
+    /// ```rust,ignore
+    /// let frame: HtmlIFrameElement = query(this, "iframe").unwrap_throw();
+    /// MyComponent::define_in_window(&frame.content_window().unwrap_throw(), "my-component");
+    /// ```
+    fn define_in_window(window: &Window, tag_name: &'static str)
+    where
+        Self: CustomElement + CustomElementFactory,
+    {
+        <Self as CustomElement>::define_in(window, tag_name);
+    }
+
+    /// Like [define_in_window](CustomElement::define_in_window), but takes any global object that has a
+    /// `customElements` registry and an `HTMLElement` class, rather than a browser window. This lets components be
+    /// defined and tested in Node-based test runners, with a DOM implementation like jsdom or happy-dom:
+    ///
+    /// ```rust,ignore
+    /// #[wasm_bindgen_test]
+    /// fn renders_label() {
+    ///     // `dom` is a `new JSDOM()`, passed in from the test harness
+    ///     MyButton::define_in(&dom.window(), "my-button");
+    ///     let el = MyButton::create_element(&dom.window().document());
+    ///     // ...
+    /// }
+    /// ```
+    ///
+    /// [define](CustomElement::define) itself uses `globalThis`, so it also works in environments that install a
+    /// DOM implementation's globals there, with no `window`.
+    ///
+    /// Panics if `global` has no `customElements` registry, unless the [fallback] policy says otherwise.
+    fn define_in(global: &JsValue, tag_name: &'static str)
+    where
+        Self: CustomElement + CustomElementFactory,
+    {
+        let constructor =
+            define_element(global, tag_name, ElementOptions::of::<Self>(), Self::create);
+        register_constructor::<Self>(global, constructor);
+    }
+
+    /// Like [define](CustomElement::define), but each instance of the component is created by calling `factory`
+    /// rather than [Default::default], so that components can be given configuration or services when they are created.
+    ///
+    /// `factory` is called once for each element, when the browser constructs it, which includes elements that
+    /// already exist in the document when the tag is defined. Anything it captures is shared by every instance,
+    /// so clone shared state into each component rather than moving it:
+    ///
+    /// ```rust,ignore
+    /// let config = Rc::new(Config::load());
+    /// MyComponent::define_with_factory("my-component", move || MyComponent::new(config.clone()));
+    /// ```
+    fn define_with_factory(tag_name: &'static str, factory: impl Fn() -> Self + 'static)
+    where
+        Self: CustomElement,
+    {
+        let global = js_sys::global();
+        let constructor = define_element(&global, tag_name, ElementOptions::of::<Self>(), factory);
+        register_constructor::<Self>(&global, constructor);
+    }
+
+    /// Like [define](CustomElement::define), but defines a version of the component under a versioned tag name, like
+    /// `x-button-v2` for `("x-button", "2")`, so that a library can ship breaking changes to a component alongside the
+    /// old version. See [versions].
+    ///
+    /// ```rust,ignore
+    /// ButtonV2::define_versioned("x-button", "2");
+    /// ```
+    fn define_versioned(tag_name: &str, version: &str)
+    where
+        Self: CustomElement + CustomElementFactory,
+    {
+        let options = ElementOptions::of::<Self>();
+        let tag_name = versions::register(tag_name, version, options.superclass_tag.as_deref());
+        let global = js_sys::global();
+        let constructor = define_element(&global, &tag_name, options, Self::create);
+        register_constructor::<Self>(&global, constructor);
+    }
+
+    /// The JavaScript class generated for this component when it was [defined](CustomElement::define).
+    /// If it was defined in more than one window, this is the class it was defined with first.
+    ///
+    /// Panics if the component has not been defined yet.
+    fn constructor_js() -> js_sys::Function
+    where
+        Self: CustomElement,
+    {
+        registered_constructor::<Self>().expect_throw("custom element has not been defined")
+    }
+
+    /// Creates a new, upgraded instance of this custom element by calling its class with `new`. If the component was
+    /// [defined in](CustomElement::define_in) `document`'s window, the class defined there is used; otherwise,
+    /// the element is created with [constructor_js](CustomElement::constructor_js) and then adopted into `document`.
+    ///
+    /// Panics if the component has not been defined yet.
+    fn create_element(document: &Document) -> HtmlElement
+    where
+        Self: CustomElement,
+    {
+        let constructor = document
+            .default_view()
+            .and_then(|window| registered_constructor_in::<Self>(&window))
+            .unwrap_or_else(<Self as CustomElement>::constructor_js);
+        let element: HtmlElement = js_sys::Reflect::construct(&constructor, &js_sys::Array::new())
+            .unwrap_throw()
+            .unchecked_into();
+        if element.owner_document().as_ref() != Some(document) {
+            document.adopt_node(&element).unwrap_throw();
+        }
+        element
+    }
+}
+
+/// Creates the component for each new instance of a custom element defined with [define](CustomElement::define).
+///
+/// Every type that implements [Default] has this implementation. Implement it yourself for components whose
+/// construction needs something else, like loading configuration, generating a random ID, or looking up a service:
+///
+/// ```rust,ignore
+/// impl CustomElementFactory for MyComponent {
+///     fn create() -> Self {
+///         MyComponent::new(services::get::<Api>())
+///     }
+/// }
+/// ```
+pub trait CustomElementFactory {
+    fn create() -> Self;
+}
+
+impl<T: Default> CustomElementFactory for T {
+    fn create() -> Self {
+        T::default()
     }
 }
 
 /// Attaches a `<style>` element with the given content to the element,
 /// either to its shadow root (if it exists) or to the custom element itself.
+/// It is given the page's [CSP nonce](csp), if there is one.
 ///
 /// This is an optional helper function; if you use it, you probably want it somewhere
 /// in your [inject_children](CustomElement::inject_children) function.
 pub fn inject_style(this: &HtmlElement, style: &str) {
-    let style_el = window()
-        .unwrap_throw()
-        .document()
+    let style_el = this
+        .owner_document()
         .unwrap_throw()
         .create_element("style")
         .unwrap_throw();
-    style_el.set_inner_html(style);
+    csp::apply(&style_el);
+    style_el.set_text_content(Some(style));
     match this.shadow_root() {
         Some(shadow_root) => shadow_root.append_child(&style_el).unwrap_throw(),
         None => this.append_child(&style_el).unwrap_throw(),
@@ -271,39 +753,118 @@ pub fn inject_style(this: &HtmlElement, style: &str) {
 
 /// Attaches a `<link rel="stylesheet">` element with the given URL to the custom element,
 /// either to its shadow root (if it exists) or to the custom element itself.
+/// It is given the page's [CSP nonce](csp), if there is one.
 ///
 /// This is an optional helper function; if you use it, you probably want it somewhere
 /// in your [inject_children](CustomElement::inject_children) function.
 pub fn inject_stylesheet(this: &HtmlElement, url: &str) {
-    let style_el = window()
-        .unwrap_throw()
-        .document()
+    let style_el = this
+        .owner_document()
         .unwrap_throw()
         .create_element("link")
         .unwrap_throw();
     style_el.set_attribute("rel", "stylesheet").unwrap_throw();
     style_el.set_attribute("href", url).unwrap_throw();
+    csp::apply(&style_el);
     match this.shadow_root() {
         Some(shadow_root) => shadow_root.append_child(&style_el).unwrap_throw(),
         None => this.append_child(&style_el).unwrap_throw(),
     };
 }
 
-// JavaScript shim
-#[wasm_bindgen(module = "/src/make_custom_element.js")]
-extern "C" {
-    fn make_custom_element(
-        superclass: &js_sys::Function,
-        tag_name: &str,
-        shadow: bool,
-        constructor: JsValue,
-        observed_attributes: JsValue,
-        superclass_tag: Option<&str>,
-    );
+/// Plays the element's [leave animation](CustomElement::leave_animation) and then removes it from the document,
+/// by calling its `removeWithTransition()` method. The returned promise resolves once the element has been removed.
+///
+/// Calling this again while the animation is playing returns the same promise.
+pub fn remove_with_transition(this: &HtmlElement) -> js_sys::Promise {
+    let host = host(this);
+    js_sys::Reflect::get(&host, &JsValue::from_str("removeWithTransition"))
+        .unwrap_throw()
+        .unchecked_into::<js_sys::Function>()
+        .call0(&host)
+        .unwrap_throw()
+        .unchecked_into()
+}
+
+/// Returns the first element matching `selector` inside the component, searching its shadow root if it has one and
+/// the custom element itself otherwise, or `None` if there is no match or it is not a `T`.
+///
+/// ```rust,ignore
+/// let input: HtmlInputElement = query(this, "input[name=email]").unwrap_throw();
+/// ```
+pub fn query<T: JsCast>(this: &HtmlElement, selector: &str) -> Option<T> {
+    let host = host(this);
+    match host.shadow_root() {
+        Some(shadow_root) => shadow_root.query_selector(selector),
+        None => host.query_selector(selector),
+    }
+    .unwrap_throw()?
+    .dyn_into()
+    .ok()
+}
+
+/// Returns every element matching `selector` inside the component, like [query]. Matches that are not a `T` are skipped.
+pub fn query_all<T: JsCast>(this: &HtmlElement, selector: &str) -> Vec<T> {
+    let host = host(this);
+    let nodes = match host.shadow_root() {
+        Some(shadow_root) => shadow_root.query_selector_all(selector),
+        None => host.query_selector_all(selector),
+    }
+    .unwrap_throw();
+    (0..nodes.length())
+        .filter_map(|index| nodes.get(index)?.dyn_into().ok())
+        .collect()
+}
+
+// The tag name the element was defined with, which for a customized built-in element is its `is` attribute.
+pub(crate) fn defined_tag_name(this: &HtmlElement) -> String {
+    let host = host(this);
+    let tag_name = host.local_name();
+    if tag_name.contains('-') {
+        tag_name
+    } else {
+        host.get_attribute("is").unwrap_or(tag_name)
+    }
+}
+
+// In shadow mode, `inject_children` is handed the shadow root rather than the element itself;
+// helpers that need the custom element use this to find it either way.
+pub(crate) fn host(this: &HtmlElement) -> HtmlElement {
+    match this.dyn_ref::<ShadowRoot>() {
+        Some(shadow_root) => shadow_root.host().unchecked_into(),
+        None => this.clone(),
+    }
+}
+
+// The document `node` belongs to, or `node` itself if it is a document.
+pub(crate) fn document_of(node: &Node) -> Document {
+    match node.owner_document() {
+        Some(document) => document,
+        None => node.clone().unchecked_into(),
+    }
 }
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_name = HTMLElement, js_namespace = window)]
-    pub static HtmlElementConstructor: js_sys::Function;
+// The window of the document `node` belongs to, which for an element in an iframe is the iframe's window rather than
+// the one the crate runs in. A document without a window, like a template's, uses the crate's.
+pub(crate) fn window_of(node: &Node) -> Window {
+    document_of(node)
+        .default_view()
+        .or_else(web_sys::window)
+        .unwrap_throw()
+}
+
+#[allow(deprecated)]
+pub use constructors::HtmlElementConstructor;
+
+// `HtmlElementConstructor` predates `thread_local_v2` statics; it is kept as a `&'static` for `superclass()`
+mod constructors {
+    #![allow(deprecated, non_upper_case_globals)]
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        // looked up on `globalThis` rather than `window`, which test environments like jsdom may not define
+        #[wasm_bindgen(js_name = HTMLElement)]
+        pub static HtmlElementConstructor: js_sys::Function;
+    }
 }
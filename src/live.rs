@@ -0,0 +1,262 @@
+//! Live data over a WebSocket or server-sent events, for as long as a component is connected. Requires the `live`
+//! feature.
+//!
+//! [subscribe] opens a connection, parses each message it receives as JSON into a `T`, and calls its callback with
+//! it. The connection is closed when the custom element is disconnected, so subscriptions are usually made in
+//! [connected_callback](crate::CustomElement::connected_callback). If the connection drops in the meantime, it is
+//! reopened after a delay that grows with each failed attempt, as set by [Backoff]:
+//!
+//! ```rust,ignore
+//! #[derive(Deserialize)]
+//! struct Quote { symbol: String, price: f64 }
+//!
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     let price = self.price.clone();
+//!     live::subscribe(this, Source::WebSocket("wss://example.com/quotes".into()), Backoff::default(), move |quote: Quote| {
+//!         price.set_data(&format!("{}: {:.2}", quote.symbol, quote.price));
+//!     });
+//! }
+//! ```
+//!
+//! Only text messages are delivered. Messages that are not valid JSON for a `T` are logged and skipped; use
+//! [subscribe_text] to handle messages that are not JSON.
+
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, EventSource, HtmlElement, MessageEvent, WebSocket};
+
+use crate::logging;
+use crate::scope::Scope;
+
+/// Where live data comes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// A WebSocket, at a `ws:` or `wss:` URL.
+    WebSocket(String),
+    /// A stream of [server-sent events](https://developer.mozilla.org/en-US/docs/Web/API/Server-sent_events), whose
+    /// unnamed `message` events are delivered.
+    EventSource(String),
+}
+
+/// How long to wait before reopening a dropped connection: `initial_ms` after the first failure, multiplied by
+/// `factor` after each failure after that, up to `max_ms`. The delay starts over once a connection opens.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Backoff {
+    pub initial_ms: f64,
+    pub max_ms: f64,
+    pub factor: f64,
+}
+
+impl Default for Backoff {
+    /// Half a second, doubling up to 30 seconds.
+    fn default() -> Self {
+        Backoff {
+            initial_ms: 500.0,
+            max_ms: 30_000.0,
+            factor: 2.0,
+        }
+    }
+}
+
+impl Backoff {
+    fn delay(&self, attempt: u32) -> f64 {
+        (self.initial_ms * self.factor.powi(attempt as i32)).min(self.max_ms)
+    }
+}
+
+enum Socket {
+    WebSocket(WebSocket),
+    EventSource(EventSource),
+}
+
+type Handler = Closure<dyn FnMut(JsValue)>;
+
+struct Connection {
+    source: Source,
+    backoff: Backoff,
+    on_message: RefCell<Box<dyn FnMut(String)>>,
+    socket: RefCell<Option<Socket>>,
+    // the handlers of the open socket, kept so that they can be dropped once it is closed
+    handlers: RefCell<Vec<Handler>>,
+    attempt: Cell<u32>,
+    retry: Cell<Option<i32>>,
+    closed: Cell<bool>,
+}
+
+impl Connection {
+    fn open(self: &Rc<Self>) {
+        if self.closed.get() {
+            return;
+        }
+        let weak = Rc::downgrade(self);
+        let handler = |weak: &Weak<Self>, f: fn(&Rc<Self>, JsValue)| {
+            let weak = weak.clone();
+            Closure::wrap(Box::new(move |event: JsValue| {
+                if let Some(connection) = weak.upgrade() {
+                    f(&connection, event);
+                }
+            }) as Box<dyn FnMut(JsValue)>)
+        };
+        let on_open = handler(&weak, |connection, _| connection.attempt.set(0));
+        let on_message = handler(&weak, |connection, event| {
+            if let Some(text) = event.unchecked_into::<MessageEvent>().data().as_string() {
+                (connection.on_message.borrow_mut())(text);
+            }
+        });
+        let on_close = handler(&weak, |connection, _| connection.reopen_later());
+        let socket = match &self.source {
+            Source::WebSocket(url) => {
+                let socket = WebSocket::new(url).unwrap_throw();
+                socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+                socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+                socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+                Socket::WebSocket(socket)
+            }
+            Source::EventSource(url) => {
+                let source = EventSource::new(url).unwrap_throw();
+                source.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+                source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+                // an event source reconnects by itself, unless the error closed it
+                source.set_onerror(Some(on_close.as_ref().unchecked_ref()));
+                Socket::EventSource(source)
+            }
+        };
+        self.socket.replace(Some(socket));
+        self.handlers.replace(vec![on_open, on_message, on_close]);
+    }
+
+    fn reopen_later(self: &Rc<Self>) {
+        let closed_by_error = match &*self.socket.borrow() {
+            Some(Socket::EventSource(source)) => source.ready_state() == EventSource::CLOSED,
+            _ => true,
+        };
+        if self.closed.get() || !closed_by_error {
+            return;
+        }
+        self.close_socket();
+        let delay = self.backoff.delay(self.attempt.get());
+        self.attempt.set(self.attempt.get() + 1);
+        let weak = Rc::downgrade(self);
+        let reopen = Closure::once_into_js(move || {
+            if let Some(connection) = weak.upgrade() {
+                connection.retry.set(None);
+                connection.open();
+            }
+        });
+        let handle = window()
+            .unwrap_throw()
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                reopen.unchecked_ref(),
+                delay as i32,
+            )
+            .unwrap_throw();
+        self.retry.set(Some(handle));
+    }
+
+    fn close_socket(&self) {
+        match self.socket.borrow_mut().take() {
+            Some(Socket::WebSocket(socket)) => {
+                socket.set_onopen(None);
+                socket.set_onmessage(None);
+                socket.set_onclose(None);
+                socket.close().unwrap_throw();
+            }
+            Some(Socket::EventSource(source)) => {
+                source.set_onopen(None);
+                source.set_onmessage(None);
+                source.set_onerror(None);
+                source.close();
+            }
+            None => {}
+        }
+        self.handlers.borrow_mut().clear();
+    }
+
+    fn close(&self) {
+        self.closed.set(true);
+        if let Some(handle) = self.retry.take() {
+            window().unwrap_throw().clear_timeout_with_handle(handle);
+        }
+        self.close_socket();
+    }
+}
+
+/// A live connection opened with [subscribe] or [subscribe_text]. Dropping it does not close the connection, which
+/// stays open until the element is disconnected or [close](Live::close) is called.
+pub struct Live {
+    connection: Rc<Connection>,
+}
+
+impl Live {
+    /// Sends a message, serialized as JSON, over a WebSocket. Returns `false`, without sending it, if the source is
+    /// not a WebSocket or it is not open.
+    pub fn send<M: Serialize>(&self, message: &M) -> bool {
+        let value = serde_wasm_bindgen::to_value(message).unwrap_throw();
+        let text = js_sys::JSON::stringify(&value).unwrap_throw();
+        self.send_text(&String::from(text))
+    }
+
+    /// Sends a text message over a WebSocket, like [send](Live::send).
+    pub fn send_text(&self, text: &str) -> bool {
+        match &*self.connection.socket.borrow() {
+            Some(Socket::WebSocket(socket)) if socket.ready_state() == WebSocket::OPEN => {
+                socket.send_with_str(text).is_ok()
+            }
+            _ => false,
+        }
+    }
+
+    /// Closes the connection for good, before the element is disconnected.
+    pub fn close(&self) {
+        self.connection.close();
+    }
+}
+
+/// Opens a connection to `source`, and calls `on_message` with each message it receives, parsed as JSON, until the
+/// element is disconnected. `this` may be either the element or its shadow root. See the
+/// [module documentation](self).
+pub fn subscribe<T: DeserializeOwned>(
+    this: &HtmlElement,
+    source: Source,
+    backoff: Backoff,
+    mut on_message: impl FnMut(T) + 'static,
+) -> Live {
+    subscribe_text(this, source, backoff, move |text| {
+        let parsed = js_sys::JSON::parse(&text)
+            .and_then(|value| serde_wasm_bindgen::from_value(value).map_err(JsValue::from));
+        match parsed {
+            Ok(message) => on_message(message),
+            Err(error) => logging::error("invalid live message", &error),
+        }
+    })
+}
+
+/// Like [subscribe], but calls `on_message` with the text of each message as it is.
+pub fn subscribe_text(
+    this: &HtmlElement,
+    source: Source,
+    backoff: Backoff,
+    on_message: impl FnMut(String) + 'static,
+) -> Live {
+    let connection = Rc::new(Connection {
+        source,
+        backoff,
+        on_message: RefCell::new(Box::new(on_message)),
+        socket: RefCell::new(None),
+        handlers: RefCell::new(Vec::new()),
+        attempt: Cell::new(0),
+        retry: Cell::new(None),
+        closed: Cell::new(false),
+    });
+    connection.open();
+    Scope::new(this).on_cleanup({
+        let connection = connection.clone();
+        move || connection.close()
+    });
+    Live { connection }
+}
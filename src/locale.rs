@@ -0,0 +1,150 @@
+//! A document-level locale shared by all custom elements defined by this crate.
+//!
+//! The locale is the `lang` attribute of the document element (`<html lang="...">`), or the browser's
+//! preferred language if that is not set. Every element whose type returns `true` from
+//! [observe_locale](crate::CustomElement::observe_locale) has its
+//! [locale_changed_callback](crate::CustomElement::locale_changed_callback) invoked when it changes,
+//! through a single `MutationObserver` shared by all elements in the same document.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Document, Event, HtmlElement, MutationObserver, MutationObserverInit};
+
+use crate::document_of;
+use crate::subscribers::Subscribers;
+
+// The elements observing the locale of one document, and the observer of its `lang` attribute.
+struct Observed {
+    document: Document,
+    subscribers: Rc<Subscribers>,
+    observer: MutationObserver,
+    _callback: Closure<dyn FnMut()>,
+    listener: Closure<dyn FnMut(Event)>,
+}
+
+thread_local! {
+    static DOCUMENTS: RefCell<Vec<Observed>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The current locale of the document.
+pub fn current_locale() -> String {
+    locale_of(&window().unwrap_throw().document().unwrap_throw())
+}
+
+fn locale_of(document: &Document) -> String {
+    let lang = document
+        .document_element()
+        .and_then(|root| root.get_attribute("lang"))
+        .filter(|lang| !lang.is_empty());
+    lang.or_else(|| {
+        document
+            .default_view()
+            .or_else(window)
+            .and_then(|window| window.navigator().language())
+    })
+    .unwrap_or_default()
+}
+
+/// Sets the `lang` attribute of the document element, which notifies all elements observing the locale.
+pub fn set_locale(locale: &str) {
+    window()
+        .unwrap_throw()
+        .document()
+        .unwrap_throw()
+        .document_element()
+        .unwrap_throw()
+        .set_attribute("lang", locale)
+        .unwrap_throw();
+}
+
+// Registers a connected element and immediately delivers the locale of its document to it.
+pub(crate) fn subscribe(this: &HtmlElement) {
+    let document = document_of(this);
+    let subscribers = DOCUMENTS.with(|documents| {
+        let mut documents = documents.borrow_mut();
+        let index = match documents
+            .iter()
+            .position(|observed| observed.document == document)
+        {
+            Some(index) => index,
+            None => {
+                documents.push(observe(&document));
+                documents.len() - 1
+            }
+        };
+        documents[index].subscribers.clone()
+    });
+    subscribers.subscribe(this);
+    subscribers.deliver(this, &[&JsValue::from_str(&locale_of(&document))]);
+}
+
+// Unregisters a disconnected element, and stops observing each document in which no connected element observes the
+// locale.
+pub(crate) fn unsubscribe(this: &HtmlElement) {
+    let unused = DOCUMENTS.with(|documents| {
+        let mut documents = documents.borrow_mut();
+        for observed in documents.iter() {
+            observed.subscribers.unsubscribe(this);
+        }
+        let (unused, used) = documents
+            .drain(..)
+            .partition(|observed| observed.subscribers.is_empty());
+        *documents = used;
+        unused
+    });
+    // dropped, with their closures, once the list is no longer borrowed
+    for observed in unused {
+        observed.observer.disconnect();
+        if let Some(window) = observed.document.default_view() {
+            window
+                .remove_event_listener_with_callback(
+                    "languagechange",
+                    observed.listener.as_ref().unchecked_ref(),
+                )
+                .unwrap_throw();
+        }
+    }
+}
+
+// Sets up the observer of the `lang` attribute of `document`, the first time any element in it subscribes.
+fn observe(document: &Document) -> Observed {
+    let subscribers = Rc::new(Subscribers::new("localeChangedCallback"));
+    let last = Rc::new(RefCell::new(locale_of(document)));
+    let notify = {
+        let (document, subscribers) = (document.clone(), subscribers.clone());
+        move || {
+            let locale = locale_of(&document);
+            if *last.borrow() != locale {
+                *last.borrow_mut() = locale.clone();
+                subscribers.notify(&[&JsValue::from_str(&locale)]);
+            }
+        }
+    };
+
+    let callback = Closure::wrap(Box::new(notify.clone()) as Box<dyn FnMut()>);
+    let observer = MutationObserver::new(callback.as_ref().unchecked_ref()).unwrap_throw();
+    let options = MutationObserverInit::new();
+    options.set_attributes(true);
+    options.set_attribute_filter(&js_sys::Array::of1(&JsValue::from_str("lang")));
+    observer
+        .observe_with_options(&document.document_element().unwrap_throw(), &options)
+        .unwrap_throw();
+
+    // the browser's preferred language is only used as a fallback, but can change too
+    let listener = Closure::wrap(Box::new(move |_: Event| notify()) as Box<dyn FnMut(Event)>);
+    if let Some(window) = document.default_view() {
+        window
+            .add_event_listener_with_callback("languagechange", listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+    }
+    Observed {
+        document: document.clone(),
+        subscribers,
+        observer,
+        _callback: callback,
+        listener,
+    }
+}
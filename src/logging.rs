@@ -0,0 +1,53 @@
+// The crate's diagnostics. With the `log` feature they are emitted through the `log` crate, and with the `tracing`
+// feature through `tracing`, so that they follow the application's own logging setup; errors are otherwise written
+// to the console, and everything else is dropped.
+//
+// Everything is logged under the `custom_elements` target.
+
+use wasm_bindgen::JsValue;
+
+// Entered while a lifecycle callback runs, so that anything logged by the component is attributed to it.
+pub(crate) struct CallbackSpan {
+    #[cfg(feature = "tracing")]
+    _span: tracing::span::EnteredSpan,
+}
+
+pub(crate) fn callback(tag_name: &str, callback: &'static str) -> CallbackSpan {
+    #[cfg(feature = "log")]
+    log::debug!(target: "custom_elements", "<{}> {}", tag_name, callback);
+    #[cfg(not(any(feature = "log", feature = "tracing")))]
+    let _ = (tag_name, callback);
+    CallbackSpan {
+        #[cfg(feature = "tracing")]
+        _span: tracing::debug_span!(target: "custom_elements", "callback", tag_name, callback)
+            .entered(),
+    }
+}
+
+// A callback that arrived while the component was busy with another one, and will run once it has finished.
+pub(crate) fn queued(tag_name: &str, callback: &'static str) {
+    #[cfg(feature = "log")]
+    log::debug!(target: "custom_elements", "<{}> {} queued", tag_name, callback);
+    #[cfg(feature = "tracing")]
+    tracing::debug!(target: "custom_elements", tag_name, callback, "queued");
+    #[cfg(not(any(feature = "log", feature = "tracing")))]
+    let _ = (tag_name, callback);
+}
+
+pub(crate) fn error(message: &str, error: &JsValue) {
+    #[cfg(feature = "log")]
+    log::error!(target: "custom_elements", "{}: {:?}", message, error);
+    #[cfg(feature = "tracing")]
+    tracing::error!(target: "custom_elements", ?error, "{}", message);
+    #[cfg(not(any(feature = "log", feature = "tracing")))]
+    web_sys::console::error_2(&JsValue::from_str(message), error);
+}
+
+pub(crate) fn warn(message: &str) {
+    #[cfg(feature = "log")]
+    log::warn!(target: "custom_elements", "{}", message);
+    #[cfg(feature = "tracing")]
+    tracing::warn!(target: "custom_elements", "{}", message);
+    #[cfg(not(any(feature = "log", feature = "tracing")))]
+    web_sys::console::warn_1(&JsValue::from_str(message));
+}
@@ -1,58 +1,683 @@
-export function make_custom_element(
-  superclass,
-  tag_name,
-  shadow,
-  constructor,
-  observedAttributes,
-  superclassTag
-) {
+// runs the teardown functions registered through `Scope::on_cleanup`
+function runCleanups(el) {
+  const cleanups = el._cleanups || [];
+  el._cleanups = [];
+  cleanups.forEach((cleanup) => cleanup());
+}
+
+// runs one of the teardown functions now, rather than on disconnect, if it has not run yet
+export function run_cleanup(el, cleanup) {
+  const cleanups = el._cleanups || [];
+  const index = cleanups.indexOf(cleanup);
+  if (index !== -1) {
+    cleanups.splice(index, 1);
+    cleanup();
+  }
+}
+
+// registers a function to be called with the element each time it is connected, after its `connected_callback`
+export function on_connect(el, hook) {
+  el._connectHooks = el._connectHooks || [];
+  el._connectHooks.push(hook);
+}
+
+// the tag name an element was defined with, which for a customized built-in element is its `is` attribute
+function tagName(el) {
+  return el.localName.includes("-") ? el.localName : el.getAttribute("is") || el.localName;
+}
+
+// reports a failed lifecycle callback to the page, with a `ce-error` event from the element
+export function dispatch_error(el, phase, error) {
+  const message = error instanceof Error ? error.message : String(error);
+  el.dispatchEvent(
+    new CustomEvent("ce-error", {
+      bubbles: true,
+      composed: true,
+      detail: { tag: tagName(el), phase, message, error },
+    })
+  );
+}
+
+// resets an element's component, and the panic hook's record of the callbacks running, after one of its callbacks
+// panics or throws; WebAssembly doesn't unwind, so the crate can't do it itself
+let reset = null;
+
+export function set_reset(resetHandle) {
+  reset = resetHandle;
+}
+
+// runs one of the component's callbacks, reporting it if it throws, which is also how a Rust panic surfaces; the
+// component has already been reset by `_dispatch`
+function guard(el, phase, callback) {
+  try {
+    return callback();
+  } catch (error) {
+    dispatch_error(el, phase, error);
+    throw error;
+  }
+}
+
+// releases each element's handle, which frees its Rust component and callbacks, once the element has been garbage
+// collected; without `FinalizationRegistry`, they are never freed
+let release = null;
+const finalizers =
+  typeof FinalizationRegistry === "function" ? new FinalizationRegistry((handle) => release(handle)) : null;
+
+export function set_release(releaseHandle) {
+  release = releaseHandle;
+}
+
+export function release_when_collected(el, handle) {
+  if (finalizers) {
+    finalizers.register(el, handle, el);
+  }
+}
+
+// stops waiting for an element that has been released early to be collected
+export function forget(el) {
+  if (finalizers) {
+    finalizers.unregister(el);
+  }
+}
+
+// a reference to `value` that doesn't keep it from being collected, or null where there are no weak references
+export function weak_ref(value) {
+  return typeof WeakRef === "function" ? new WeakRef(value) : null;
+}
+
+export function deref(weak) {
+  return weak ? weak.deref() : undefined;
+}
+
+// stylesheets registered with `styles::register_global`, and the shadow roots they are adopted into
+const globalStyles = [];
+let shadowRoots = [];
+let sweepAt = 64;
+
+function trackShadowRoot(root) {
+  // without weak references the roots are not tracked, rather than kept alive, so they only get the styles
+  // registered before they were created
+  if (typeof WeakRef !== "function") {
+    return;
+  }
+  shadowRoots.push(new WeakRef(root));
+  // drop the references to collected roots from time to time, so the list does not grow forever
+  if (shadowRoots.length >= sweepAt) {
+    shadowRoots = shadowRoots.filter((ref) => ref.deref());
+    sweepAt = Math.max(64, shadowRoots.length * 2);
+  }
+}
+
+function adoptGlobalStyles(root, styles) {
+  // constructed stylesheets can only be adopted into the document they were created in, not an iframe's
+  const sameDocument = root.ownerDocument === globalThis.document;
+  if (sameDocument && "adoptedStyleSheets" in root && globalStyles.every((style) => style.sheet)) {
+    const sheets = styles.map((style) => style.sheet);
+    const own = root.adoptedStyleSheets.filter((sheet) => !sheets.includes(sheet));
+    root.adoptedStyleSheets = [...globalStyles.map((style) => style.sheet), ...own];
+  } else {
+    // without constructable stylesheets, each root gets its own copies, after earlier ones but before its own styles
+    const copies = root.querySelectorAll("style[data-global-style]");
+    const next = copies.length ? copies[copies.length - 1].nextSibling : root.firstChild;
+    for (const style of styles) {
+      const el = root.ownerDocument.createElement("style");
+      el.dataset.globalStyle = "";
+      if (style.nonce) {
+        el.nonce = style.nonce;
+      }
+      el.textContent = style.css;
+      root.insertBefore(el, next);
+    }
+  }
+}
+
+export function register_global_style(css, nonce) {
+  let sheet = null;
+  if (typeof CSSStyleSheet === "function" && "replaceSync" in CSSStyleSheet.prototype) {
+    sheet = new CSSStyleSheet();
+    sheet.replaceSync(css);
+  }
+  const style = { css, sheet, nonce };
+  globalStyles.push(style);
+  shadowRoots = shadowRoots.filter((ref) => ref.deref());
+  for (const ref of shadowRoots) {
+    const root = ref.deref();
+    if (root) {
+      adoptGlobalStyles(root, [style]);
+    }
+  }
+}
+
+// the handler an `on<event>` attribute describes, which has to be compiled from its value like the browser does for
+// built-in elements; a Content Security Policy without `unsafe-eval` forbids that, so the attribute is then ignored,
+// and handlers can only be set through the property
+function handlerFromAttribute(el, name, type, body) {
+  try {
+    return new Function("event", body);
+  } catch (error) {
+    if (error instanceof EvalError) {
+      console.warn(
+        `the ${name} attribute of <${tagName(el)}> was ignored, since the page's Content Security Policy does not ` +
+          `allow compiling it; set the on${type} property to a function instead`
+      );
+      return null;
+    }
+    throw error;
+  }
+}
+
+// the base classes of the elements this crate defines, one for each superclass, shared by every element that extends
+// it; their methods read the element's own configuration from the static `_rust` of its class
+const baseClasses = new WeakMap();
+
+function baseClass(superclass) {
+  if (baseClasses.has(superclass)) {
+    return baseClasses.get(superclass);
+  }
+  const base = class extends superclass {
+    constructor() {
+      super();
+      // a lazily initialized element only creates its component once it is first connected, which elements cloned
+      // from a template and never inserted into the document never are
+      if (!this.constructor._rust.lazyInit) {
+        this._init();
+      }
+    }
+
+    _init() {
+      const { setup, shadow, lazyInit, placeholder, wildcards, allObservedAttributes } = this.constructor._rust;
+      this._initialized = true;
+
+      // run whatever custom constructor we've been given, and other setup as necessary
+      this._handle = setup(this);
+      guard(this, "constructor", () => this._dispatch("constructor", this));
+
+      if (shadow) {
+        this.attachShadow({ mode: "open" });
+        trackShadowRoot(this.shadowRoot);
+        if (globalStyles.length) {
+          adoptGlobalStyles(this.shadowRoot, globalStyles);
+        }
+        guard(this, "inject_children", () => this._dispatch("injectChildren", this.shadowRoot));
+      }
+
+      // the changes to attributes made before a lazily initialized element was connected were ignored, and the
+      // registry reports none for a placeholder
+      if (lazyInit || placeholder) {
+        for (const name of allObservedAttributes) {
+          if (this.hasAttribute(name)) {
+            this.attributeChangedCallback(name, null, this.getAttribute(name));
+          }
+        }
+      }
+
+      if (wildcards.length || placeholder) {
+        this._observeWildcards();
+      }
+    }
+
+    // calls the component's handler for `kind`, like "connectedCallback", through the function shared by every
+    // class, with the element's handle; nothing happens before the component has been created, and if the handler
+    // panics or throws, the component is reset before the error is rethrown
+    _dispatch(kind, ...args) {
+      if (this._handle === undefined) {
+        return undefined;
+      }
+      const handle = this._handle;
+      try {
+        return this.constructor._rust.dispatch(handle, kind, args);
+      } catch (error) {
+        reset(handle);
+        throw error;
+      }
+    }
+
+    // what the constructor, and the registry's calls to `attributeChangedCallback` and `connectedCallback`, do for
+    // an existing element when it is upgraded, for the fallback registry, which can't construct it, and for the
+    // elements of a lazily defined placeholder, which were constructed before it was filled in
+    _upgrade() {
+      const { lazyInit, placeholder, allObservedAttributes } = this.constructor._rust;
+      if (!lazyInit) {
+        this._init();
+        for (const name of placeholder ? [] : allObservedAttributes) {
+          if (this.hasAttribute(name)) {
+            this.attributeChangedCallback(name, null, this.getAttribute(name));
+          }
+        }
+      }
+      if (this.isConnected) {
+        this.connectedCallback();
+      }
+    }
+
+    // `observedAttributes` can't contain patterns like `data-*`, so attributes matching them are watched instead,
+    // starting with those the element already has when it is upgraded; so are all of a placeholder's, since the
+    // registry read its `observedAttributes` before they were known
+    _observeWildcards() {
+      const { wildcards, allObservedAttributes, placeholder } = this.constructor._rust;
+      const wildcard = (name) =>
+        !allObservedAttributes.includes(name) && wildcards.some((prefix) => name.startsWith(prefix));
+      const matches = (name) => wildcard(name) || (placeholder && allObservedAttributes.includes(name));
+      for (const { name, value } of Array.from(this.attributes)) {
+        if (wildcard(name)) {
+          this.attributeChangedCallback(name, null, value);
+        }
+      }
+      new MutationObserver((records) => {
+        // each attribute once per batch, from its value before the first change to its current value
+        const changes = new Map();
+        for (const { attributeName, oldValue } of records) {
+          if (matches(attributeName) && !changes.has(attributeName)) {
+            changes.set(attributeName, oldValue);
+          }
+        }
+        for (const [name, oldValue] of changes) {
+          const newValue = this.getAttribute(name);
+          if (newValue !== oldValue) {
+            this.attributeChangedCallback(name, oldValue, newValue);
+          }
+        }
+      }).observe(this, { attributes: true, attributeOldValue: true });
+    }
 
-  customElements.define(
-    tag_name,
-    class extends superclass {
-      static get observedAttributes() {
-        return observedAttributes;
+    attributeChangedCallback(name, oldValue, newValue) {
+      if (!this._initialized) {
+        return;
+      }
+      const { observedAttributes, handlerAttributes } = this.constructor._rust;
+      const handlerEvent = handlerAttributes.get(name);
+      if (handlerEvent) {
+        const handler = newValue === null ? null : handlerFromAttribute(this, name, handlerEvent, newValue);
+        this._setHandler(handlerEvent, handler);
+        if (!observedAttributes.includes(name)) {
+          return;
+        }
       }
+      guard(this, "attribute_changed_callback", () =>
+        this._dispatch("attributeChangedCallback", this, name, oldValue, newValue)
+      );
+    }
 
-      constructor() {
-        super();
+    connectedCallback() {
+      if (!this._initialized) {
+        this._init();
+      }
 
-        // run whatever custom constructor we've been given, and other setup as necessary
-        constructor(this);
-        this._constructor(this);
+      // on first connection, add children
+      if(!this.hasSetup) {
+        this.hasSetup = true;
 
-        if (shadow) {
-          this.attachShadow({ mode: "open" });
-          this._injectChildren(this.shadowRoot);
+        if (!this.constructor._rust.shadow) {
+          guard(this, "inject_children", () => this._dispatch("injectChildren", this));
         }
       }
 
-      attributeChangedCallback(name, oldValue, newValue) {
-        this._attributeChangedCallback(this, name, oldValue || "", newValue);
+      // reconnected in the same task as a disconnection: this is a move, reported once the microtask runs
+      if (this._disconnectPending) {
+        return;
       }
 
-      connectedCallback() {
-        // on first connection, add children
-        if(!this.hasSetup) {
-          this.hasSetup = true;
-  
-          if (!shadow) {
-            this._injectChildren(this);
+      // otherwise, and also the first time, just run the callback
+      guard(this, "connected_callback", () => this._dispatch("connectedCallback", this));
+      for (const hook of this._connectHooks || []) {
+        hook(this);
+      }
+    }
+
+    disconnectedCallback() {
+      if (!this.constructor._rust.coalesceMoves) {
+        guard(this, "disconnected_callback", () => this._dispatch("disconnectedCallback", this));
+        runCleanups(this);
+        return;
+      }
+
+      // wait to see whether the element is reinserted before reporting a disconnection
+      this._disconnectPending = true;
+      queueMicrotask(() => {
+        if (this._disconnectPending) {
+          this._disconnectPending = false;
+          if (this.isConnected) {
+            guard(this, "moved_callback", () => this._dispatch("movedCallback", this));
+          } else {
+            guard(this, "disconnected_callback", () => this._dispatch("disconnectedCallback", this));
+            runCleanups(this);
           }
         }
+      });
+    }
 
-        // otherwise, and also the first time, just run the callback
-        this._connectedCallback(this);
+    adoptedCallback(oldDocument, newDocument) {
+      if (!this._initialized) {
+        return;
       }
+      guard(this, "adopted_callback", () =>
+        this._dispatch("adoptedCallback", this, oldDocument, newDocument)
+      );
+    }
 
-      disconnectedCallback() {
-        this._disconnectedCallback(this);
+    // sets the handler for an `on<event>` property or attribute; like the built-in ones, a handler that returns
+    // `false` cancels the event, and its listener stays in the position it was first added in
+    _setHandler(type, handler) {
+      this._handlers = this._handlers || {};
+      let entry = this._handlers[type];
+      if (!entry) {
+        entry = this._handlers[type] = {
+          handler: null,
+          listener: (event) => {
+            if (entry.handler && entry.handler.call(this, event) === false) {
+              event.preventDefault();
+            }
+          },
+        };
+        this.addEventListener(type, entry.listener);
       }
+      entry.handler = handler;
+    }
 
-      adoptedCallback() {
-        this._adoptedCallback(this);
+    // plays the component's leave animation, if it has one, and then removes the element
+    removeWithTransition() {
+      if (!this._initialized) {
+        this.remove();
+        return Promise.resolve();
       }
+      if (!this._leaving) {
+        this._leaving = Promise.resolve(this._dispatch("leaveAnimation", this))
+          .catch(() => {})
+          .then(() => {
+            this._leaving = null;
+            this.remove();
+          });
+      }
+      return this._leaving;
+    }
+  };
+  baseClasses.set(superclass, base);
+  return base;
+}
+
+// creates the class for one element, from the definition Rust gives: its `superclass`, the `setup` that creates its
+// component and returns the element's handle, the `dispatch` function its callbacks go through, whether it has a
+// `shadow` root, its `observedAttributes` and `wildcards`, and so on
+function elementClass({
+  superclass,
+  shadow,
+  setup,
+  dispatch,
+  observedAttributes,
+  wildcards,
+  coalesceMoves,
+  statics,
+  eventNames,
+  lazyInit,
+}) {
+  // `on<event>` properties and attributes for the declared events that the superclass doesn't already handle, like
+  // it does `onchange`; `on-<event>` attributes work too
+  const handlerEvents = eventNames.filter((name) => !(`on${name}` in superclass.prototype));
+  const handlerAttributes = new Map();
+  for (const name of handlerEvents) {
+    handlerAttributes.set(`on${name}`, name);
+    handlerAttributes.set(`on-${name}`, name);
+  }
+  const allObservedAttributes = [...observedAttributes, ...handlerAttributes.keys()];
+
+  const cls = class extends baseClass(superclass) {
+    static get observedAttributes() {
+      return allObservedAttributes;
+    }
+  };
+  Object.defineProperty(cls, "_rust", {
+    value: {
+      setup,
+      dispatch,
+      shadow,
+      lazyInit,
+      coalesceMoves,
+      observedAttributes,
+      allObservedAttributes,
+      wildcards,
+      handlerAttributes,
     },
+  });
+
+  for (const type of handlerEvents) {
+    Object.defineProperty(cls.prototype, `on${type}`, {
+      get() {
+        const entry = this._handlers && this._handlers[type];
+        return entry ? entry.handler : null;
+      },
+      set(handler) {
+        this._setHandler(type, typeof handler === "function" ? handler : null);
+      },
+      configurable: true,
+    });
+  }
+
+  // statics like `formAssociated` are read by `customElements.define()`, so they must be in place first
+  for (const [name, value] of Object.entries(statics)) {
+    Object.defineProperty(cls, name, { value, configurable: true });
+  }
+  return cls;
+}
+
+export function make_custom_element(registry, definition) {
+  const cls = elementClass(definition);
+  const placeholder = registry.get(definition.tagName);
+  if (placeholder && placeholder._rust && placeholder._rust.pending) {
+    return fillPlaceholder(placeholder, cls, definition);
+  }
+  registry.define(
+    definition.tagName,
+    cls,
+    definition.superclassTag ? { extends: definition.superclassTag } : undefined
+  );
+  return cls;
+}
+
+// defines each element of a batch, in a single call from Rust
+export function make_custom_elements(registry, definitions) {
+  return definitions.map((definition) => make_custom_element(registry, definition));
+}
+
+// defines a placeholder class for `tagName`, whose elements call `loader` when the first of them is connected, and
+// wait for it to define the tag, which fills the placeholder in with the real class's configuration
+export function define_lazy(tagName, loader) {
+  // the elements created before the real definition, which are upgraded once it arrives
+  const waiting = new Set();
+  let loading = false;
+  const placeholder = class extends baseClass(HTMLElement) {
+    constructor() {
+      super();
+      if (this.constructor._rust.pending) {
+        waiting.add(this);
+      }
+    }
+
+    connectedCallback() {
+      if (!this.constructor._rust.pending) {
+        super.connectedCallback();
+      } else if (!loading) {
+        loading = true;
+        loader();
+      }
+    }
+
+    disconnectedCallback() {
+      if (!this.constructor._rust.pending) {
+        super.disconnectedCallback();
+      }
+    }
+  };
+  // until it is filled in, an element is initialized lazily, on a connection that never initializes it
+  Object.defineProperty(placeholder, "_rust", {
+    value: { pending: true, lazyInit: true, waiting },
+    configurable: true,
+  });
+  customElements.define(tagName, placeholder);
+}
+
+// gives a placeholder defined by `define_lazy` the configuration and properties of `cls`, the class that would have
+// been defined for its tag name, and upgrades the elements created before then; the registry read the
+// placeholder's `observedAttributes` and statics when it was defined, so attributes are watched instead, and
+// statics like `formAssociated` can't take effect
+function fillPlaceholder(placeholder, cls, { tagName, superclass, statics }) {
+  if (superclass !== HTMLElement) {
+    throw new Error(`<${tagName}> is defined lazily, so it must extend HTMLElement`);
+  }
+  const ignored = Object.keys(statics);
+  if (ignored.length) {
+    console.warn(`<${tagName}> is defined lazily, so its ${ignored.join(", ")} static properties are ignored`);
+  }
+  const { waiting } = placeholder._rust;
+  Object.defineProperty(placeholder, "_rust", { value: { ...cls._rust, placeholder: true } });
+  for (const name of Object.getOwnPropertyNames(cls.prototype)) {
+    if (name !== "constructor") {
+      Object.defineProperty(placeholder.prototype, name, Object.getOwnPropertyDescriptor(cls.prototype, name));
+    }
+  }
+  for (const el of waiting) {
+    el._upgrade();
+  }
+  waiting.clear();
+  return placeholder;
+}
+
+// whether `tagName` is defined in `window`, other than by a placeholder that is still waiting for its bundle
+export function is_defined(window, tagName) {
+  const cls = window.customElements && window.customElements.get(tagName);
+  return cls !== undefined && !(cls._rust && cls._rust.pending);
+}
+
+// loads a wasm-bindgen module (initializing it, if it was built with `--target web`) and calls one of its exports
+export function import_and_call(moduleUrl, exportName) {
+  return import(moduleUrl).then(async (module) => {
+    if (typeof module.default === "function") {
+      await module.default();
+    }
+    module[exportName]();
+  });
+}
+
+// defines `alias` as another name for the element class `cls`; a class can only be defined once, so `alias` gets a
+// subclass that adds nothing
+export function define_alias(registry, alias, cls, superclassTag) {
+  registry.define(
+    alias,
+    class extends cls {},
     superclassTag ? { extends: superclassTag } : undefined
   );
 }
+
+// whether `global` supports customized built-in elements, which some browsers never implemented; checked once per
+// window, by defining one under an unused name and seeing whether an element created with it keeps its `is` value
+const builtInSupport = new WeakMap();
+
+export function supports_customized_built_ins(global) {
+  if (!builtInSupport.has(global)) {
+    let supported = false;
+    try {
+      const name = `ce-built-in-check-${Math.random().toString(36).slice(2)}`;
+      global.customElements.define(name, class extends global.HTMLLIElement {}, { extends: "li" });
+      supported = /\bis=/.test(global.document.createElement("li", { is: name }).outerHTML);
+    } catch (error) {
+      supported = false;
+    }
+    builtInSupport.set(global, supported);
+  }
+  return builtInSupport.get(global);
+}
+
+// a stand-in for the `customElements` registry of a global that has none, which classes can be defined in, but which
+// only upgrades elements if `enhance` is set: it then finds them in the document and in the shadow roots of elements
+// it upgraded, and calls their lifecycle callbacks itself as they are connected, disconnected and changed
+const fallbackRegistries = new WeakMap();
+
+export function fallback_registry(global, enhance) {
+  if (fallbackRegistries.has(global)) {
+    return fallbackRegistries.get(global);
+  }
+  const definitions = new Map();
+  const connected = new WeakSet();
+
+  const selector = (name, options) => (options && options.extends ? `${options.extends}[is="${name}"]` : name);
+  const definitionOf = (el) => {
+    for (const definition of definitions.values()) {
+      if (el.matches(definition.selector)) {
+        return definition;
+      }
+    }
+    return null;
+  };
+
+  const upgrade = (el, cls) => {
+    Object.setPrototypeOf(el, cls.prototype);
+    el._fallbackUpgraded = true;
+    const attributeFilter = cls.observedAttributes;
+    if (attributeFilter.length) {
+      new MutationObserver((records) => {
+        for (const { attributeName, oldValue } of records) {
+          el.attributeChangedCallback(attributeName, oldValue, el.getAttribute(attributeName));
+        }
+      }).observe(el, { attributes: true, attributeOldValue: true, attributeFilter });
+    }
+    el._upgrade();
+    if (el.isConnected) {
+      connected.add(el);
+    }
+    if (el.shadowRoot) {
+      watch(el.shadowRoot);
+    }
+  };
+
+  // upgrades the elements in `root`, and calls the callbacks of those that were connected or disconnected
+  const update = (root) => {
+    const descendants = Array.from(root.querySelectorAll("*"));
+    const elements = root.nodeType === global.Node.ELEMENT_NODE ? [root, ...descendants] : descendants;
+    for (const el of elements) {
+      if (!el._fallbackUpgraded) {
+        const definition = definitionOf(el);
+        if (definition && el.isConnected) {
+          upgrade(el, definition.cls);
+        }
+      } else if (el.isConnected && !connected.has(el)) {
+        connected.add(el);
+        el.connectedCallback();
+      } else if (!el.isConnected && connected.has(el)) {
+        connected.delete(el);
+        el.disconnectedCallback();
+      }
+    }
+  };
+
+  const watch = (root) => {
+    new MutationObserver((records) => {
+      for (const { addedNodes, removedNodes } of records) {
+        for (const node of [...removedNodes, ...addedNodes]) {
+          if (node.nodeType === global.Node.ELEMENT_NODE) {
+            update(node);
+          }
+        }
+      }
+    }).observe(root, { childList: true, subtree: true });
+  };
+
+  const registry = {
+    define(name, cls, options) {
+      if (definitions.has(name)) {
+        throw new DOMException(`"${name}" has already been used with this registry`, "NotSupportedError");
+      }
+      definitions.set(name, { cls, selector: selector(name, options) });
+      if (enhance) {
+        update(global.document);
+      }
+    },
+    get(name) {
+      const definition = definitions.get(name);
+      return definition ? definition.cls : undefined;
+    },
+  };
+  if (enhance) {
+    watch(global.document);
+  }
+  fallbackRegistries.set(global, registry);
+  return registry;
+}
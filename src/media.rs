@@ -0,0 +1,117 @@
+//! Shared [media query](https://developer.mozilla.org/en-US/docs/Web/CSS/Media_Queries/Using_media_queries) listeners.
+//!
+//! Each element whose type declares queries in
+//! [observed_media_queries](crate::CustomElement::observed_media_queries) has its
+//! [media_query_changed_callback](crate::CustomElement::media_query_changed_callback) invoked when one of
+//! them starts or stops matching. There is one `matchMedia` listener per distinct query in each window, shared by all
+//! elements in it, and it is removed once no connected element there observes that query.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, HtmlElement, MediaQueryList, MediaQueryListEvent, Window};
+
+use crate::subscribers::Subscribers;
+use crate::window_of;
+
+struct MediaQuery {
+    window: Window,
+    list: MediaQueryList,
+    subscribers: Rc<Subscribers>,
+    listener: Closure<dyn FnMut(MediaQueryListEvent)>,
+}
+
+thread_local! {
+    static QUERIES: RefCell<HashMap<String, Vec<MediaQuery>>> = RefCell::new(HashMap::new());
+}
+
+/// Whether the document currently matches the given media query, like `(prefers-color-scheme: dark)`.
+pub fn matches(query: &str) -> bool {
+    media_query_list(&window().unwrap_throw(), query).matches()
+}
+
+fn media_query_list(window: &Window, query: &str) -> MediaQueryList {
+    window.match_media(query).unwrap_throw().unwrap_throw()
+}
+
+// Registers a connected element for each query and immediately delivers whether each one matches in its window.
+pub(crate) fn subscribe(this: &HtmlElement, queries: &[String]) {
+    let window = window_of(this);
+    for query in queries {
+        let (subscribers, matches) = QUERIES.with(|queries| {
+            let mut queries = queries.borrow_mut();
+            let entries = queries.entry(query.to_string()).or_default();
+            let index = match entries.iter().position(|entry| entry.window == window) {
+                Some(index) => index,
+                None => {
+                    entries.push(listen(&window, query));
+                    entries.len() - 1
+                }
+            };
+            let entry = &entries[index];
+            entry.subscribers.subscribe(this);
+            (entry.subscribers.clone(), entry.list.matches())
+        });
+        subscribers.deliver(
+            this,
+            &[&JsValue::from_str(query), &JsValue::from_bool(matches)],
+        );
+    }
+}
+
+// Unregisters a disconnected element, and removes the listener for each query no connected element observes.
+pub(crate) fn unsubscribe(this: &HtmlElement, queries: &[String]) {
+    let unused = QUERIES.with(|queries_map| {
+        let mut queries_map = queries_map.borrow_mut();
+        let mut unused = Vec::new();
+        for query in queries {
+            if let Some(entries) = queries_map.get_mut(query.as_str()) {
+                for entry in entries.iter() {
+                    entry.subscribers.unsubscribe(this);
+                }
+                let (empty, used): (Vec<_>, Vec<_>) = entries
+                    .drain(..)
+                    .partition(|entry| entry.subscribers.is_empty());
+                *entries = used;
+                unused.extend(empty);
+                if entries.is_empty() {
+                    queries_map.remove(query.as_str());
+                }
+            }
+        }
+        unused
+    });
+    // dropped, with their closures, once the map is no longer borrowed
+    for entry in unused {
+        entry
+            .list
+            .remove_event_listener_with_callback("change", entry.listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+    }
+}
+
+fn listen(window: &Window, query: &str) -> MediaQuery {
+    let list = media_query_list(window, query);
+    let subscribers = Rc::new(Subscribers::new("mediaQueryChangedCallback"));
+    let listener = Closure::wrap(Box::new({
+        let subscribers = subscribers.clone();
+        let query = query.to_string();
+        move |event: MediaQueryListEvent| {
+            subscribers.notify(&[
+                &JsValue::from_str(&query),
+                &JsValue::from_bool(event.matches()),
+            ]);
+        }
+    }) as Box<dyn FnMut(MediaQueryListEvent)>);
+    list.add_event_listener_with_callback("change", listener.as_ref().unchecked_ref())
+        .unwrap_throw();
+    MediaQuery {
+        window: window.clone(),
+        list,
+        subscribers,
+        listener,
+    }
+}
@@ -0,0 +1,211 @@
+//! Counters for monitoring a page's custom elements. Requires the `metrics` feature.
+//!
+//! For each tag name defined with this crate, the crate counts how many of its elements exist, how often they have
+//! been connected and disconnected, and how many lifecycle callbacks they have run and for how long. [all] and [get]
+//! read the counters from Rust, and [to_json] serializes them for a dashboard or an analytics endpoint:
+//!
+//! ```rust,ignore
+//! let report = metrics::to_json();
+//! // [{"tag":"user-card","instances":12,"created":40,"connects":52,"disconnects":40,"callbacks":310,"callback_ms":18.5}]
+//! ```
+//!
+//! Elements are counted until they have been garbage collected, so detached elements still count for a while. A
+//! callback's time includes any callbacks of other elements that run inside it, like the constructors of the
+//! elements it creates.
+
+use std::cell::{Cell, RefCell};
+use std::fmt::Write;
+use std::rc::Rc;
+
+use web_sys::window;
+
+/// The counters for one tag name. See the [module documentation](self).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ElementMetrics {
+    /// The tag name.
+    pub tag: String,
+    /// The number of elements that exist.
+    pub instances: u64,
+    /// The number of elements created in total.
+    pub created: u64,
+    /// The number of times an element was connected.
+    pub connects: u64,
+    /// The number of times an element was disconnected.
+    pub disconnects: u64,
+    /// The number of lifecycle callbacks run.
+    pub callbacks: u64,
+    /// The time spent in lifecycle callbacks, in milliseconds.
+    pub callback_ms: f64,
+}
+
+impl ElementMetrics {
+    /// The average time spent in a lifecycle callback, in milliseconds, or `0.0` if none have run.
+    pub fn average_callback_ms(&self) -> f64 {
+        if self.callbacks == 0 {
+            0.0
+        } else {
+            self.callback_ms / self.callbacks as f64
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    instances: Cell<u64>,
+    created: Cell<u64>,
+    connects: Cell<u64>,
+    disconnects: Cell<u64>,
+    callbacks: Cell<u64>,
+    callback_ms: Cell<f64>,
+}
+
+thread_local! {
+    // the counters of each defined tag name, in the order they were defined
+    static TAGS: RefCell<Vec<(String, Rc<Counters>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The counters of every tag name defined with this crate, in the order they were defined.
+pub fn all() -> Vec<ElementMetrics> {
+    TAGS.with(|tags| {
+        tags.borrow()
+            .iter()
+            .map(|(tag, counters)| snapshot(tag, counters))
+            .collect()
+    })
+}
+
+/// The counters of one tag name, if it has been defined with this crate.
+pub fn get(tag_name: &str) -> Option<ElementMetrics> {
+    counters(tag_name).map(|counters| snapshot(tag_name, &counters))
+}
+
+/// The counters of every tag name, as a JSON array of objects with the fields of [ElementMetrics].
+pub fn to_json() -> String {
+    let mut json = String::from("[");
+    for (i, metrics) in all().iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str("{\"tag\":\"");
+        for c in metrics.tag.chars() {
+            match c {
+                '"' => json.push_str("\\\""),
+                '\\' => json.push_str("\\\\"),
+                c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+                c => json.push(c),
+            }
+        }
+        write!(
+            json,
+            "\",\"instances\":{},\"created\":{},\"connects\":{},\"disconnects\":{},\"callbacks\":{},\"callback_ms\":{}}}",
+            metrics.instances,
+            metrics.created,
+            metrics.connects,
+            metrics.disconnects,
+            metrics.callbacks,
+            metrics.callback_ms
+        )
+        .unwrap();
+    }
+    json.push(']');
+    json
+}
+
+/// Sets every counter except the number of elements that exist back to zero, to start a new measurement period.
+pub fn reset() {
+    TAGS.with(|tags| {
+        for (_, counters) in tags.borrow().iter() {
+            counters.created.set(0);
+            counters.connects.set(0);
+            counters.disconnects.set(0);
+            counters.callbacks.set(0);
+            counters.callback_ms.set(0.0);
+        }
+    });
+}
+
+fn snapshot(tag: &str, counters: &Counters) -> ElementMetrics {
+    ElementMetrics {
+        tag: tag.to_string(),
+        instances: counters.instances.get(),
+        created: counters.created.get(),
+        connects: counters.connects.get(),
+        disconnects: counters.disconnects.get(),
+        callbacks: counters.callbacks.get(),
+        callback_ms: counters.callback_ms.get(),
+    }
+}
+
+fn counters(tag_name: &str) -> Option<Rc<Counters>> {
+    TAGS.with(|tags| {
+        tags.borrow()
+            .iter()
+            .find(|(tag, _)| tag == tag_name)
+            .map(|(_, counters)| counters.clone())
+    })
+}
+
+// Starts counting a newly defined tag name.
+pub(crate) fn defined(tag_name: &str) {
+    if counters(tag_name).is_none() {
+        TAGS.with(|tags| {
+            tags.borrow_mut()
+                .push((tag_name.to_string(), Rc::new(Counters::default())))
+        });
+    }
+}
+
+// Counts an element of the given tag name for as long as the returned value is kept, which is as long as the
+// element's component exists.
+pub(crate) fn track(tag_name: &str) -> Tracked {
+    let counters = counters(tag_name).unwrap_or_default();
+    counters.instances.set(counters.instances.get() + 1);
+    counters.created.set(counters.created.get() + 1);
+    Tracked(counters)
+}
+
+pub(crate) struct Tracked(Rc<Counters>);
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.0.instances.set(self.0.instances.get() - 1);
+    }
+}
+
+// Times a lifecycle callback until it is dropped.
+pub(crate) struct Timer {
+    counters: Option<Rc<Counters>>,
+    start: f64,
+}
+
+pub(crate) fn callback(tag_name: &str, callback: &'static str) -> Timer {
+    let counters = counters(tag_name);
+    if let Some(counters) = &counters {
+        match callback {
+            "connected_callback" => counters.connects.set(counters.connects.get() + 1),
+            "disconnected_callback" => counters.disconnects.set(counters.disconnects.get() + 1),
+            _ => {}
+        }
+    }
+    // only elements whose tag name is counted are timed
+    let start = if counters.is_some() { now() } else { 0.0 };
+    Timer { counters, start }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        if let Some(counters) = &self.counters {
+            counters.callbacks.set(counters.callbacks.get() + 1);
+            counters
+                .callback_ms
+                .set(counters.callback_ms.get() + now() - self.start);
+        }
+    }
+}
+
+fn now() -> f64 {
+    window()
+        .and_then(|window| window.performance())
+        .map(|performance| performance.now())
+        .unwrap_or_else(js_sys::Date::now)
+}
@@ -0,0 +1,378 @@
+//! A harness for testing components natively, without a browser. Requires the `mock` feature.
+//!
+//! [MockElement] drives a component through its lifecycle the way the crate would in a browser, with a [MockHost]
+//! as its element: setting an observed attribute calls
+//! [attribute_changed_callback](CustomElement::attribute_changed_callback), connecting the element calls
+//! [inject_children](CustomElement::inject_children) the first time and then
+//! [connected_callback](CustomElement::connected_callback), and so on. Tests then make assertions about the
+//! component's state, and about what it did to its element:
+//!
+//! ```rust,ignore
+//! #[test]
+//! fn counts_up_from_start() {
+//!     let mut counter = MockElement::new(Counter::default());
+//!     counter.set_attribute("start", "5");
+//!     counter.connect();
+//!     assert_eq!(counter.component().count, 5);
+//!     assert_eq!(counter.host().text(), "0 5");
+//! }
+//! ```
+//!
+//! The component has to implement [CustomElement] for any [Host], rather than only for the browser's `HtmlElement`,
+//! so that its callbacks can be given the `MockHost`; see [host](crate::host).
+//!
+//! A callback that panics fails the test's call, as it would fail the browser's, and the component stays usable,
+//! as it does in the browser, so that a test can go on to check how it recovers.
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+use crate::host::Host;
+use crate::instance::Instance;
+use crate::CustomElement;
+
+/// A [Host] that keeps its attributes and text, and records the events fired from it, for native tests.
+#[derive(Debug, Default)]
+pub struct MockHost {
+    attributes: RefCell<BTreeMap<String, String>>,
+    text: RefCell<String>,
+    events: RefCell<Vec<(String, Option<String>)>>,
+}
+
+impl MockHost {
+    /// A host with no attributes, no text, and no events.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The text set with [set_text](Host::set_text), followed by any appended since with
+    /// [append_text](Host::append_text).
+    pub fn text(&self) -> String {
+        self.text.borrow().clone()
+    }
+
+    /// The type and detail of each event fired so far, oldest first.
+    pub fn events(&self) -> Vec<(String, Option<String>)> {
+        self.events.borrow().clone()
+    }
+}
+
+impl Host for MockHost {
+    fn attr(&self, name: &str) -> Option<String> {
+        self.attributes.borrow().get(name).cloned()
+    }
+
+    fn set_attr(&self, name: &str, value: &str) {
+        self.attributes
+            .borrow_mut()
+            .insert(name.to_string(), value.to_string());
+    }
+
+    fn remove_attr(&self, name: &str) {
+        self.attributes.borrow_mut().remove(name);
+    }
+
+    fn set_text(&self, text: &str) {
+        *self.text.borrow_mut() = text.to_string();
+    }
+
+    fn append_text(&self, text: &str) {
+        self.text.borrow_mut().push_str(text);
+    }
+
+    fn emit(&self, event_type: &str, detail: Option<&str>) -> bool {
+        self.events
+            .borrow_mut()
+            .push((event_type.to_string(), detail.map(String::from)));
+        true
+    }
+}
+
+/// A component and the state of its simulated element. See the [module documentation](self).
+pub struct MockElement<T> {
+    component: Instance<T>,
+    host: Rc<MockHost>,
+    initialized: bool,
+    set_up: bool,
+    connected: bool,
+}
+
+impl<T: CustomElement<MockHost>> MockElement<T> {
+    /// Creates the element, calling the component's [constructor](CustomElement::constructor) and, if it has a
+    /// [shadow root](CustomElement::shadow), [inject_children](CustomElement::inject_children), as the browser would
+    /// when the element is created. With [lazy_init](CustomElement::lazy_init), that happens when it is first
+    /// connected instead.
+    pub fn new(component: T) -> Self {
+        let mut mock = Self {
+            component: Instance::new(Rc::from("mock-element"), component),
+            host: Rc::new(MockHost::new()),
+            initialized: false,
+            set_up: false,
+            connected: false,
+        };
+        if !T::lazy_init() {
+            mock.init();
+        }
+        mock
+    }
+
+    /// The component.
+    pub fn component(&self) -> &T {
+        self.component.get()
+    }
+
+    /// The component, mutably, for tests that need to call its methods directly.
+    pub fn component_mut(&mut self) -> &mut T {
+        self.component.get_mut()
+    }
+
+    /// The element, which the component's callbacks are given, with the attributes and text they set and the events
+    /// they fired.
+    pub fn host(&self) -> &MockHost {
+        &self.host
+    }
+
+    /// The value of the attribute, if the element has it.
+    pub fn attribute(&self, name: &str) -> Option<String> {
+        self.host.attr(name)
+    }
+
+    /// Sets an attribute, notifying the component if it observes the attribute. Attributes the component sets on its
+    /// host itself do not notify it.
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        let old_value = self.host.attr(name);
+        self.host.set_attr(name, value);
+        self.attribute_changed(name, old_value, Some(value.to_string()));
+    }
+
+    /// Removes an attribute, notifying the component if it observes the attribute and the element had it.
+    pub fn remove_attribute(&mut self, name: &str) {
+        if let Some(old_value) = self.host.attr(name) {
+            self.host.remove_attr(name);
+            self.attribute_changed(name, Some(old_value), None);
+        }
+    }
+
+    /// Whether the element is connected.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// Connects the element, calling [inject_children](CustomElement::inject_children) the first time if the
+    /// element has no shadow root, and then [connected_callback](CustomElement::connected_callback). Does nothing if
+    /// it is already connected.
+    pub fn connect(&mut self) {
+        if self.connected {
+            return;
+        }
+        self.connected = true;
+        if !self.initialized {
+            self.init();
+        }
+        if !self.set_up {
+            self.set_up = true;
+            if !T::shadow() {
+                self.inject_children();
+            }
+        }
+        self.with("connected_callback", |component, host| {
+            component.connected_callback(host)
+        });
+    }
+
+    /// Disconnects the element, calling [disconnected_callback](CustomElement::disconnected_callback). Does nothing
+    /// if it is not connected.
+    pub fn disconnect(&mut self) {
+        if !self.connected {
+            return;
+        }
+        self.connected = false;
+        self.with("disconnected_callback", |component, host| {
+            component.disconnected_callback(host)
+        });
+    }
+
+    /// Moves the element, calling [moved_callback](CustomElement::moved_callback) if the component
+    /// [coalesces moves](CustomElement::coalesce_moves), and disconnecting and reconnecting it otherwise.
+    pub fn move_element(&mut self) {
+        if T::coalesce_moves() && self.connected {
+            self.with("moved_callback", |component, host| {
+                component.moved_callback(host)
+            });
+        } else {
+            self.disconnect();
+            self.connect();
+        }
+    }
+
+    fn init(&mut self) {
+        self.initialized = true;
+        self.with("constructor", |component, host| component.constructor(host));
+        if T::shadow() {
+            self.inject_children();
+        }
+        if T::lazy_init() {
+            let attributes = self.host.attributes.borrow().clone();
+            for (name, value) in attributes {
+                self.attribute_changed(&name, None, Some(value));
+            }
+        }
+    }
+
+    fn inject_children(&mut self) {
+        self.with("inject_children", |component, host| {
+            if let Err(error) = component.try_inject_children(host) {
+                component.error_callback(host, error);
+            }
+        });
+    }
+
+    fn attribute_changed(
+        &mut self,
+        name: &str,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    ) {
+        if !self.initialized {
+            return;
+        }
+        let matches = |attributes: &[&str]| {
+            attributes
+                .iter()
+                .any(|attribute| match attribute.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => *attribute == name,
+                })
+        };
+        if matches(T::observed_bool_attributes()) || (T::disableable() && name == "disabled") {
+            if old_value.is_some() != new_value.is_some() {
+                let name = name.to_string();
+                self.with("bool_attribute_changed_callback", move |component, host| {
+                    component.bool_attribute_changed_callback(host, name, new_value.is_some())
+                });
+            }
+        } else if matches(T::observed_attributes()) || T::observe_all_attributes() {
+            let name = name.to_string();
+            self.with("attribute_changed_callback", move |component, host| {
+                component.attribute_changed_callback(host, name, old_value, new_value)
+            });
+        }
+    }
+
+    // Runs a callback the way the crate does in the browser, resetting the component if it panics, as the element's
+    // class does when a callback throws.
+    fn with(&mut self, name: &'static str, callback: impl FnOnce(&mut T, &MockHost) + 'static) {
+        let host = self.host.clone();
+        let component = &self.component;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            component.with(name, move |component| callback(component, &host))
+        }));
+        if let Err(error) = result {
+            component.reset();
+            panic::resume_unwind(error);
+        }
+    }
+}
+
+impl<T: CustomElement<MockHost> + Default> Default for MockElement<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Counter {
+        count: i32,
+        connections: u32,
+    }
+
+    impl<H: Host> CustomElement<H> for Counter {
+        fn inject_children(&mut self, this: &H) {
+            this.set_text(&self.count.to_string());
+        }
+
+        fn connected_callback(&mut self, this: &H) {
+            self.connections += 1;
+            this.set_attr("connected", "");
+            this.emit("counter-connected", Some(&self.count.to_string()));
+        }
+
+        fn disconnected_callback(&mut self, this: &H) {
+            this.remove_attr("connected");
+        }
+
+        fn observed_attributes() -> &'static [&'static str] {
+            &["start"]
+        }
+
+        fn attribute_changed_callback(
+            &mut self,
+            this: &H,
+            _name: String,
+            _old_value: Option<String>,
+            new_value: Option<String>,
+        ) {
+            if new_value.as_deref() == Some("fail") {
+                panic!("cannot count from \"fail\"");
+            }
+            self.count = new_value.and_then(|value| value.parse().ok()).unwrap_or(0);
+            this.append_text(&format!(" {}", self.count));
+        }
+    }
+
+    #[test]
+    fn callbacks_receive_the_host() {
+        let mut counter = MockElement::<Counter>::default();
+        counter.set_attribute("start", "5");
+        counter.connect();
+        assert_eq!(counter.component().count, 5);
+        assert_eq!(counter.host().text(), "0 5");
+        assert_eq!(counter.attribute("connected").as_deref(), Some(""));
+        assert_eq!(
+            counter.host().events(),
+            vec![("counter-connected".to_string(), Some("5".to_string()))]
+        );
+
+        counter.set_attribute("start", "7");
+        assert_eq!(counter.host().text(), "0 5 7");
+
+        counter.disconnect();
+        assert_eq!(counter.attribute("connected"), None);
+    }
+
+    #[test]
+    fn ignores_unobserved_and_unchanged_attributes() {
+        let mut counter = MockElement::<Counter>::default();
+        counter.set_attribute("label", "3");
+        assert_eq!(counter.component().count, 0);
+        counter.remove_attribute("start");
+        assert_eq!(counter.host().text(), "0");
+    }
+
+    #[test]
+    fn recovers_from_a_failed_callback() {
+        let mut counter = MockElement::<Counter>::default();
+        let failed =
+            panic::catch_unwind(AssertUnwindSafe(|| counter.set_attribute("start", "fail")));
+        assert!(failed.is_err());
+
+        counter.set_attribute("start", "3");
+        assert_eq!(counter.component().count, 3);
+        assert_eq!(counter.host().text(), "0 3");
+    }
+
+    #[test]
+    fn connects_once() {
+        let mut counter = MockElement::<Counter>::default();
+        counter.connect();
+        counter.connect();
+        counter.move_element();
+        assert_eq!(counter.component().connections, 2);
+        assert!(counter.is_connected());
+    }
+}
@@ -0,0 +1,101 @@
+//! A panic hook that says which custom element panicked, and in which of its callbacks.
+//!
+//! A panic in WebAssembly usually shows up as an `unreachable` error, with a stack trace of mangled function names.
+//! The crate keeps track of the element and lifecycle callback it is running, and the hook installed by [set_hook]
+//! writes them to the console along with the panic message:
+//!
+//! ```text
+//! <user-card> panicked in connected_callback: panicked at src/lib.rs:42:9:
+//! called `Option::unwrap()` on a `None` value
+//! ```
+//!
+//! ```rust,ignore
+//! #[wasm_bindgen(start)]
+//! pub fn start() {
+//!     custom_elements::panic::set_hook();
+//!     UserCard::define("user-card");
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::panic::PanicHookInfo;
+use std::rc::Rc;
+use std::sync::Once;
+
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    // the element and callback running, innermost last; callbacks can nest when one component creates another
+    static PHASES: RefCell<Vec<(Rc<str>, &'static str)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Installs a panic hook that writes the panic message, the tag name of the element and the callback that was
+/// running when it panicked, if any, and a stack trace to the console. It replaces any hook already installed, like
+/// `console_error_panic_hook`, and only installs itself once, however often it is called.
+pub fn set_hook() {
+    static SET_HOOK: Once = Once::new();
+    SET_HOOK.call_once(|| std::panic::set_hook(Box::new(hook)));
+}
+
+fn hook(info: &PanicHookInfo) {
+    let message = match current() {
+        Some((tag_name, phase)) => format!("<{}> panicked in {}: {}", tag_name, phase, info),
+        None => info.to_string(),
+    };
+    let stack = js_sys::Reflect::get(&js_sys::Error::new(""), &JsValue::from_str("stack"))
+        .ok()
+        .and_then(|stack| stack.as_string())
+        .unwrap_or_default();
+    web_sys::console::error_1(&JsValue::from_str(&format!(
+        "{}\n\nStack:\n\n{}",
+        message, stack
+    )));
+}
+
+/// The tag name of the element and the name of the callback that is running, if any.
+pub fn current() -> Option<(Rc<str>, &'static str)> {
+    PHASES.with(|phases| phases.borrow().last().cloned())
+}
+
+// The number of callbacks running, which `truncate` can go back to.
+pub(crate) fn depth() -> usize {
+    PHASES.with(|phases| phases.borrow().len())
+}
+
+// Forgets the callbacks entered since `depth` was taken. WebAssembly doesn't unwind, so a callback that panicked or
+// threw never drops its `Phase`, and would otherwise be blamed for every later panic.
+pub(crate) fn truncate(depth: usize) {
+    PHASES.with(|phases| phases.borrow_mut().truncate(depth));
+}
+
+// Marks a callback as running until it is dropped.
+pub(crate) struct Phase(());
+
+pub(crate) fn enter(tag_name: &Rc<str>, callback: &'static str) -> Phase {
+    PHASES.with(|phases| phases.borrow_mut().push((tag_name.clone(), callback)));
+    Phase(())
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        PHASES.with(|phases| phases.borrow_mut().pop());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_forgets_callbacks_that_never_returned() {
+        let tag_name: Rc<str> = Rc::from("user-card");
+        let depth = depth();
+        let outer = enter(&tag_name, "connected_callback");
+        // a callback that traps never drops its phase
+        std::mem::forget(enter(&tag_name, "attribute_changed_callback"));
+        truncate(depth + 1);
+        assert_eq!(current(), Some((tag_name.clone(), "connected_callback")));
+        drop(outer);
+        assert_eq!(current(), None);
+    }
+}
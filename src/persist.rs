@@ -0,0 +1,183 @@
+//! Component state that survives page loads, kept in `localStorage` or `sessionStorage`. Requires the `persist`
+//! feature.
+//!
+//! A component can [restore] a value when it is connected and [save] it each time it changes. Values are stored as
+//! JSON, with [serde](https://serde.rs), under a key made of the element's tag name, its `id` attribute and the
+//! value's name, so that each element on a page keeps its own state:
+//!
+//! ```rust,ignore
+//! // <collapsible-panel id="filters">
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     if let Some(open) = persist::restore(this, Storage::Local, "open") {
+//!         self.set_open(this, open);
+//!     }
+//! }
+//!
+//! fn toggle(&mut self, this: &HtmlElement) {
+//!     self.set_open(this, !self.open);
+//!     persist::save(this, Storage::Local, "open", &self.open);
+//! }
+//! ```
+//!
+//! Saves are debounced: a value saved several times in quick succession is only written once, [DEBOUNCE_MS] after the
+//! last time, or when the element is disconnected, whichever comes first. Elements without an `id` have no key, so
+//! nothing is restored or saved for them.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use web_sys::{window, HtmlElement};
+
+use crate::scope::Scope;
+use crate::{defined_tag_name, host};
+
+/// Which of the browser's storage areas a value is kept in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Storage {
+    /// `localStorage`, kept until it is cleared.
+    Local,
+    /// `sessionStorage`, kept for as long as the tab is open.
+    Session,
+}
+
+impl Storage {
+    fn get(self) -> Option<web_sys::Storage> {
+        let window = window()?;
+        match self {
+            Storage::Local => window.local_storage(),
+            Storage::Session => window.session_storage(),
+        }
+        .ok()
+        .flatten()
+    }
+}
+
+/// How long after the last [save] of a value it is written, in milliseconds.
+pub const DEBOUNCE_MS: i32 = 250;
+
+// A write waiting for its debounce timer. The closure is kept for the key until it is written, and set again with
+// each save, so that saves in quick succession share it.
+struct Pending {
+    storage: Storage,
+    json: String,
+    timer: i32,
+    write: Closure<dyn FnMut()>,
+}
+
+thread_local! {
+    // the writes waiting for their debounce timer, by key
+    static PENDING: RefCell<HashMap<String, Pending>> = RefCell::new(HashMap::new());
+    // the keys that are written when their element is disconnected
+    static FLUSHED_ON_DISCONNECT: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+/// The storage key for the value `name` of the element, like `collapsible-panel#filters/open`, or `None` if the
+/// element has no `id`. `this` may be either the element or its shadow root.
+pub fn key(this: &HtmlElement, name: &str) -> Option<String> {
+    let id = host(this).id();
+    if id.is_empty() {
+        return None;
+    }
+    Some(format!("{}#{}/{}", defined_tag_name(this), id, name))
+}
+
+/// The value `name` of the element, if it has been saved and can be parsed as a `T`. A value that has been saved but
+/// not yet written is returned too.
+pub fn restore<T: DeserializeOwned>(this: &HtmlElement, storage: Storage, name: &str) -> Option<T> {
+    let key = key(this, name)?;
+    let pending = PENDING.with(|pending| {
+        pending
+            .borrow()
+            .get(&key)
+            .filter(|pending| pending.storage == storage)
+            .map(|pending| pending.json.clone())
+    });
+    let json = match pending {
+        Some(json) => json,
+        None => storage.get()?.get_item(&key).ok()??,
+    };
+    serde_wasm_bindgen::from_value(js_sys::JSON::parse(&json).ok()?).ok()
+}
+
+/// Saves the value `name` of the element, writing it after [DEBOUNCE_MS] or when the element is disconnected. Does
+/// nothing if the element has no `id`.
+pub fn save<T: Serialize>(this: &HtmlElement, storage: Storage, name: &str, value: &T) {
+    let key = match key(this, name) {
+        Some(key) => key,
+        None => return,
+    };
+    let value = serde_wasm_bindgen::to_value(value).unwrap_throw();
+    let json = String::from(js_sys::JSON::stringify(&value).unwrap_throw());
+
+    let window = window().unwrap_throw();
+    PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        let write = match pending.remove(&key) {
+            Some(previous) => {
+                window.clear_timeout_with_handle(previous.timer);
+                previous.write
+            }
+            None => Closure::wrap(Box::new({
+                let key = key.clone();
+                move || flush(&key)
+            }) as Box<dyn FnMut()>),
+        };
+        let timer = window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                write.as_ref().unchecked_ref(),
+                DEBOUNCE_MS,
+            )
+            .unwrap_throw();
+        pending.insert(
+            key.clone(),
+            Pending {
+                storage,
+                json,
+                timer,
+                write,
+            },
+        );
+    });
+    // the first save since the element was connected also writes on disconnect
+    if FLUSHED_ON_DISCONNECT.with(|keys| keys.borrow_mut().insert(key.clone())) {
+        Scope::new(this).on_cleanup(move || {
+            FLUSHED_ON_DISCONNECT.with(|keys| keys.borrow_mut().remove(&key));
+            flush(&key);
+        });
+    }
+}
+
+/// Removes the value `name` of the element from storage, along with any save of it that has not been written yet.
+pub fn forget(this: &HtmlElement, storage: Storage, name: &str) {
+    let key = match key(this, name) {
+        Some(key) => key,
+        None => return,
+    };
+    if let Some(pending) = PENDING.with(|pending| pending.borrow_mut().remove(&key)) {
+        window()
+            .unwrap_throw()
+            .clear_timeout_with_handle(pending.timer);
+    }
+    if let Some(storage) = storage.get() {
+        let _ = storage.remove_item(&key);
+    }
+}
+
+// Writes the pending save for `key`, if there is one.
+fn flush(key: &str) {
+    let pending = PENDING.with(|pending| pending.borrow_mut().remove(key));
+    if let Some(pending) = pending {
+        window()
+            .unwrap_throw()
+            .clear_timeout_with_handle(pending.timer);
+        // storage can be full, or unavailable in private browsing; the value is then simply not kept
+        if let Some(storage) = pending.storage.get() {
+            let _ = storage.set_item(key, &pending.json);
+        }
+        // dropping the closure while it runs, when the timer has fired, is safe: wasm-bindgen frees it once it
+        // returns
+    }
+}
@@ -0,0 +1,214 @@
+//! A base for custom elements that wrap a `<video>` or `<audio>` element. Requires the `player` feature.
+//!
+//! Implement [MediaComponent] for the component's own behavior and define it with [Player], which puts the media
+//! element in the custom element's shadow root and takes care of the rest:
+//!
+//! - The media attributes of the custom element, like `src`, `poster`, `autoplay`, `loop` and `muted`, are set on the
+//!   media element, so that `<my-video src="clip.mp4" muted>` works.
+//! - The custom element has `play()` and `pause()` methods, which call those of the media element.
+//! - The media element's events, like `play`, `timeupdate` and `ended`, are fired from the custom element too.
+//! - When the custom element is disconnected, playback stops and the media is released, so that a removed player
+//!   stops downloading; it is loaded again if the element is reconnected.
+//!
+//! ```rust,ignore
+//! #[derive(Default)]
+//! struct Trailer;
+//!
+//! impl MediaComponent for Trailer {
+//!     fn inject_children(&mut self, root: &HtmlElement, _media: &HtmlMediaElement) {
+//!         inject_style(root, "video { border-radius: 8px; }");
+//!     }
+//! }
+//!
+//! #[wasm_bindgen]
+//! pub fn define_elements() {
+//!     Player::<Trailer>::define("my-trailer");
+//! }
+//! ```
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, HtmlElement, HtmlMediaElement};
+
+use crate::{events, inject_style, CustomElement};
+
+/// The kind of media element a [Player] wraps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaKind {
+    Audio,
+    Video,
+}
+
+/// The attributes of a [Player] that are set on its media element.
+pub const MEDIA_ATTRIBUTES: &[&str] = &[
+    "autoplay",
+    "controls",
+    "crossorigin",
+    "loop",
+    "muted",
+    "playsinline",
+    "poster",
+    "preload",
+    "src",
+];
+
+/// The media events a [Player] fires from the custom element.
+pub const MEDIA_EVENTS: &[&str] = &[
+    "canplay",
+    "durationchange",
+    "emptied",
+    "ended",
+    "error",
+    "loadeddata",
+    "loadedmetadata",
+    "pause",
+    "play",
+    "playing",
+    "progress",
+    "ratechange",
+    "seeked",
+    "seeking",
+    "stalled",
+    "timeupdate",
+    "volumechange",
+    "waiting",
+];
+
+/// A component that wraps a media element, defined with [Player].
+pub trait MediaComponent: Default + 'static {
+    /// The kind of media element to create.
+    ///
+    /// Defaults to [MediaKind::Video].
+    fn kind() -> MediaKind {
+        MediaKind::Video
+    }
+
+    /// Whether the media element shows the browser's controls, whether or not the custom element has a `controls`
+    /// attribute.
+    ///
+    /// Defaults to `true`.
+    fn controls() -> bool {
+        true
+    }
+
+    /// Invoked once the media element has been added to the shadow root, `root`, to add anything else, like styles or
+    /// custom controls.
+    fn inject_children(&mut self, _root: &HtmlElement, _media: &HtmlMediaElement) {}
+
+    /// The names of the attributes, besides the [media attributes](MEDIA_ATTRIBUTES), whose changes should be
+    /// observed, like [CustomElement::observed_attributes].
+    fn observed_attributes() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Invoked each time one of the observed attributes changes.
+    fn attribute_changed_callback(
+        &mut self,
+        _media: &HtmlMediaElement,
+        _name: String,
+        _old_value: Option<String>,
+        _new_value: Option<String>,
+    ) {
+    }
+}
+
+/// A custom element that wraps a [MediaComponent]'s `<video>` or `<audio>` element. See the
+/// [module documentation](self).
+#[derive(Default)]
+pub struct Player<T: MediaComponent> {
+    component: T,
+    media: Option<HtmlMediaElement>,
+}
+
+impl<T: MediaComponent> Player<T> {
+    fn media(&self) -> &HtmlMediaElement {
+        self.media
+            .as_ref()
+            .expect_throw("the media element is created in inject_children")
+    }
+}
+
+impl<T: MediaComponent> CustomElement for Player<T> {
+    fn inject_children(&mut self, this: &HtmlElement) {
+        inject_style(
+            this,
+            ":host { display: inline-block; } video { display: block; width: 100%; height: 100%; }",
+        );
+        let tag_name = match T::kind() {
+            MediaKind::Audio => "audio",
+            MediaKind::Video => "video",
+        };
+        let media: HtmlMediaElement = window()
+            .unwrap_throw()
+            .document()
+            .unwrap_throw()
+            .create_element(tag_name)
+            .unwrap_throw()
+            .unchecked_into();
+        media.set_controls(T::controls());
+        this.append_child(&media).unwrap_throw();
+
+        // methods on the element, which only hold the media element, so that they don't keep the component alive
+        let host = crate::host(this);
+        let play = Closure::wrap(Box::new({
+            let media = media.clone();
+            move || media.play().unwrap_throw()
+        }) as Box<dyn FnMut() -> js_sys::Promise>);
+        let pause = Closure::wrap(Box::new({
+            let media = media.clone();
+            move || media.pause().unwrap_throw()
+        }) as Box<dyn FnMut()>);
+        js_sys::Reflect::set(&host, &"play".into(), &play.into_js_value()).unwrap_throw();
+        js_sys::Reflect::set(&host, &"pause".into(), &pause.into_js_value()).unwrap_throw();
+
+        self.component.inject_children(this, &media);
+        self.media = Some(media);
+    }
+
+    fn observe_all_attributes() -> bool {
+        true
+    }
+
+    fn connected_callback(&mut self, this: &HtmlElement) {
+        let host = crate::host(this);
+        let media = self.media().clone();
+        // restore the source released on disconnect
+        if media.get_attribute("src").is_none() {
+            if let Some(src) = host.get_attribute("src") {
+                media.set_src(&src);
+            }
+        }
+        events::forward_events(&media, this, MEDIA_EVENTS);
+    }
+
+    fn disconnected_callback(&mut self, _this: &HtmlElement) {
+        let media = self.media();
+        media.pause().unwrap_throw();
+        media.remove_attribute("src").unwrap_throw();
+        media.load();
+    }
+
+    fn attribute_changed_callback(
+        &mut self,
+        _this: &HtmlElement,
+        name: String,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    ) {
+        let media = self.media().clone();
+        if MEDIA_ATTRIBUTES.contains(&name.as_str()) {
+            match &new_value {
+                Some(value) => media.set_attribute(&name, value).unwrap_throw(),
+                None if name == "controls" && T::controls() => {}
+                None => media.remove_attribute(&name).unwrap_throw(),
+            }
+            // the `muted` attribute only sets whether the media starts out muted
+            if name == "muted" {
+                media.set_muted(new_value.is_some());
+            }
+        } else if T::observed_attributes().contains(&name.as_str()) {
+            self.component
+                .attribute_changed_callback(&media, name, old_value, new_value);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Pools of rendered DOM, for pages that create and destroy many identical elements, like the rows of a
+//! virtualized list.
+//!
+//! Each component type has its own pool, keyed by the type itself. The first time a type's DOM is needed, it is
+//! built once as a template; after that, each instance gets either a deep copy of the template, made in the
+//! instance's own document, which is much cheaper than building it again, or a fragment that a disconnected instance
+//! [recycled](recycle).
+//!
+//! ```rust,ignore
+//! fn inject_children(&mut self, this: &HtmlElement) {
+//!     let fragment = pool::take::<Self>(this, build_row);
+//!     self.label = fragment.query_selector(".label").unwrap_throw();
+//!     this.append_child(&fragment).unwrap_throw();
+//! }
+//!
+//! fn disconnected_callback(&mut self, this: &HtmlElement) {
+//!     if let Some(root) = this.shadow_root() {
+//!         pool::recycle::<Self>(&root);
+//!     }
+//! }
+//! ```
+//!
+//! A recycled fragment keeps whatever changes its last owner made to it, so components should set all of their
+//! dynamic content when they take one, rather than assuming it matches the template.
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Document, DocumentFragment, HtmlElement, Node};
+
+use crate::document_of;
+
+// the number of recycled fragments kept for each type, unless set with `set_capacity`
+const DEFAULT_CAPACITY: usize = 64;
+
+struct Pool {
+    template: Option<Node>,
+    free: Vec<DocumentFragment>,
+    capacity: usize,
+}
+
+impl Default for Pool {
+    fn default() -> Self {
+        Self {
+            template: None,
+            free: Vec::new(),
+            capacity: DEFAULT_CAPACITY,
+        }
+    }
+}
+
+thread_local! {
+    static POOLS: RefCell<HashMap<TypeId, Pool>> = RefCell::new(HashMap::new());
+}
+
+fn with_pool<T: 'static, U>(f: impl FnOnce(&mut Pool) -> U) -> U {
+    POOLS.with(|pools| f(pools.borrow_mut().entry(TypeId::of::<T>()).or_default()))
+}
+
+// `T`'s template, building it with `build` if this is the first time it is needed.
+fn template<T: 'static>(build: impl FnOnce() -> Node) -> Node {
+    let template = with_pool::<T, _>(|pool| pool.template.clone());
+    template.unwrap_or_else(|| {
+        let template = build();
+        with_pool::<T, _>(|pool| pool.template = Some(template.clone()));
+        template
+    })
+}
+
+// Copies `template` into `document`, which is not the one it was built in for an element in an iframe.
+fn clone_template(document: &Document, template: &Node) -> DocumentFragment {
+    let clone = document
+        .import_node_with_deep(template, true)
+        .unwrap_throw();
+    match clone.dyn_into::<DocumentFragment>() {
+        Ok(fragment) => fragment,
+        Err(node) => {
+            let fragment = document.create_document_fragment();
+            fragment.append_child(&node).unwrap_throw();
+            fragment
+        }
+    }
+}
+
+/// Returns a fragment with `T`'s DOM for the element `this`, either recycled from a previous instance or copied
+/// from its template into the element's document.
+///
+/// `build` creates the template, the first time it is needed; it can return a single element or a
+/// [DocumentFragment] with several.
+pub fn take<T: 'static>(this: &HtmlElement, build: impl FnOnce() -> Node) -> DocumentFragment {
+    let document = document_of(this);
+    let recycled = with_pool::<T, _>(|pool| {
+        let index = pool
+            .free
+            .iter()
+            .rposition(|fragment| document_of(fragment) == document)?;
+        Some(pool.free.remove(index))
+    });
+    match recycled {
+        Some(fragment) => fragment,
+        None => clone_template(&document, &template::<T>(build)),
+    }
+}
+
+/// Moves all of `root`'s children into a fragment and keeps it for the next instance of `T` that calls [take],
+/// unless `T`'s pool is already full, in which case they are simply removed.
+pub fn recycle<T: 'static>(root: &Node) {
+    let fragment = document_of(root).create_document_fragment();
+    while let Some(child) = root.first_child() {
+        fragment.append_child(&child).unwrap_throw();
+    }
+    with_pool::<T, _>(|pool| {
+        if pool.free.len() < pool.capacity {
+            pool.free.push(fragment);
+        }
+    });
+}
+
+/// Fills `T`'s pool with up to `count` fragments cloned from its template ahead of time, for example while the page
+/// is idle before a long list is rendered. The fragments are in the document the template was built in, so
+/// elements in other documents, like an iframe's, copy the template instead.
+pub fn prefill<T: 'static>(count: usize, build: impl FnOnce() -> Node) {
+    let template = template::<T>(build);
+    let missing =
+        with_pool::<T, _>(|pool| count.min(pool.capacity).saturating_sub(pool.free.len()));
+    for _ in 0..missing {
+        let fragment = clone_template(&document_of(&template), &template);
+        with_pool::<T, _>(|pool| pool.free.push(fragment));
+    }
+}
+
+/// Sets the number of recycled fragments kept for `T`, dropping any beyond it. Defaults to 64.
+pub fn set_capacity<T: 'static>(capacity: usize) {
+    with_pool::<T, _>(|pool| {
+        pool.capacity = capacity;
+        pool.free.truncate(capacity);
+    });
+}
+
+/// Drops `T`'s template and recycled fragments, for example after the way it renders has changed.
+pub fn clear<T: 'static>() {
+    POOLS.with(|pools| pools.borrow_mut().remove(&TypeId::of::<T>()));
+}
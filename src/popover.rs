@@ -0,0 +1,153 @@
+//! The top layer: popovers and modal dialogs.
+//!
+//! Elements in the top layer are drawn above everything else in the document, whatever the `overflow` and `z-index`
+//! of their ancestors, which makes it the right place for dropdowns, tooltips and menus. These helpers wrap the
+//! [Popover API](https://developer.mozilla.org/en-US/docs/Web/API/Popover_API) and modal `<dialog>`s, and tie the
+//! event listeners they add to the custom element that uses them, so that they are removed when it is disconnected:
+//!
+//! ```rust,ignore
+//! fn inject_children(&mut self, this: &HtmlElement) {
+//!     this.set_inner_html(r#"<button data-ref="trigger">Menu</button><div data-ref="menu">...</div>"#);
+//!     self.refs = Refs::collect(this);
+//!     let menu: HtmlElement = self.refs.expect("menu");
+//!     popover::make_popover(&menu, PopoverMode::Auto);
+//!     popover::set_invoker(&self.refs.expect("trigger"), &menu);
+//! }
+//!
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     let menu: HtmlElement = self.refs.expect("menu");
+//!     popover::on_toggle(this, &menu, |open| log(&format!("menu open: {}", open)));
+//! }
+//! ```
+//!
+//! Browsers without the Popover API ignore the `popover` attribute, and [show] and [hide] do nothing there.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, Event, EventTarget, HtmlDialogElement, HtmlElement, ToggleEvent};
+
+use crate::host;
+use crate::scope::Scope;
+
+/// How a popover is dismissed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PopoverMode {
+    /// Closed by clicking outside it or pressing Escape, and when another `Auto` popover opens, except one nested in
+    /// it. Suits menus and dropdowns.
+    Auto,
+    /// Only closed explicitly, with [hide] or an invoker. Suits toasts.
+    Manual,
+}
+
+impl PopoverMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            PopoverMode::Auto => "auto",
+            PopoverMode::Manual => "manual",
+        }
+    }
+}
+
+/// Makes `element` a popover, hidden until it is shown, by setting its `popover` attribute. `element` may be the
+/// custom element itself.
+pub fn make_popover(element: &HtmlElement, mode: PopoverMode) {
+    element
+        .set_attribute("popover", mode.as_str())
+        .unwrap_throw();
+}
+
+/// Shows a popover in the top layer. Does nothing if it is already shown, or the browser has no Popover API.
+pub fn show(popover: &HtmlElement) {
+    if supported(popover) && !is_open(popover) {
+        popover.show_popover().unwrap_throw();
+    }
+}
+
+/// Hides a popover. Does nothing if it is already hidden, or the browser has no Popover API.
+pub fn hide(popover: &HtmlElement) {
+    if supported(popover) && is_open(popover) {
+        popover.hide_popover().unwrap_throw();
+    }
+}
+
+/// Shows a popover if it is hidden and hides it otherwise, and returns whether it is now shown.
+pub fn toggle(popover: &HtmlElement) -> bool {
+    if is_open(popover) {
+        hide(popover);
+    } else {
+        show(popover);
+    }
+    is_open(popover)
+}
+
+/// Whether a popover is shown.
+pub fn is_open(popover: &HtmlElement) -> bool {
+    popover.matches(":popover-open").unwrap_or(false)
+}
+
+/// Makes clicking `invoker`, a `<button>` or `<input type="button">`, toggle `popover`, as its `popovertarget`
+/// attribute would. Unlike the attribute, this works when the two are in different shadow roots. As with the
+/// attribute, clicking the invoker does not count as clicking outside an `Auto` popover.
+pub fn set_invoker(invoker: &Element, popover: &HtmlElement) {
+    js_sys::Reflect::set(invoker, &JsValue::from_str("popoverTargetElement"), popover)
+        .unwrap_throw();
+}
+
+/// Calls `handler` with whether `popover` is now shown each time it is shown or hidden, however that happened,
+/// until the custom element is disconnected. `this` may be either the element or its shadow root.
+pub fn on_toggle(
+    this: &HtmlElement,
+    popover: &HtmlElement,
+    mut handler: impl FnMut(bool) + 'static,
+) {
+    listen(this, popover, "toggle", move |event| {
+        let event: &ToggleEvent = event.unchecked_ref();
+        handler(event.new_state() == "open");
+    });
+}
+
+/// Shows a `<dialog>` as a modal in the top layer, making the rest of the document inert until it is closed. Does
+/// nothing if it is already open.
+pub fn show_modal(dialog: &HtmlDialogElement) {
+    if !dialog.open() {
+        dialog.show_modal().unwrap_throw();
+    }
+}
+
+/// Calls `handler` with the dialog's [return value](HtmlDialogElement::return_value) each time `dialog` is closed,
+/// including by pressing Escape, until the custom element is disconnected. `this` may be either the element or its
+/// shadow root.
+pub fn on_close(
+    this: &HtmlElement,
+    dialog: &HtmlDialogElement,
+    mut handler: impl FnMut(String) + 'static,
+) {
+    let target = dialog.clone();
+    listen(this, dialog, "close", move |_| {
+        handler(target.return_value())
+    });
+}
+
+fn supported(popover: &HtmlElement) -> bool {
+    js_sys::Reflect::has(popover, &JsValue::from_str("showPopover")).unwrap_or(false)
+}
+
+// Listens for `event_type` on `target` until the custom element is disconnected.
+fn listen(
+    this: &HtmlElement,
+    target: &EventTarget,
+    event_type: &'static str,
+    mut handler: impl FnMut(&Event) + 'static,
+) {
+    let listener =
+        Closure::wrap(Box::new(move |event: Event| handler(&event)) as Box<dyn FnMut(Event)>);
+    target
+        .add_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref())
+        .unwrap_throw();
+    let target = target.clone();
+    Scope::new(&host(this)).on_cleanup(move || {
+        target
+            .remove_event_listener_with_callback(event_type, listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+    });
+}
@@ -0,0 +1,72 @@
+//! Rendering part of a component somewhere else in the document.
+//!
+//! A modal, toast or tooltip inside a component is clipped by its ancestors' `overflow` and stacked by their
+//! `z-index`. [portal] gives the component a container elsewhere in the document, usually at the end of the `<body>`,
+//! to render that part of its content into instead. The container still belongs to the component: it is removed when
+//! the component's element is disconnected, and [owner] finds the element from anything inside it.
+//!
+//! ```rust,ignore
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     let dialog = portal(this, "body");
+//!     dialog.set_inner_html("<div class=\"modal\">...</div>");
+//!     self.dialog = Some(dialog);
+//! }
+//! ```
+//!
+//! The container is outside the component's shadow root, so the component's styles do not apply to it; give it
+//! its own shadow root, or style it with the page's stylesheets. Events fired inside it do not pass through the
+//! component either.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, HtmlElement, Node};
+
+use crate::scope::Scope;
+use crate::{defined_tag_name, host};
+
+// the property of a portal's container that refers to the element that owns it
+const OWNER: &str = "__portalOwner";
+
+/// Appends an empty container to the element matching `target_selector` in the document, like `"body"` or
+/// `"#overlays"`, and returns it. The container is removed the next time the element is disconnected, so portals
+/// are usually opened in [connected_callback](crate::CustomElement::connected_callback). `this` may be either the
+/// element or its shadow root.
+///
+/// The container is a `<div>` with a `data-portal` attribute set to the element's tag name.
+///
+/// Panics if no element matches `target_selector`.
+pub fn portal(this: &HtmlElement, target_selector: &str) -> HtmlElement {
+    let host = host(this);
+    let document = window().unwrap_throw().document().unwrap_throw();
+    let target = document
+        .query_selector(target_selector)
+        .unwrap_throw()
+        .expect_throw("no element matches the portal's target selector");
+    let container: HtmlElement = document
+        .create_element("div")
+        .unwrap_throw()
+        .unchecked_into();
+    container
+        .set_attribute("data-portal", &defined_tag_name(&host))
+        .unwrap_throw();
+    js_sys::Reflect::set(&container, &JsValue::from_str(OWNER), &host).unwrap_throw();
+    target.append_child(&container).unwrap_throw();
+    Scope::new(&host).on_cleanup({
+        let container = container.clone();
+        move || container.remove()
+    });
+    container
+}
+
+/// The element that owns the innermost portal containing `node`, if it is inside one.
+pub fn owner(node: &Node) -> Option<HtmlElement> {
+    let mut node = Some(node.clone());
+    while let Some(current) = node {
+        let owner = js_sys::Reflect::get(&current, &JsValue::from_str(OWNER)).unwrap_throw();
+        if let Ok(owner) = owner.dyn_into::<HtmlElement>() {
+            return Some(owner);
+        }
+        node = current.parent_node();
+    }
+    None
+}
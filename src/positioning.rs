@@ -0,0 +1,339 @@
+//! Keeping a floating element, like a dropdown or tooltip, next to the element it belongs to.
+//!
+//! [anchor] positions a floating element on one side of an anchor element, flipping it to the opposite side when
+//! there is not enough room in the viewport, and keeps it there as the page scrolls and resizes, until the custom
+//! element is disconnected:
+//!
+//! ```rust,ignore
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     let (trigger, menu): (Element, HtmlElement) = (self.refs.expect("trigger"), self.refs.expect("menu"));
+//!     positioning::anchor(this, &menu, &trigger, "bottom-start".parse().unwrap_throw(), 4.0);
+//! }
+//! ```
+//!
+//! Where the browser supports [CSS anchor positioning](https://developer.mozilla.org/en-US/docs/Web/CSS/CSS_anchor_positioning),
+//! the browser does this itself, and no listeners are needed. Anchor names only reach elements in the same tree,
+//! so the two elements must then be in the same shadow root, or both outside any. Elsewhere, the floating element is
+//! positioned with `position: fixed`, from [place], on each scroll and resize.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, DomRect, Element, HtmlElement};
+
+use crate::host;
+use crate::scope::Scope;
+
+/// The side of the anchor a floating element is placed on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Side {
+    fn as_str(self) -> &'static str {
+        match self {
+            Side::Top => "top",
+            Side::Bottom => "bottom",
+            Side::Left => "left",
+            Side::Right => "right",
+        }
+    }
+
+    fn opposite(self) -> Self {
+        match self {
+            Side::Top => Side::Bottom,
+            Side::Bottom => Side::Top,
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+
+    fn is_vertical(self) -> bool {
+        matches!(self, Side::Top | Side::Bottom)
+    }
+}
+
+/// How a floating element is aligned along the side of the anchor: with the anchor's left or top edge (`Start`), its
+/// center, or its right or bottom edge (`End`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+}
+
+/// Where a floating element goes relative to its anchor. Parsed from strings like `"bottom"` or `"right-start"`: a
+/// side, optionally followed by `-start` or `-end`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub side: Side,
+    pub align: Align,
+}
+
+impl FromStr for Placement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (side, align) = match s.split_once('-') {
+            Some((side, "start")) => (side, Align::Start),
+            Some((side, "end")) => (side, Align::End),
+            Some(_) => return Err(format!("unknown alignment in placement {:?}", s)),
+            None => (s, Align::Center),
+        };
+        let side = [Side::Top, Side::Bottom, Side::Left, Side::Right]
+            .iter()
+            .copied()
+            .find(|candidate| candidate.as_str() == side)
+            .ok_or_else(|| format!("unknown side in placement {:?}", s))?;
+        Ok(Placement { side, align })
+    }
+}
+
+/// A rectangle in viewport coordinates, in CSS pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl From<DomRect> for Rect {
+    fn from(rect: DomRect) -> Self {
+        Rect {
+            x: rect.x(),
+            y: rect.y(),
+            width: rect.width(),
+            height: rect.height(),
+        }
+    }
+}
+
+/// Where to put a floating element of size `floating` (only its width and height are used) next to `anchor`, `offset`
+/// pixels away from it, in a viewport of size `viewport`. The element goes on the opposite side if it does not fit on
+/// the side asked for and fits better there, and is then shifted along that side to stay inside the viewport.
+/// Returns its position, and the placement it got.
+pub fn place(
+    anchor: Rect,
+    floating: Rect,
+    viewport: Rect,
+    placement: Placement,
+    offset: f64,
+) -> (Rect, Placement) {
+    // the room on each side of the anchor, less the offset
+    let room = |side| match side {
+        Side::Top => anchor.y - offset,
+        Side::Bottom => viewport.height - (anchor.y + anchor.height) - offset,
+        Side::Left => anchor.x - offset,
+        Side::Right => viewport.width - (anchor.x + anchor.width) - offset,
+    };
+    let needed = if placement.side.is_vertical() {
+        floating.height
+    } else {
+        floating.width
+    };
+    let mut placed = placement;
+    if room(placement.side) < needed && room(placement.side.opposite()) > room(placement.side) {
+        placed.side = placement.side.opposite();
+    }
+
+    let align = |start: f64, anchor_size: f64, size: f64, viewport_size: f64| {
+        let position = match placed.align {
+            Align::Start => start,
+            Align::Center => start + (anchor_size - size) / 2.0,
+            Align::End => start + anchor_size - size,
+        };
+        position.min(viewport_size - size).max(0.0)
+    };
+    let (x, y) = match placed.side {
+        Side::Top => (
+            align(anchor.x, anchor.width, floating.width, viewport.width),
+            anchor.y - offset - floating.height,
+        ),
+        Side::Bottom => (
+            align(anchor.x, anchor.width, floating.width, viewport.width),
+            anchor.y + anchor.height + offset,
+        ),
+        Side::Left => (
+            anchor.x - offset - floating.width,
+            align(anchor.y, anchor.height, floating.height, viewport.height),
+        ),
+        Side::Right => (
+            anchor.x + anchor.width + offset,
+            align(anchor.y, anchor.height, floating.height, viewport.height),
+        ),
+    };
+    (
+        Rect {
+            x,
+            y,
+            width: floating.width,
+            height: floating.height,
+        },
+        placed,
+    )
+}
+
+/// Positions `floating` on the side of `anchor` given by `placement`, `offset` pixels away from it, and keeps it
+/// there until the custom element is disconnected. `this` may be either the element or its shadow root. See the
+/// [module documentation](self).
+///
+/// The side the element ended up on, after any flip, is set as its `data-placement` attribute, for styles like
+/// arrows to follow, when the browser does not position it itself.
+pub fn anchor(
+    this: &HtmlElement,
+    floating: &HtmlElement,
+    anchor: &Element,
+    placement: Placement,
+    offset: f64,
+) {
+    if web_sys::css::supports_with_value("anchor-name", "--a").unwrap_or(false) {
+        anchor_with_css(floating, anchor, placement, offset);
+    } else {
+        anchor_with_listeners(this, floating, anchor, placement, offset);
+    }
+}
+
+thread_local! {
+    static NEXT_ANCHOR: Cell<u32> = const { Cell::new(0) };
+}
+
+fn anchor_with_css(floating: &HtmlElement, anchor: &Element, placement: Placement, offset: f64) {
+    let name = format!(
+        "--ce-anchor-{}",
+        NEXT_ANCHOR.with(|next| next.replace(next.get() + 1))
+    );
+    js_sys::Reflect::get(anchor, &JsValue::from_str("style"))
+        .unwrap_throw()
+        .unchecked_into::<web_sys::CssStyleDeclaration>()
+        .set_property("anchor-name", &name)
+        .unwrap_throw();
+
+    let span = match (placement.side.is_vertical(), placement.align) {
+        (_, Align::Center) => "",
+        (true, Align::Start) => " span-right",
+        (true, Align::End) => " span-left",
+        (false, Align::Start) => " span-bottom",
+        (false, Align::End) => " span-top",
+    };
+    let (margin, flip) = match placement.side {
+        Side::Top => ("margin-bottom", "flip-block"),
+        Side::Bottom => ("margin-top", "flip-block"),
+        Side::Left => ("margin-right", "flip-inline"),
+        Side::Right => ("margin-left", "flip-inline"),
+    };
+    let style = floating.style();
+    for (property, value) in [
+        ("position", "fixed".to_string()),
+        ("inset", "auto".to_string()),
+        ("margin", "0".to_string()),
+        ("position-anchor", name),
+        (
+            "position-area",
+            format!("{}{}", placement.side.as_str(), span),
+        ),
+        (margin, format!("{}px", offset)),
+        ("position-try-fallbacks", flip.to_string()),
+    ] {
+        style.set_property(property, &value).unwrap_throw();
+    }
+}
+
+fn anchor_with_listeners(
+    this: &HtmlElement,
+    floating: &HtmlElement,
+    anchor: &Element,
+    placement: Placement,
+    offset: f64,
+) {
+    let update = {
+        let (floating, anchor) = (floating.clone(), anchor.clone());
+        move || {
+            let window = window().unwrap_throw();
+            let viewport = Rect {
+                x: 0.0,
+                y: 0.0,
+                width: window
+                    .inner_width()
+                    .unwrap_throw()
+                    .as_f64()
+                    .unwrap_or_default(),
+                height: window
+                    .inner_height()
+                    .unwrap_throw()
+                    .as_f64()
+                    .unwrap_or_default(),
+            };
+            let (rect, placed) = place(
+                anchor.get_bounding_client_rect().into(),
+                floating.get_bounding_client_rect().into(),
+                viewport,
+                placement,
+                offset,
+            );
+            let style = floating.style();
+            for (property, value) in [
+                ("position", "fixed".to_string()),
+                ("margin", "0".to_string()),
+                ("left", format!("{}px", rect.x)),
+                ("top", format!("{}px", rect.y)),
+            ] {
+                style.set_property(property, &value).unwrap_throw();
+            }
+            floating
+                .set_attribute("data-placement", placed.side.as_str())
+                .unwrap_throw();
+        }
+    };
+    update();
+
+    // at most one update per frame, however many scroll events there are
+    let update = Rc::new(update);
+    let scheduled = Rc::new(Cell::new(false));
+    let listener = Closure::wrap(Box::new(move || {
+        if scheduled.replace(true) {
+            return;
+        }
+        let (update, scheduled) = (update.clone(), scheduled.clone());
+        let run = Closure::once_into_js(move || {
+            scheduled.set(false);
+            update();
+        });
+        window()
+            .unwrap_throw()
+            .request_animation_frame(run.unchecked_ref())
+            .unwrap_throw();
+    }) as Box<dyn FnMut()>);
+
+    // scroll events don't bubble, but are captured from any scrolling container
+    let window = window().unwrap_throw();
+    window
+        .add_event_listener_with_callback_and_bool(
+            "scroll",
+            listener.as_ref().unchecked_ref(),
+            true,
+        )
+        .unwrap_throw();
+    window
+        .add_event_listener_with_callback("resize", listener.as_ref().unchecked_ref())
+        .unwrap_throw();
+    Scope::new(&host(this)).on_cleanup(move || {
+        window
+            .remove_event_listener_with_callback_and_bool(
+                "scroll",
+                listener.as_ref().unchecked_ref(),
+                true,
+            )
+            .unwrap_throw();
+        window
+            .remove_event_listener_with_callback("resize", listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+    });
+}
@@ -0,0 +1,85 @@
+//! Named references to the elements inside a component.
+//!
+//! Rather than keeping a struct field for each element a component needs to reach after creating its DOM, it can mark
+//! them with `data-ref` attributes in its template and [collect](Refs::collect) them into a [Refs] map, which it
+//! keeps instead:
+//!
+//! ```rust,ignore
+//! fn inject_children(&mut self, this: &HtmlElement) {
+//!     this.set_inner_html(r#"<input data-ref="query"><ul data-ref="results"></ul>"#);
+//!     self.refs = Refs::collect(this);
+//! }
+//!
+//! fn attribute_changed_callback(&mut self, _this: &HtmlElement, _name: String, _old: Option<String>, new: Option<String>) {
+//!     let query: HtmlInputElement = self.refs.expect("query");
+//!     query.set_value(&new.unwrap_or_default());
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement};
+
+use crate::query_all;
+
+/// Elements inside a component, by name. See the [module documentation](self).
+#[derive(Clone, Debug, Default)]
+pub struct Refs {
+    elements: HashMap<String, Element>,
+}
+
+impl Refs {
+    /// An empty map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The elements inside the component that have a `data-ref` attribute, by its value, searching the shadow root if
+    /// the element has one and the element itself otherwise, as [query](crate::query) does. Elements inside nested
+    /// shadow roots are not included. If several elements have the same name, the last one wins.
+    pub fn collect(this: &HtmlElement) -> Self {
+        let mut refs = Self::new();
+        refs.extend(this);
+        refs
+    }
+
+    /// Adds the elements inside the component that have a `data-ref` attribute, as [collect](Refs::collect) does,
+    /// replacing any with the same names. Useful after adding more DOM to the component.
+    pub fn extend(&mut self, this: &HtmlElement) {
+        for element in query_all::<Element>(this, "[data-ref]") {
+            if let Some(name) = element.get_attribute("data-ref") {
+                self.elements.insert(name, element);
+            }
+        }
+    }
+
+    /// Adds an element created without a `data-ref` attribute, replacing any with the same name.
+    pub fn insert(&mut self, name: impl Into<String>, element: &Element) {
+        self.elements.insert(name.into(), element.clone());
+    }
+
+    /// Removes the element with the given name, returning it if there was one.
+    pub fn remove(&mut self, name: &str) -> Option<Element> {
+        self.elements.remove(name)
+    }
+
+    /// The element with the given name, or `None` if there is none or it is not a `T`.
+    pub fn get<T: JsCast>(&self, name: &str) -> Option<T> {
+        self.elements.get(name)?.clone().dyn_into().ok()
+    }
+
+    /// The element with the given name.
+    ///
+    /// Panics if there is none or it is not a `T`.
+    pub fn expect<T: JsCast>(&self, name: &str) -> T {
+        self.get(name)
+            .expect_throw("no element with this name in Refs, or it has a different type")
+    }
+
+    /// Whether there is an element with the given name.
+    pub fn contains(&self, name: &str) -> bool {
+        self.elements.contains_key(name)
+    }
+}
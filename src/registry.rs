@@ -0,0 +1,329 @@
+//! Defining elements from a manifest decided at runtime.
+//!
+//! A page whose components are chosen by a CMS, say, can ship every component in its bundle but only define the ones a
+//! page asks for. Each component is [registered](register) under an identifier first, and then
+//! [define_from_manifest] defines the elements a JSON manifest lists:
+//!
+//! ```rust,ignore
+//! registry::register::<Chart>("chart");
+//! registry::register::<Gallery>("gallery");
+//! registry::define_from_manifest(r#"[
+//!     { "tag": "cms-chart", "factory": "chart", "observedAttributes": ["data", "kind"] },
+//!     { "tag": "cms-gallery", "factory": "gallery", "shadow": false }
+//! ]"#)?;
+//! ```
+//!
+//! The manifest is an array with an entry for each element:
+//!
+//! - `tag`, the tag name to define, and `factory`, the identifier the component was registered under, are required.
+//! - `shadow`, `observedAttributes`, and `observedBoolAttributes` are optional, and replace the
+//!   [ElementOptions] the component was registered with.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::{
+    define_batch, CustomElement, CustomElementFactory, DynCustomElement, ElementOptions,
+    ElementSpec,
+};
+
+type Factory = Rc<dyn Fn() -> Box<dyn DynCustomElement>>;
+
+thread_local! {
+    static FACTORIES: RefCell<Vec<(String, ElementOptions, Factory)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Registers a component under `id`, with the options its [CustomElement] implementation gives, so that manifests
+/// can define elements with it.
+pub fn register<T: CustomElement + CustomElementFactory>(id: &str) {
+    register_boxed(
+        id,
+        ElementOptions::of::<T>(),
+        Box::new(|| Box::new(T::create())),
+    );
+}
+
+/// Registers a component created at runtime under `id`, like [define_boxed](crate::define_boxed) but without defining
+/// it yet. Replaces any component already registered under `id`.
+pub fn register_boxed(
+    id: &str,
+    options: ElementOptions,
+    factory: Box<dyn Fn() -> Box<dyn DynCustomElement>>,
+) {
+    FACTORIES.with(|factories| {
+        let mut factories = factories.borrow_mut();
+        factories.retain(|(registered, _, _)| registered != id);
+        factories.push((id.to_string(), options, factory.into()));
+    });
+}
+
+/// Defines each element listed in `json`, a manifest as described in the [module documentation](self), and returns
+/// their tag names.
+///
+/// Returns an error, before defining any elements, if `json` is not a valid manifest or names a component that has not
+/// been registered.
+pub fn define_from_manifest(json: &str) -> Result<Vec<String>, JsValue> {
+    let manifest = Value::from_js(&js_sys::JSON::parse(json)?);
+    let entries = parse_manifest(&manifest)?
+        .into_iter()
+        .map(resolve)
+        .collect::<Result<Vec<_>, _>>()?;
+    let specs = entries
+        .into_iter()
+        .map(|(tag_name, options, factory)| {
+            ElementSpec::boxed(&tag_name, options, Box::new(move || factory()))
+        })
+        .collect::<Vec<_>>();
+    define_batch(&specs);
+    Ok(specs
+        .iter()
+        .map(|spec| spec.tag_name().to_string())
+        .collect())
+}
+
+// A parsed JSON value, as far as validating a manifest is concerned.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Null,
+    Bool(bool),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+    Other,
+}
+
+impl Value {
+    fn from_js(value: &JsValue) -> Self {
+        if value.is_undefined() || value.is_null() {
+            Value::Null
+        } else if let Some(value) = value.as_bool() {
+            Value::Bool(value)
+        } else if let Some(value) = value.as_string() {
+            Value::String(value)
+        } else if js_sys::Array::is_array(value) {
+            Value::Array(
+                js_sys::Array::from(value)
+                    .iter()
+                    .map(|value| Value::from_js(&value))
+                    .collect(),
+            )
+        } else if value.is_object() {
+            let fields = js_sys::Object::entries(value.unchecked_ref())
+                .iter()
+                .map(|entry| {
+                    let entry = js_sys::Array::from(&entry);
+                    (
+                        entry.get(0).as_string().unwrap_or_default(),
+                        Value::from_js(&entry.get(1)),
+                    )
+                })
+                .collect();
+            Value::Object(fields)
+        } else {
+            Value::Other
+        }
+    }
+}
+
+// An entry of a manifest, before its component has been looked up.
+#[derive(Debug, PartialEq)]
+struct Entry {
+    tag_name: String,
+    id: String,
+    shadow: Option<bool>,
+    observed_attributes: Option<Vec<String>>,
+    observed_bool_attributes: Option<Vec<String>>,
+}
+
+fn parse_manifest(manifest: &Value) -> Result<Vec<Entry>, String> {
+    match manifest {
+        Value::Array(entries) => entries.iter().map(parse_entry).collect(),
+        _ => Err("manifest must be an array".to_string()),
+    }
+}
+
+fn parse_entry(entry: &Value) -> Result<Entry, String> {
+    let tag_name = string(entry, "tag")?.ok_or("manifest entry has no \"tag\"")?;
+    let id = string(entry, "factory")?
+        .ok_or_else(|| format!("manifest entry for <{}> has no \"factory\"", tag_name))?;
+    let shadow = match field(entry, "shadow")? {
+        Some(Value::Bool(shadow)) => Some(*shadow),
+        Some(_) => return Err("\"shadow\" must be a boolean in manifest".to_string()),
+        None => None,
+    };
+    Ok(Entry {
+        tag_name,
+        id,
+        shadow,
+        observed_attributes: strings(entry, "observedAttributes")?,
+        observed_bool_attributes: strings(entry, "observedBoolAttributes")?,
+    })
+}
+
+// Looks up the component an entry names, and applies the entry's options to the ones it was registered with.
+fn resolve(entry: Entry) -> Result<(String, ElementOptions, Factory), String> {
+    let (mut options, factory) = FACTORIES
+        .with(|factories| {
+            factories
+                .borrow()
+                .iter()
+                .find(|(registered, _, _)| *registered == entry.id)
+                .map(|(_, options, factory)| (options.clone(), factory.clone()))
+        })
+        .ok_or_else(|| {
+            format!(
+                "no component registered as {:?}, for <{}>",
+                entry.id, entry.tag_name
+            )
+        })?;
+    if let Some(shadow) = entry.shadow {
+        options.shadow = shadow;
+    }
+    if let Some(attributes) = entry.observed_attributes {
+        options.observed_attributes = attributes;
+    }
+    if let Some(attributes) = entry.observed_bool_attributes {
+        options.observed_bool_attributes = attributes;
+    }
+    Ok((entry.tag_name, options, factory))
+}
+
+// The field `name` of a manifest entry, or `None` if it is missing.
+fn field<'a>(entry: &'a Value, name: &str) -> Result<Option<&'a Value>, String> {
+    match entry {
+        Value::Object(fields) => Ok(fields
+            .iter()
+            .find(|(field, value)| field == name && *value != Value::Null)
+            .map(|(_, value)| value)),
+        _ => Err("manifest entries must be objects".to_string()),
+    }
+}
+
+fn string(entry: &Value, name: &str) -> Result<Option<String>, String> {
+    match field(entry, name)? {
+        Some(Value::String(value)) => Ok(Some(value.clone())),
+        Some(_) => Err(format!("{:?} must be a string in manifest", name)),
+        None => Ok(None),
+    }
+}
+
+fn strings(entry: &Value, name: &str) -> Result<Option<Vec<String>>, String> {
+    let error = || format!("{:?} must be an array of strings in manifest", name);
+    match field(entry, name)? {
+        Some(Value::Array(values)) => values
+            .iter()
+            .map(|value| match value {
+                Value::String(value) => Ok(value.clone()),
+                _ => Err(error()),
+            })
+            .collect::<Result<_, _>>()
+            .map(Some),
+        Some(_) => Err(error()),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    fn object(fields: &[(&str, Value)]) -> Value {
+        Value::Object(
+            fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn parses_entries() {
+        let manifest = Value::Array(vec![
+            object(&[
+                ("tag", string("cms-chart")),
+                ("factory", string("chart")),
+                ("observedAttributes", Value::Array(vec![string("data")])),
+            ]),
+            object(&[
+                ("tag", string("cms-gallery")),
+                ("factory", string("gallery")),
+                ("shadow", Value::Bool(false)),
+                ("observedBoolAttributes", Value::Null),
+            ]),
+        ]);
+        assert_eq!(
+            parse_manifest(&manifest),
+            Ok(vec![
+                Entry {
+                    tag_name: "cms-chart".to_string(),
+                    id: "chart".to_string(),
+                    shadow: None,
+                    observed_attributes: Some(vec!["data".to_string()]),
+                    observed_bool_attributes: None,
+                },
+                Entry {
+                    tag_name: "cms-gallery".to_string(),
+                    id: "gallery".to_string(),
+                    shadow: Some(false),
+                    observed_attributes: None,
+                    observed_bool_attributes: None,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_manifests() {
+        let error = |manifest: Value| parse_manifest(&manifest).unwrap_err();
+        let entry = |fields: &[(&str, Value)]| {
+            let mut all = vec![("tag", string("cms-chart")), ("factory", string("chart"))];
+            all.extend(fields.iter().cloned());
+            Value::Array(vec![object(&all)])
+        };
+        assert_eq!(
+            error(object(&[("tag", string("cms-chart"))])),
+            "manifest must be an array"
+        );
+        assert_eq!(
+            error(Value::Array(vec![string("cms-chart")])),
+            "manifest entries must be objects"
+        );
+        assert_eq!(
+            error(Value::Array(vec![object(&[("factory", string("chart"))])])),
+            "manifest entry has no \"tag\""
+        );
+        assert_eq!(
+            error(Value::Array(vec![object(&[("tag", string("cms-chart"))])])),
+            "manifest entry for <cms-chart> has no \"factory\""
+        );
+        assert_eq!(
+            error(Value::Array(vec![object(&[
+                ("tag", Value::Bool(true)),
+                ("factory", string("chart")),
+            ])])),
+            "\"tag\" must be a string in manifest"
+        );
+        assert_eq!(
+            error(entry(&[("shadow", string("false"))])),
+            "\"shadow\" must be a boolean in manifest"
+        );
+        assert_eq!(
+            error(entry(&[("observedAttributes", string("data"))])),
+            "\"observedAttributes\" must be an array of strings in manifest"
+        );
+        assert_eq!(
+            error(entry(&[(
+                "observedBoolAttributes",
+                Value::Array(vec![string("open"), Value::Other]),
+            )])),
+            "\"observedBoolAttributes\" must be an array of strings in manifest"
+        );
+    }
+}
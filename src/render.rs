@@ -0,0 +1,314 @@
+//! A structured update model for components that manage their own DOM.
+//!
+//! Rebuilding a component's DOM each time one of its attributes changes is simple, but slow, and it loses state like
+//! focus and selection. A component that implements [Render] instead builds its DOM once, in [Render::create], which
+//! returns the nodes that can change later (its "bindings"), and then changes only those nodes in [Render::update].
+//!
+//! The crate calls `create` when the element is first connected, and `update` each time an update is
+//! [requested](request_update), batched so that several requests in the same task result in a single update.
+//! Changes to the element's observed attributes request an update automatically.
+//!
+//! ```rust,ignore
+//! struct Greeting {
+//!     name: String,
+//! }
+//!
+//! struct GreetingBindings {
+//!     name: Text,
+//! }
+//!
+//! impl Render for Greeting {
+//!     type Bindings = GreetingBindings;
+//!
+//!     fn create(&mut self, root: &HtmlElement) -> GreetingBindings {
+//!         root.set_inner_html("<p>Hello, <b></b>!</p>");
+//!         let name = document().create_text_node(&self.name);
+//!         query::<Element>(root, "b").unwrap_throw().append_child(&name).unwrap_throw();
+//!         GreetingBindings { name }
+//!     }
+//!
+//!     fn update(&mut self, bindings: &mut GreetingBindings) {
+//!         bindings.name.set_data(&self.name);
+//!     }
+//! }
+//!
+//! impl CustomElement for Greeting {
+//!     fn observed_attributes() -> &'static [&'static str] {
+//!         &["name"]
+//!     }
+//!
+//!     fn attribute_changed_callback(&mut self, _this: &HtmlElement, _name: String, _old: Option<String>, new: Option<String>) {
+//!         self.name = new.unwrap_or_default();
+//!     }
+//! }
+//!
+//! render::define::<Greeting>("my-greeting");
+//! ```
+//!
+//! Building a very large subtree at once, like thousands of rows, freezes the page until it is done.
+//! [chunked_render] builds it a few nodes at a time instead, in each animation frame, reporting its progress.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlElement, Node};
+
+use crate::definition::forward_dyn_custom_element;
+use crate::instances::dispatch;
+use crate::scope::Scope;
+use crate::{
+    define_element, host, register_constructor, window_of, CustomElement, CustomElementFactory,
+    DynCustomElement, ElementOptions,
+};
+
+/// A component that builds its DOM once and then updates only the parts of it that change.
+/// See the [module documentation](self).
+pub trait Render: CustomElement {
+    /// The nodes that [update](Render::update) changes, like text nodes and elements whose attributes depend on
+    /// the component's state.
+    type Bindings: 'static;
+
+    /// Builds the component's DOM inside `root`, which is the element's shadow root if it has one and the element
+    /// itself otherwise, and returns the nodes that later updates will change. Called when the element is first
+    /// connected.
+    fn create(&mut self, root: &HtmlElement) -> Self::Bindings;
+
+    /// Brings the DOM created by [create](Render::create) up to date with the component's state.
+    fn update(&mut self, bindings: &mut Self::Bindings);
+}
+
+/// Defines a custom element for a component that implements [Render], like [define](CustomElement::define).
+pub fn define<R: Render + CustomElementFactory>(tag_name: &'static str) {
+    let global = js_sys::global();
+    let constructor = define_element(&global, tag_name, ElementOptions::of::<R>(), || Rendered {
+        component: <R as CustomElementFactory>::create(),
+        bindings: None,
+    });
+    register_constructor::<R>(&global, constructor);
+}
+
+/// Schedules a call to the element's [scheduled_update](CustomElement::scheduled_update), which, for components
+/// [defined](define) with [Render], calls [Render::update]. Any further requests before it runs are ignored.
+pub fn request_update(this: &HtmlElement) {
+    let host = host(this);
+    let pending = JsValue::from_str("_updatePending");
+    if js_sys::Reflect::get(&host, &pending)
+        .unwrap_throw()
+        .is_truthy()
+    {
+        return;
+    }
+    js_sys::Reflect::set(&host, &pending, &JsValue::TRUE).unwrap_throw();
+
+    let run = Closure::once_into_js(move || {
+        js_sys::Reflect::set(&host, &JsValue::from_str("_updatePending"), &JsValue::FALSE)
+            .unwrap_throw();
+        if host.is_connected() {
+            dispatch(&host, "scheduledUpdate", &[]);
+        }
+    });
+    js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("queueMicrotask"))
+        .unwrap_throw()
+        .unchecked_into::<js_sys::Function>()
+        .call1(&JsValue::NULL, &run)
+        .unwrap_throw();
+}
+
+/// Appends the node returned by each of `builders` to `root`, running as many of them as fit in `budget_ms`
+/// milliseconds in each animation frame, so that the page stays responsive while a large subtree is built. `root` is
+/// usually the element's shadow root, or an element inside it.
+///
+/// After each frame, `progress` is called with the number of nodes built so far and, if the iterator knows its
+/// length, the total. Nothing more is built once the element, `this`, is disconnected.
+///
+/// ```rust,ignore
+/// fn connected_callback(&mut self, this: &HtmlElement) {
+///     let rows = self.rows.clone();
+///     let list: HtmlElement = self.refs.expect("list");
+///     render::chunked_render(this, &list, rows.into_iter().map(|row| move || render_row(&row)), 4.0, |done, total| {
+///         log(&format!("{} of {:?} rows", done, total));
+///     });
+/// }
+/// ```
+pub fn chunked_render<I, F>(
+    this: &HtmlElement,
+    root: &Node,
+    builders: I,
+    budget_ms: f64,
+    progress: impl FnMut(usize, Option<usize>) + 'static,
+) where
+    I: IntoIterator<Item = F>,
+    I::IntoIter: 'static,
+    F: FnOnce() -> Node,
+{
+    let builders = builders.into_iter();
+    let total = match builders.size_hint() {
+        (lower, Some(upper)) if lower == upper => Some(upper),
+        _ => None,
+    };
+    let chunks = Rc::new(Chunks {
+        root: root.clone(),
+        builders: RefCell::new(builders),
+        built: Cell::new(0),
+        total,
+        budget_ms,
+        progress: RefCell::new(Box::new(progress)),
+        canceled: Cell::new(false),
+    });
+    Scope::new(this).on_cleanup({
+        let chunks = chunks.clone();
+        move || chunks.canceled.set(true)
+    });
+    next_chunk(chunks);
+}
+
+type Progress = Box<dyn FnMut(usize, Option<usize>)>;
+
+// The state of a `chunked_render`.
+struct Chunks<I> {
+    root: Node,
+    builders: RefCell<I>,
+    built: Cell<usize>,
+    total: Option<usize>,
+    budget_ms: f64,
+    progress: RefCell<Progress>,
+    canceled: Cell<bool>,
+}
+
+fn next_chunk<I, F>(chunks: Rc<Chunks<I>>)
+where
+    I: Iterator<Item = F> + 'static,
+    F: FnOnce() -> Node,
+{
+    let root = chunks.root.clone();
+    let run = Closure::once_into_js(move || {
+        if chunks.canceled.get() {
+            return;
+        }
+        let started = js_sys::Date::now();
+        let mut finished = false;
+        while js_sys::Date::now() - started < chunks.budget_ms {
+            let builder = chunks.builders.borrow_mut().next();
+            match builder {
+                Some(builder) => {
+                    chunks.root.append_child(&builder()).unwrap_throw();
+                    chunks.built.set(chunks.built.get() + 1);
+                }
+                None => {
+                    finished = true;
+                    break;
+                }
+            }
+        }
+        (chunks.progress.borrow_mut())(chunks.built.get(), chunks.total);
+        if !finished {
+            next_chunk(chunks);
+        }
+    });
+    window_of(&root)
+        .request_animation_frame(run.unchecked_ref())
+        .unwrap_throw();
+}
+
+// A `Render` component, and its bindings once it has been created.
+struct Rendered<R: Render> {
+    component: R,
+    bindings: Option<R::Bindings>,
+}
+
+// Calls the component's method of the same name, and for the callbacks that change what it renders, then creates or
+// updates its DOM.
+macro_rules! to_component {
+    ($self:ident, connected_callback, $this:ident) => {{
+        DynCustomElement::connected_callback(&mut $self.component, $this);
+        if $self.bindings.is_none() {
+            let root = match $this.shadow_root() {
+                Some(shadow_root) => shadow_root.unchecked_into(),
+                None => $this.clone(),
+            };
+            $self.bindings = Some($self.component.create(&root));
+        }
+    }};
+    ($self:ident, scheduled_update, $this:ident) => {{
+        DynCustomElement::scheduled_update(&mut $self.component, $this);
+        if let Some(bindings) = &mut $self.bindings {
+            $self.component.update(bindings);
+        }
+    }};
+    ($self:ident, attribute_changed_callback, $this:ident $(, $arg:ident)*) => {{
+        DynCustomElement::attribute_changed_callback(&mut $self.component, $this $(, $arg)*);
+        request_update($this);
+    }};
+    ($self:ident, bool_attribute_changed_callback, $this:ident $(, $arg:ident)*) => {{
+        DynCustomElement::bool_attribute_changed_callback(&mut $self.component, $this $(, $arg)*);
+        request_update($this);
+    }};
+    ($self:ident, inspect) => {
+        DynCustomElement::inspect(&$self.component)
+    };
+    ($self:ident, $method:ident $(, $arg:ident)*) => {
+        DynCustomElement::$method(&mut $self.component $(, $arg)*)
+    };
+}
+
+impl<R: Render> DynCustomElement for Rendered<R> {
+    forward_dyn_custom_element!(self, to_component);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::composition::{child, Child};
+
+    #[derive(Default)]
+    struct Focusable {
+        hooks: Vec<&'static str>,
+    }
+
+    impl CustomElement for Focusable {
+        fn focus_callback(&mut self, _this: &HtmlElement) {
+            self.hooks.push("focus");
+        }
+
+        fn blur_callback(&mut self, _this: &HtmlElement) {
+            self.hooks.push("blur");
+        }
+
+        fn child_attached(&mut self, _this: &HtmlElement, _child: Child) {
+            self.hooks.push("child_attached");
+        }
+
+        fn child_detached(&mut self, _this: &HtmlElement, _child: Child) {
+            self.hooks.push("child_detached");
+        }
+    }
+
+    impl Render for Focusable {
+        type Bindings = ();
+
+        fn create(&mut self, _root: &HtmlElement) {}
+
+        fn update(&mut self, _bindings: &mut ()) {}
+    }
+
+    #[test]
+    fn forwards_hooks_to_the_component() {
+        // never used by the component, so it doesn't have to be a real element
+        let el: HtmlElement = JsValue::NULL.unchecked_into();
+        let new_child = || child(JsValue::NULL.unchecked_into());
+        let mut rendered = Rendered {
+            component: Focusable::default(),
+            bindings: None,
+        };
+        DynCustomElement::focus_callback(&mut rendered, &el);
+        DynCustomElement::blur_callback(&mut rendered, &el);
+        DynCustomElement::child_attached(&mut rendered, &el, new_child());
+        DynCustomElement::child_detached(&mut rendered, &el, new_child());
+        assert_eq!(
+            rendered.component.hooks,
+            ["focus", "blur", "child_attached", "child_detached"]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Shared client-side routing for custom elements. Requires the `router` feature.
+//!
+//! Every element whose type returns `true` from [observe_route](crate::CustomElement::observe_route)
+//! has its [route_changed_callback](crate::CustomElement::route_changed_callback) invoked with the new URL
+//! whenever the document's URL changes, through a single listener shared by all elements defined by this crate.
+//! The URL changes when the user navigates through history (`popstate`), when [push_state] or [replace_state]
+//! is called, or, in browsers that support the
+//! [Navigation API](https://developer.mozilla.org/en-US/docs/Web/API/Navigation_API), after any same-document navigation.
+//!
+//! Elements can also keep some of their attributes in the URL's query string, so that a filter or search widget's
+//! state can be linked to, with [bind_query].
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    window, Event, HtmlElement, MutationObserver, MutationObserverInit, MutationRecord, Url,
+};
+
+use crate::host;
+use crate::scope::Scope;
+use crate::subscribers::Subscribers;
+
+thread_local! {
+    static SUBSCRIBERS: Subscribers = const { Subscribers::new("routeChangedCallback") };
+    static LISTENING: Cell<bool> = const { Cell::new(false) };
+    // query parameter changes waiting to be written to the URL, by parameter, in the order they were made
+    static QUERY_CHANGES: RefCell<Vec<(String, Option<String>)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The current URL of the document.
+pub fn current_url() -> String {
+    window().unwrap_throw().location().href().unwrap_throw()
+}
+
+/// Adds an entry to the session history with the given URL and notifies all routed elements.
+pub fn push_state(url: &str) {
+    window()
+        .unwrap_throw()
+        .history()
+        .unwrap_throw()
+        .push_state_with_url(&JsValue::NULL, "", Some(url))
+        .unwrap_throw();
+    if navigation().is_none() {
+        notify();
+    }
+}
+
+/// Replaces the current session history entry with the given URL and notifies all routed elements.
+pub fn replace_state(url: &str) {
+    window()
+        .unwrap_throw()
+        .history()
+        .unwrap_throw()
+        .replace_state_with_url(&JsValue::NULL, "", Some(url))
+        .unwrap_throw();
+    if navigation().is_none() {
+        notify();
+    }
+}
+
+/// Turns same-origin link clicks and form submissions into same-document navigations, so that routed
+/// elements can render the new URL without the page reloading.
+///
+/// This requires the Navigation API; returns `false` if the browser does not support it.
+pub fn intercept_navigations() -> bool {
+    let navigation = match navigation() {
+        Some(navigation) => navigation,
+        None => return false,
+    };
+    let listener = Closure::wrap(Box::new(move |event: Event| {
+        let get = |key: &str| {
+            js_sys::Reflect::get(&event, &JsValue::from_str(key))
+                .unwrap_throw()
+                .is_truthy()
+        };
+        if get("canIntercept") && !get("hashChange") && !get("downloadRequest") {
+            let intercept: js_sys::Function =
+                js_sys::Reflect::get(&event, &JsValue::from_str("intercept"))
+                    .unwrap_throw()
+                    .unchecked_into();
+            intercept.call0(&event).unwrap_throw();
+        }
+    }) as Box<dyn FnMut(Event)>);
+    navigation
+        .add_event_listener_with_callback("navigate", listener.as_ref().unchecked_ref())
+        .unwrap_throw();
+    listener.forget();
+    true
+}
+
+/// Binds attributes of the element to parameters of the URL's query string, given as `(attribute, parameter)` pairs.
+/// Each attribute is set from its parameter, if the URL has it, when this is called, so it is usually called in
+/// [connected_callback](crate::CustomElement::connected_callback). `this` may be either the element or its shadow
+/// root.
+///
+/// If `write` is `true`, changes to the attributes are then written back to the URL with [replace_state], until the
+/// element is disconnected, removing the parameter when the attribute is removed. Changes made at the same time by
+/// several elements, even to different parameters, are written together, so that none of them is lost.
+///
+/// ```rust,ignore
+/// fn connected_callback(&mut self, this: &HtmlElement) {
+///     // <product-filter category="shoes"> for ?cat=shoes
+///     router::bind_query(this, &[("category", "cat"), ("sort", "sort")], true);
+/// }
+/// ```
+pub fn bind_query(this: &HtmlElement, bindings: &[(&str, &str)], write: bool) {
+    let host = host(this);
+    let url = Url::new(&current_url()).unwrap_throw();
+    for (attribute, param) in bindings {
+        if let Some(value) = url.search_params().get(param) {
+            if host.get_attribute(attribute).as_ref() != Some(&value) {
+                host.set_attribute(attribute, &value).unwrap_throw();
+            }
+        }
+    }
+    if !write {
+        return;
+    }
+
+    let bindings: Rc<[(String, String)]> = bindings
+        .iter()
+        .map(|(attribute, param)| (attribute.to_string(), param.to_string()))
+        .collect();
+    let observer_callback = Closure::wrap(Box::new({
+        let (host, bindings) = (host.clone(), bindings.clone());
+        move |records: js_sys::Array| {
+            for record in records.iter() {
+                let record: MutationRecord = record.unchecked_into();
+                let name = match record.attribute_name() {
+                    Some(name) => name,
+                    None => continue,
+                };
+                for (_, param) in bindings.iter().filter(|(attribute, _)| *attribute == name) {
+                    change_query(param, host.get_attribute(&name));
+                }
+            }
+        }
+    }) as Box<dyn FnMut(js_sys::Array)>);
+    let observer = MutationObserver::new(observer_callback.as_ref().unchecked_ref()).unwrap_throw();
+    let options = MutationObserverInit::new();
+    options.set_attribute_filter(
+        &bindings
+            .iter()
+            .map(|(attribute, _)| JsValue::from_str(attribute))
+            .collect::<js_sys::Array>(),
+    );
+    observer
+        .observe_with_options(&host, &options)
+        .unwrap_throw();
+    Scope::new(&host).on_cleanup(move || {
+        observer.disconnect();
+        drop(observer_callback);
+    });
+}
+
+// Queues a change to a query parameter, to be written along with any others made before the next microtask.
+fn change_query(param: &str, value: Option<String>) {
+    let first = QUERY_CHANGES.with(|changes| {
+        let mut changes = changes.borrow_mut();
+        changes.push((param.to_string(), value));
+        changes.len() == 1
+    });
+    if first {
+        let write = Closure::once_into_js(write_query);
+        window()
+            .unwrap_throw()
+            .queue_microtask(write.unchecked_ref());
+    }
+}
+
+// Writes the queued query parameter changes to the URL in one history entry, starting from the URL as it is now.
+fn write_query() {
+    let changes = QUERY_CHANGES.with(|changes| changes.take());
+    let current = current_url();
+    let url = Url::new(&current).unwrap_throw();
+    let params = url.search_params();
+    for (param, value) in changes {
+        match value {
+            Some(value) => params.set(&param, &value),
+            None => params.delete(&param),
+        }
+    }
+    url.set_search(&String::from(params.to_string()));
+    let href = url.href();
+    if href != current {
+        replace_state(&href);
+    }
+}
+
+// Registers a connected element and immediately delivers the current URL to it.
+pub(crate) fn subscribe(this: &HtmlElement) {
+    listen();
+    SUBSCRIBERS.with(|subscribers| {
+        subscribers.subscribe(this);
+        subscribers.deliver(this, &[&JsValue::from_str(&current_url())]);
+    });
+}
+
+pub(crate) fn unsubscribe(this: &HtmlElement) {
+    SUBSCRIBERS.with(|subscribers| subscribers.unsubscribe(this));
+}
+
+fn navigation() -> Option<web_sys::EventTarget> {
+    js_sys::Reflect::get(&window().unwrap_throw(), &JsValue::from_str("navigation"))
+        .ok()
+        .filter(|navigation| navigation.is_object())
+        .map(|navigation| navigation.unchecked_into())
+}
+
+// Installs the one document-level listener, the first time any element subscribes.
+fn listen() {
+    if LISTENING.with(|listening| listening.replace(true)) {
+        return;
+    }
+    let listener = Closure::wrap(Box::new(move |_: Event| notify()) as Box<dyn FnMut(Event)>);
+    match navigation() {
+        Some(navigation) => navigation.add_event_listener_with_callback(
+            "currententrychange",
+            listener.as_ref().unchecked_ref(),
+        ),
+        None => window()
+            .unwrap_throw()
+            .add_event_listener_with_callback("popstate", listener.as_ref().unchecked_ref()),
+    }
+    .unwrap_throw();
+    listener.forget();
+}
+
+fn notify() {
+    let url = JsValue::from_str(&current_url());
+    SUBSCRIBERS.with(|subscribers| subscribers.notify(&[&url]));
+}
@@ -0,0 +1,257 @@
+//! Sanitizing HTML from untrusted sources before adding it to a component.
+//!
+//! Components that accept markup, through an attribute or a property, should not pass it to `set_inner_html`: it
+//! could contain scripts, event handler attributes or `javascript:` links. [sanitize_html] parses it into a
+//! `DocumentFragment` with all of those removed, which the component appends instead:
+//!
+//! ```rust,ignore
+//! fn attribute_changed_callback(&mut self, this: &HtmlElement, _name: String, _old: Option<String>, new: Option<String>) {
+//!     let body: Element = self.refs.expect("body");
+//!     body.set_text_content(None);
+//!     body.append_child(&sanitize_html(&new.unwrap_or_default())).unwrap_throw();
+//! }
+//! ```
+//!
+//! Where the browser supports the [HTML Sanitizer API](https://developer.mozilla.org/en-US/docs/Web/API/HTML_Sanitizer_API),
+//! it does the work with its default configuration. Elsewhere, the crate keeps only an allowlist of formatting
+//! elements and attributes: text, headings, lists, tables, links and images. Links and images keep only `http`,
+//! `https` and `mailto` URLs, and relative ones. Elements not on the list are replaced by their content, except for
+//! those whose content is not text, like `<script>`, `<style>` or `<iframe>`, which are removed with it, as are
+//! forms and their controls, SVG and MathML.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Document, DocumentFragment, Element, HtmlTemplateElement, Node};
+
+use crate::trusted_types;
+
+const HTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+
+// elements kept by the fallback sanitizer
+const ALLOWED_ELEMENTS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "bdi",
+    "bdo",
+    "blockquote",
+    "br",
+    "caption",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "del",
+    "details",
+    "dfn",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "s",
+    "samp",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "tr",
+    "u",
+    "ul",
+    "var",
+    "wbr",
+];
+
+// elements removed by the fallback sanitizer along with their content; others not allowed are replaced by their
+// content
+const DROPPED_ELEMENTS: &[&str] = &[
+    "applet", "base", "button", "embed", "form", "frame", "frameset", "head", "iframe", "input",
+    "link", "meta", "noembed", "noframes", "noscript", "object", "option", "script", "select",
+    "style", "template", "textarea", "title",
+];
+
+// attributes kept on any allowed element
+const GLOBAL_ATTRIBUTES: &[&str] = &["class", "dir", "lang", "title"];
+
+// attributes kept on particular elements
+const ELEMENT_ATTRIBUTES: &[(&str, &[&str])] = &[
+    ("a", &["href"]),
+    ("blockquote", &["cite"]),
+    ("col", &["span"]),
+    ("colgroup", &["span"]),
+    ("del", &["cite", "datetime"]),
+    ("details", &["open"]),
+    ("img", &["alt", "height", "src", "width"]),
+    ("ins", &["cite", "datetime"]),
+    ("li", &["value"]),
+    ("ol", &["reversed", "start"]),
+    ("q", &["cite"]),
+    ("td", &["colspan", "rowspan"]),
+    ("th", &["colspan", "rowspan", "scope"]),
+    ("time", &["datetime"]),
+];
+
+// attributes whose values are URLs, which are checked for their scheme
+const URL_ATTRIBUTES: &[&str] = &["cite", "href", "src"];
+
+const ALLOWED_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Parses `input` as HTML and returns its content with anything that could run script removed. See the
+/// [module documentation](self).
+///
+/// Nothing in `input` runs or loads while it is sanitized; images start loading once the fragment is added to the
+/// document.
+pub fn sanitize_html(input: &str) -> DocumentFragment {
+    sanitize_html_in(&window().unwrap_throw().document().unwrap_throw(), input)
+}
+
+/// Like [sanitize_html], but parses `input` in `document`, like the document of the element it is for, when that
+/// is in an iframe.
+pub fn sanitize_html_in(document: &Document, input: &str) -> DocumentFragment {
+    let template: HtmlTemplateElement = document
+        .create_element("template")
+        .unwrap_throw()
+        .unchecked_into();
+    let set_html = js_sys::Reflect::get(&template, &JsValue::from_str("setHTML"))
+        .ok()
+        .and_then(|set_html| set_html.dyn_into::<js_sys::Function>().ok());
+    match set_html {
+        Some(set_html) => {
+            set_html
+                .call1(&template, &JsValue::from_str(input))
+                .unwrap_throw();
+        }
+        None => {
+            // the template is inert, so nothing in the markup runs before it is cleaned
+            trusted_types::set_trusted_inner_html(&template, input);
+            clean_children(&template.content());
+        }
+    }
+    template.content()
+}
+
+fn clean_children(parent: &Node) {
+    let mut child = parent.first_child();
+    while let Some(node) = child {
+        child = node.next_sibling();
+        match node.node_type() {
+            Node::TEXT_NODE => {}
+            Node::ELEMENT_NODE => clean_element(parent, node.unchecked_into()),
+            _ => {
+                parent.remove_child(&node).unwrap_throw();
+            }
+        }
+    }
+}
+
+fn clean_element(parent: &Node, element: Element) {
+    let tag = element.local_name();
+    if element.namespace_uri().as_deref() != Some(HTML_NAMESPACE)
+        || DROPPED_ELEMENTS.contains(&tag.as_str())
+    {
+        element.remove();
+        return;
+    }
+    clean_children(&element);
+    if !ALLOWED_ELEMENTS.contains(&tag.as_str()) {
+        while let Some(grandchild) = element.first_child() {
+            parent
+                .insert_before(&grandchild, Some(&element))
+                .unwrap_throw();
+        }
+        element.remove();
+        return;
+    }
+    let allowed = ELEMENT_ATTRIBUTES
+        .iter()
+        .find(|(element_tag, _)| *element_tag == tag)
+        .map(|(_, attributes)| *attributes)
+        .unwrap_or(&[]);
+    for name in element.get_attribute_names().iter() {
+        let name = name.as_string().unwrap_throw();
+        let keep = (GLOBAL_ATTRIBUTES.contains(&name.as_str()) || allowed.contains(&name.as_str()))
+            && (!URL_ATTRIBUTES.contains(&name.as_str())
+                || is_safe_url(&element.get_attribute(&name).unwrap_or_default()));
+        if !keep {
+            element.remove_attribute(&name).unwrap_throw();
+        }
+    }
+}
+
+// Whether a URL is relative or has an allowed scheme. Browsers ignore whitespace and control characters in schemes,
+// so those are removed before checking.
+fn is_safe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(index) if url[index..].starts_with(':') => ALLOWED_SCHEMES
+            .iter()
+            .any(|scheme| url[..index].eq_ignore_ascii_case(scheme)),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_relative_and_allowed_schemes() {
+        for url in [
+            "https://example.com/",
+            "HTTP://example.com",
+            "mailto:someone@example.com",
+            "/path/to/page",
+            "page?next=javascript:alert(1)",
+            "#section",
+            "",
+        ] {
+            assert!(is_safe_url(url), "{:?} should be allowed", url);
+        }
+    }
+
+    #[test]
+    fn rejects_other_schemes() {
+        for url in [
+            "javascript:alert(1)",
+            "JaVaScRiPt:alert(1)",
+            "java\tscript:alert(1)",
+            " javascript:alert(1)",
+            "java\u{0}script:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+            "vbscript:msgbox(1)",
+        ] {
+            assert!(!is_safe_url(url), "{:?} should be rejected", url);
+        }
+    }
+}
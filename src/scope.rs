@@ -0,0 +1,197 @@
+//! Resources whose lifetime is tied to a custom element being connected to the document.
+
+use std::cell::RefCell;
+#[cfg(feature = "fetch")]
+use std::future::Future;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+#[cfg(feature = "fetch")]
+use wasm_bindgen_futures::JsFuture;
+use web_sys::HtmlElement;
+#[cfg(feature = "fetch")]
+use web_sys::{AbortController, Request, RequestInit, Response};
+
+use crate::{host, window_of};
+
+/// A handle to the resources owned by a custom element while it is connected.
+///
+/// Teardown functions, timers, and intervals registered with a `Scope` are released automatically the next time
+/// the element is disconnected, after [disconnected_callback](crate::CustomElement::disconnected_callback) runs.
+/// Resources are not recreated when the element is reconnected, so they are usually registered in
+/// [connected_callback](crate::CustomElement::connected_callback).
+///
+/// A `Scope` is cheap to create and holds no state of its own: every `Scope` for the same element
+/// registers resources with that element.
+///
+/// ```rust,ignore
+/// fn connected_callback(&mut self, this: &HtmlElement) {
+///     let scope = Scope::new(this);
+///     let ticks = self.ticks.clone();
+///     scope.set_interval(1000, move || ticks.set(ticks.get() + 1));
+///     scope.on_cleanup(|| log("cleaned up"));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Scope {
+    host: HtmlElement,
+}
+
+impl Scope {
+    /// The scope of the given custom element. `this` may be either the element or its shadow root.
+    pub fn new(this: &HtmlElement) -> Self {
+        Self { host: host(this) }
+    }
+
+    /// The custom element that owns this scope.
+    pub fn host(&self) -> &HtmlElement {
+        &self.host
+    }
+
+    /// Registers a function to be run the next time the element is disconnected.
+    pub fn on_cleanup(&self, cleanup: impl FnOnce() + 'static) {
+        self.push_cleanup(cleanup);
+    }
+
+    // Registers `cleanup` like `on_cleanup`, and returns it as a function that can be passed to `run_cleanup` to run
+    // it early.
+    pub(crate) fn push_cleanup(&self, cleanup: impl FnOnce() + 'static) -> JsValue {
+        let key = JsValue::from_str("_cleanups");
+        let cleanups = js_sys::Reflect::get(&self.host, &key).unwrap_throw();
+        let cleanups: js_sys::Array = if cleanups.is_undefined() {
+            let cleanups = js_sys::Array::new();
+            js_sys::Reflect::set(&self.host, &key, &cleanups).unwrap_throw();
+            cleanups
+        } else {
+            cleanups.unchecked_into()
+        };
+        let cleanup = Closure::once_into_js(cleanup);
+        cleanups.push(&cleanup);
+        cleanup
+    }
+
+    // Runs `cleanup`, returned by `push_cleanup`, now rather than when the element is disconnected. Does nothing if it
+    // has already run.
+    pub(crate) fn run_cleanup(&self, cleanup: &JsValue) {
+        run_cleanup(&self.host, cleanup);
+    }
+
+    /// Calls `callback` once after `ms` milliseconds, unless the element is disconnected first.
+    ///
+    /// Returns the timer's ID, which can be passed to `clearTimeout` to cancel it early.
+    pub fn set_timeout(&self, ms: i32, callback: impl FnOnce() + 'static) -> i32 {
+        // the cleanup clears the timer and drops its closure; it also runs once the timer has fired, so that neither
+        // is kept until the element is disconnected
+        let timer = Rc::new(RefCell::new(None::<Closure<dyn FnMut()>>));
+        let cleanup = Rc::new(RefCell::new(JsValue::UNDEFINED));
+        let mut callback = Some(callback);
+        let closure = Closure::wrap(Box::new({
+            let (scope, cleanup) = (self.clone(), cleanup.clone());
+            move || {
+                if let Some(callback) = callback.take() {
+                    callback();
+                }
+                let cleanup = cleanup.borrow().clone();
+                scope.run_cleanup(&cleanup);
+            }
+        }) as Box<dyn FnMut()>);
+        let window = window_of(&self.host);
+        let handle = window
+            .set_timeout_with_callback_and_timeout_and_arguments_0(
+                closure.as_ref().unchecked_ref(),
+                ms,
+            )
+            .unwrap_throw();
+        *timer.borrow_mut() = Some(closure);
+        *cleanup.borrow_mut() = self.push_cleanup(move || {
+            window.clear_timeout_with_handle(handle);
+            // dropping the closure while it runs is safe: wasm-bindgen frees it once it returns
+            timer.borrow_mut().take();
+        });
+        handle
+    }
+
+    /// Calls `callback` every `ms` milliseconds until the element is disconnected.
+    ///
+    /// Returns the timer's ID, which can be passed to `clearInterval` to cancel it early.
+    pub fn set_interval(&self, ms: i32, callback: impl FnMut() + 'static) -> i32 {
+        let callback = Closure::wrap(Box::new(callback) as Box<dyn FnMut()>);
+        let window = window_of(&self.host);
+        let handle = window
+            .set_interval_with_callback_and_timeout_and_arguments_0(
+                callback.as_ref().unchecked_ref(),
+                ms,
+            )
+            .unwrap_throw();
+        self.on_cleanup(move || {
+            window.clear_interval_with_handle(handle);
+            drop(callback);
+        });
+        handle
+    }
+
+    /// Starts fetching `request`, and aborts it if the element is disconnected before the response has arrived. The
+    /// request is made even if the returned future is not awaited. Requires the `fetch` feature.
+    ///
+    /// The future resolves to an `AbortError` if the request was aborted, so a component can tell that it was removed
+    /// before updating any state. If the element is not connected, the request is aborted immediately.
+    #[cfg(feature = "fetch")]
+    pub fn fetch(
+        &self,
+        request: &Request,
+    ) -> impl Future<Output = Result<Response, JsValue>> + 'static {
+        let controller = AbortController::new().unwrap_throw();
+        let init = RequestInit::new();
+        init.set_signal(Some(&controller.signal()));
+        if !self.host.is_connected() {
+            controller.abort();
+        }
+        let promise = window_of(&self.host).fetch_with_request_and_init(request, &init);
+        // aborting a request that has finished does nothing
+        self.on_cleanup(move || controller.abort());
+        async move { Ok(JsFuture::from(promise).await?.unchecked_into()) }
+    }
+}
+
+/// Calls `callback` once after `ms` milliseconds, unless the element is disconnected first.
+/// Shorthand for [Scope::set_timeout].
+pub fn set_timeout_scoped(this: &HtmlElement, ms: i32, callback: impl FnOnce() + 'static) -> i32 {
+    Scope::new(this).set_timeout(ms, callback)
+}
+
+/// Calls `callback` every `ms` milliseconds until the element is disconnected.
+/// Shorthand for [Scope::set_interval].
+pub fn set_interval_scoped(this: &HtmlElement, ms: i32, callback: impl FnMut() + 'static) -> i32 {
+    Scope::new(this).set_interval(ms, callback)
+}
+
+/// Fetches `request`, aborting it if the element is disconnected before the response has arrived. Requires the
+/// `fetch` feature. Shorthand for [Scope::fetch].
+///
+/// ```rust,ignore
+/// fn connected_callback(&mut self, this: &HtmlElement) {
+///     let request = Request::new_with_str(&format!("/api/users/{}", self.id)).unwrap_throw();
+///     let response = fetch_scoped(this, &request);
+///     let name = self.name.clone();
+///     spawn_local(async move {
+///         // an error here includes the request being aborted because the element was removed
+///         if let Ok(response) = response.await {
+///             let text = JsFuture::from(response.text().unwrap_throw()).await.unwrap_throw();
+///             name.set_data(&text.as_string().unwrap_throw());
+///         }
+///     });
+/// }
+/// ```
+#[cfg(feature = "fetch")]
+pub fn fetch_scoped(
+    this: &HtmlElement,
+    request: &Request,
+) -> impl Future<Output = Result<Response, JsValue>> + 'static {
+    Scope::new(this).fetch(request)
+}
+
+#[wasm_bindgen(module = "/src/make_custom_element.js")]
+extern "C" {
+    fn run_cleanup(el: &HtmlElement, cleanup: &JsValue);
+}
@@ -0,0 +1,269 @@
+//! Following the scrolling of the page, or of the container a custom element scrolls in.
+//!
+//! Scroll events fire many times a frame, and a component that reads layout in each of them slows scrolling down.
+//! The helpers here listen to the [scroll container](scroll_container) an element is in, read the layout at most once
+//! per animation frame, and stop when the element is disconnected:
+//!
+//! - [on_scroll] reports where the element is in the container's visible area, for effects like parallax.
+//! - [spy] reports which of a list of sections has been scrolled to, for a scroll-spy navigation.
+//! - [on_sticky_change] reports when a `position: sticky` element becomes stuck, or stops being stuck.
+//!
+//! ```rust,ignore
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     let background: HtmlElement = self.refs.expect("background");
+//!     scroll::on_scroll(this, move |position| {
+//!         let offset = (position.progress - 0.5) * 100.0;
+//!         background.style().set_property("translate", &format!("0 {}px", offset)).unwrap_throw();
+//!     });
+//! }
+//! ```
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Element, EventTarget, HtmlElement, ShadowRoot};
+
+use crate::host;
+use crate::scope::Scope;
+
+/// Where an element is, relative to the visible area of its [scroll container](scroll_container).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScrollPosition {
+    /// How far the container is scrolled horizontally, in pixels.
+    pub scroll_x: f64,
+    /// How far the container is scrolled vertically, in pixels.
+    pub scroll_y: f64,
+    /// The distance from the left edge of the visible area to the left edge of the element, in pixels.
+    pub left: f64,
+    /// The distance from the top edge of the visible area to the top edge of the element, in pixels.
+    pub top: f64,
+    /// How far the element has travelled through the visible area, from 0, when its top edge is at the bottom of the
+    /// area, to 1, when its bottom edge is at the top. Outside that range while it is out of view.
+    pub progress: f64,
+}
+
+/// The nearest ancestor of the element, looking through shadow roots, that scrolls its content, or `None` if it only
+/// scrolls with the page. `this` may be either the element or its shadow root.
+pub fn scroll_container(this: &HtmlElement) -> Option<Element> {
+    container_of(&host(this).into())
+}
+
+fn container_of(element: &Element) -> Option<Element> {
+    let window = window().unwrap_throw();
+    let mut element = parent(element);
+    while let Some(current) = element {
+        if let Ok(Some(style)) = window.get_computed_style(&current) {
+            let scrolls = ["overflow-x", "overflow-y"].iter().any(|property| {
+                matches!(
+                    style.get_property_value(property).as_deref(),
+                    Ok("auto") | Ok("scroll") | Ok("overlay")
+                )
+            });
+            if scrolls {
+                return Some(current);
+            }
+        }
+        element = parent(&current);
+    }
+    None
+}
+
+/// Calls `callback` with the element's [ScrollPosition] now, and again after it has been scrolled or the window has
+/// been resized, at most once per frame, until the element is disconnected. `this` may be either the element or its
+/// shadow root.
+pub fn on_scroll(this: &HtmlElement, mut callback: impl FnMut(&ScrollPosition) + 'static) {
+    let host = host(this);
+    let container = scroll_container(&host);
+    let element = host.clone();
+    on_frame(&host, container.clone(), move || {
+        let viewport = viewport(container.as_ref());
+        let (scroll_x, scroll_y) = match &container {
+            Some(container) => (
+                container.scroll_left() as f64,
+                container.scroll_top() as f64,
+            ),
+            None => {
+                let window = window().unwrap_throw();
+                (
+                    window.scroll_x().unwrap_or_default(),
+                    window.scroll_y().unwrap_or_default(),
+                )
+            }
+        };
+        let rect = element.get_bounding_client_rect();
+        let top = rect.top() - viewport.1;
+        let travel = viewport.3 + rect.height();
+        callback(&ScrollPosition {
+            scroll_x,
+            scroll_y,
+            left: rect.left() - viewport.0,
+            top,
+            progress: if travel > 0.0 {
+                (viewport.3 - top) / travel
+            } else {
+                0.0
+            },
+        });
+    });
+}
+
+/// Calls `callback` with the index of the section that has been scrolled to, among `sections`, which are in document
+/// order: the last one whose top edge is at most `offset` pixels below the top of the visible area, or `None` while
+/// the first one is below that line. It is called with the current section, and again each time it changes, until
+/// the element is disconnected. `this` may be either the element or its shadow root.
+///
+/// ```rust,ignore
+/// let sections = headings.iter().map(|h| h.clone().into()).collect();
+/// scroll::spy(this, sections, 80.0, move |index| highlight(index));
+/// ```
+pub fn spy(
+    this: &HtmlElement,
+    sections: Vec<Element>,
+    offset: f64,
+    mut callback: impl FnMut(Option<usize>) + 'static,
+) {
+    let host = host(this);
+    let container = match sections.first() {
+        Some(section) => container_of(section),
+        None => scroll_container(&host),
+    };
+    // `None` until the first call
+    let mut current = None;
+    on_frame(&host, container.clone(), move || {
+        let line = viewport(container.as_ref()).1 + offset;
+        let index = sections
+            .iter()
+            .rposition(|section| section.get_bounding_client_rect().top() <= line);
+        if current != Some(index) {
+            current = Some(index);
+            callback(index);
+        }
+    });
+}
+
+/// Calls `callback` with `true` when `sticky`, an element with `position: sticky` and a `top` offset, becomes stuck
+/// to the top of its scroll container, and `false` when it stops being stuck, until `this` is disconnected. `this` may
+/// be either the element or its shadow root.
+///
+/// Whether the element is stuck is worked out from its position, so an element whose `top` it sits at before
+/// anything is scrolled is not reported as stuck until the container has been scrolled.
+pub fn on_sticky_change(
+    this: &HtmlElement,
+    sticky: &Element,
+    mut callback: impl FnMut(bool) + 'static,
+) {
+    let host = host(this);
+    let container = container_of(sticky);
+    let sticky = sticky.clone();
+    let mut stuck = false;
+    on_frame(&host, container.clone(), move || {
+        let top = window()
+            .unwrap_throw()
+            .get_computed_style(&sticky)
+            .ok()
+            .flatten()
+            .and_then(|style| style.get_property_value("top").ok())
+            .and_then(|top| top.strip_suffix("px").and_then(|px| px.parse::<f64>().ok()));
+        let scrolled = match &container {
+            Some(container) => container.scroll_top() > 0,
+            None => window().unwrap_throw().scroll_y().unwrap_or_default() > 0.0,
+        };
+        let now_stuck = match top {
+            Some(top) => {
+                let line = viewport(container.as_ref()).1 + top;
+                scrolled && (sticky.get_bounding_client_rect().top() - line).abs() < 1.0
+            }
+            None => false,
+        };
+        if stuck != now_stuck {
+            stuck = now_stuck;
+            callback(now_stuck);
+        }
+    });
+}
+
+// The parent of an element, or the host of the shadow root it is in.
+fn parent(element: &Element) -> Option<Element> {
+    element.parent_element().or_else(|| {
+        element
+            .parent_node()?
+            .dyn_into::<ShadowRoot>()
+            .ok()
+            .map(|shadow_root| shadow_root.host())
+    })
+}
+
+// The visible area of the container, or of the window, as (left, top, width, height) in viewport coordinates.
+fn viewport(container: Option<&Element>) -> (f64, f64, f64, f64) {
+    match container {
+        Some(container) => {
+            let rect = container.get_bounding_client_rect();
+            (
+                rect.left() + container.client_left() as f64,
+                rect.top() + container.client_top() as f64,
+                container.client_width() as f64,
+                container.client_height() as f64,
+            )
+        }
+        None => {
+            let window = window().unwrap_throw();
+            let size = |value: Result<JsValue, JsValue>| {
+                value
+                    .ok()
+                    .and_then(|value| value.as_f64())
+                    .unwrap_or_default()
+            };
+            (
+                0.0,
+                0.0,
+                size(window.inner_width()),
+                size(window.inner_height()),
+            )
+        }
+    }
+}
+
+// Calls `update` now, and at most once per frame after the container is scrolled or the window is resized, until
+// the element is disconnected.
+fn on_frame(host: &HtmlElement, container: Option<Element>, update: impl FnMut() + 'static) {
+    let update = Rc::new(RefCell::new(update));
+    (update.borrow_mut())();
+
+    let scheduled = Rc::new(Cell::new(false));
+    let listener = Closure::wrap(Box::new(move || {
+        if scheduled.replace(true) {
+            return;
+        }
+        let (update, scheduled) = (update.clone(), scheduled.clone());
+        let run = Closure::once_into_js(move || {
+            scheduled.set(false);
+            (update.borrow_mut())();
+        });
+        window()
+            .unwrap_throw()
+            .request_animation_frame(run.unchecked_ref())
+            .unwrap_throw();
+    }) as Box<dyn FnMut()>);
+
+    let window = window().unwrap_throw();
+    let target: EventTarget = match container {
+        Some(container) => container.into(),
+        None => window.clone().into(),
+    };
+    target
+        .add_event_listener_with_callback("scroll", listener.as_ref().unchecked_ref())
+        .unwrap_throw();
+    window
+        .add_event_listener_with_callback("resize", listener.as_ref().unchecked_ref())
+        .unwrap_throw();
+    Scope::new(host).on_cleanup(move || {
+        target
+            .remove_event_listener_with_callback("scroll", listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+        window
+            .remove_event_listener_with_callback("resize", listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+    });
+}
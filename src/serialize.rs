@@ -0,0 +1,203 @@
+//! Serializing a rendered element, shadow root included, to HTML.
+//!
+//! `outerHTML` leaves out shadow roots. [serialize] writes each one as a
+//! [declarative shadow root](https://developer.mozilla.org/en-US/docs/Web/HTML/Element/template#shadowrootmode), a
+//! `<template shadowrootmode="open">` inside its host, which the browser's parser turns back into a shadow root. The
+//! result can be cached and sent with the page, so that the component is displayed before its wasm module has loaded:
+//!
+//! ```rust,ignore
+//! let html = serialize(this);
+//! // <user-card name="Ada"><template shadowrootmode="open"><style>...</style><h2>Ada</h2></template></user-card>
+//! ```
+//!
+//! Shadow roots of elements inside the component, open ones at least, are included too. Styles that are not in the
+//! DOM, like the constructable stylesheets of [register_global](crate::styles::register_global), are not.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement, HtmlTemplateElement, Node, ShadowRoot};
+
+use crate::host;
+
+// elements that have no end tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+// elements whose text is not escaped
+const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "iframe",
+    "noembed",
+    "noframes",
+    "noscript",
+    "plaintext",
+    "script",
+    "style",
+    "xmp",
+];
+
+/// The HTML of the element, including its attributes, its shadow root as a declarative shadow root, and its children.
+/// `this` may be either the element or its shadow root. See the [module documentation](self).
+///
+/// Uses the browser's `getHTML()` where it is supported, and serializes the element itself elsewhere.
+pub fn serialize(this: &HtmlElement) -> String {
+    let host = host(this);
+    match get_html(&host) {
+        Some(inner) => format!(
+            "{}{}{}",
+            start_tag(&host),
+            inner,
+            end_tag(&host).unwrap_or_default()
+        ),
+        None => {
+            let mut html = String::new();
+            write_node(&mut html, &host);
+            html
+        }
+    }
+}
+
+// The element's content with `getHTML()`, passing it every open shadow root inside it, if the browser supports it.
+fn get_html(host: &HtmlElement) -> Option<String> {
+    let get_html: js_sys::Function = js_sys::Reflect::get(host, &JsValue::from_str("getHTML"))
+        .ok()?
+        .dyn_into()
+        .ok()?;
+    let shadow_roots = js_sys::Array::new();
+    collect_shadow_roots(host, &shadow_roots);
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &"serializableShadowRoots".into(), &JsValue::TRUE)
+        .unwrap_throw();
+    js_sys::Reflect::set(&options, &"shadowRoots".into(), &shadow_roots).unwrap_throw();
+    get_html.call1(host, &options).ok()?.as_string()
+}
+
+fn collect_shadow_roots(node: &Node, shadow_roots: &js_sys::Array) {
+    if let Some(shadow_root) = node.dyn_ref::<Element>().and_then(Element::shadow_root) {
+        shadow_roots.push(&shadow_root);
+        collect_shadow_roots(&shadow_root, shadow_roots);
+    }
+    let children = node.child_nodes();
+    for index in 0..children.length() {
+        if let Some(child) = children.item(index) {
+            collect_shadow_roots(&child, shadow_roots);
+        }
+    }
+}
+
+fn write_node(html: &mut String, node: &Node) {
+    match node.node_type() {
+        Node::ELEMENT_NODE => write_element(html, node.unchecked_ref()),
+        Node::TEXT_NODE => {
+            let text = node.text_content().unwrap_or_default();
+            let raw = node
+                .parent_element()
+                .map(|parent| RAW_TEXT_ELEMENTS.contains(&parent.local_name().as_str()))
+                .unwrap_or(false);
+            if raw {
+                html.push_str(&text);
+            } else {
+                html.push_str(&escape(&text, false));
+            }
+        }
+        Node::COMMENT_NODE => {
+            html.push_str("<!--");
+            html.push_str(&node.text_content().unwrap_or_default());
+            html.push_str("-->");
+        }
+        _ => {}
+    }
+}
+
+fn write_children(html: &mut String, parent: &Node) {
+    let children = parent.child_nodes();
+    for index in 0..children.length() {
+        if let Some(child) = children.item(index) {
+            write_node(html, &child);
+        }
+    }
+}
+
+fn write_element(html: &mut String, element: &Element) {
+    html.push_str(&start_tag(element));
+    if let Some(shadow_root) = element.shadow_root() {
+        write_shadow_root(html, &shadow_root);
+    }
+    match element.dyn_ref::<HtmlTemplateElement>() {
+        Some(template) => write_children(html, &template.content()),
+        None => write_children(html, element),
+    }
+    if let Some(end_tag) = end_tag(element) {
+        html.push_str(&end_tag);
+    }
+}
+
+fn write_shadow_root(html: &mut String, shadow_root: &ShadowRoot) {
+    html.push_str("<template shadowrootmode=\"open\"");
+    let delegates_focus = js_sys::Reflect::get(shadow_root, &"delegatesFocus".into())
+        .map(|delegates_focus| delegates_focus.is_truthy())
+        .unwrap_or(false);
+    if delegates_focus {
+        html.push_str(" shadowrootdelegatesfocus");
+    }
+    html.push('>');
+    write_children(html, shadow_root);
+    html.push_str("</template>");
+}
+
+fn start_tag(element: &Element) -> String {
+    let mut tag = format!("<{}", element.local_name());
+    for name in element.get_attribute_names().iter() {
+        let name = name.as_string().unwrap_throw();
+        let value = element.get_attribute(&name).unwrap_or_default();
+        tag.push_str(&format!(" {}=\"{}\"", name, escape(&value, true)));
+    }
+    tag.push('>');
+    tag
+}
+
+fn end_tag(element: &Element) -> Option<String> {
+    let name = element.local_name();
+    if VOID_ELEMENTS.contains(&name.as_str()) {
+        None
+    } else {
+        Some(format!("</{}>", name))
+    }
+}
+
+fn escape(text: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            '<' if !attribute => escaped.push_str("&lt;"),
+            '>' if !attribute => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(
+            escape("a < b && c > \"d\"\u{a0}", false),
+            "a &lt; b &amp;&amp; c &gt; \"d\"&nbsp;"
+        );
+    }
+
+    #[test]
+    fn escapes_attribute_values() {
+        assert_eq!(
+            escape("say \"hi\" & <wave>", true),
+            "say &quot;hi&quot; &amp; <wave>"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! State shared by every instance of a custom element, like caches of parsed templates or compiled regular
+//! expressions, which would be wasteful to build for each instance.
+//!
+//! The state is created the first time any instance asks for it, and is kept for as long as the page is open. Each
+//! tag name has its own, so a component type defined under two tag names has two separate states.
+//!
+//! ```rust,ignore
+//! struct Templates {
+//!     row: HtmlTemplateElement,
+//! }
+//!
+//! fn inject_children(&mut self, this: &HtmlElement) {
+//!     let templates = shared::shared(this, || Templates { row: parse_template(ROW_HTML) });
+//!     let row = templates.borrow().row.content().clone_node_with_deep(true).unwrap_throw();
+//!     this.append_child(&row).unwrap_throw();
+//! }
+//! ```
+
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlElement;
+
+use crate::defined_tag_name;
+
+thread_local! {
+    // each tag name's shared state, by type
+    static SHARED: RefCell<HashMap<(String, TypeId), Rc<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+/// The state of type `S` shared by every element with the same tag name as `this`, which may be either the element
+/// or its shadow root, created by `init` if this is the first time it is needed.
+pub fn shared<S: 'static>(this: &HtmlElement, init: impl FnOnce() -> S) -> Rc<RefCell<S>> {
+    shared_for(&defined_tag_name(this), init)
+}
+
+/// The state of type `S` shared by every element with the given tag name, like [shared], for code outside the
+/// elements, like a function that clears a cache.
+pub fn shared_for<S: 'static>(tag_name: &str, init: impl FnOnce() -> S) -> Rc<RefCell<S>> {
+    let key = (tag_name.to_string(), TypeId::of::<S>());
+    let existing = SHARED.with(|shared| shared.borrow().get(&key).cloned());
+    let state = match existing {
+        Some(state) => state,
+        None => {
+            // `init` runs without the map borrowed, so it may ask for other shared state
+            let state: Rc<dyn Any> = Rc::new(RefCell::new(init()));
+            SHARED.with(|shared| shared.borrow_mut().entry(key).or_insert(state).clone())
+        }
+    };
+    state
+        .downcast()
+        .ok()
+        .expect_throw("shared state has the wrong type")
+}
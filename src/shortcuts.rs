@@ -0,0 +1,117 @@
+//! Keyboard shortcuts that are active while focus is inside a custom element.
+//!
+//! `keydown` events are composed, so those fired by anything focused inside the element, including inside its
+//! shadow root, reach the host. [on] listens for them there, and calls its handler for those that match the
+//! [Shortcut], until the element is disconnected:
+//!
+//! ```rust,ignore
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     let form = self.form.clone();
+//!     shortcuts::on(this, "Mod+Enter", move |_| form.submit());
+//!     shortcuts::on(this, "Escape", |_| close());
+//! }
+//! ```
+
+use std::str::FromStr;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, HtmlElement, KeyboardEvent};
+
+use crate::host;
+use crate::scope::Scope;
+
+/// A key combination, like `Ctrl+Enter` or `Shift+Alt+K`, parsed from a string.
+///
+/// The modifiers are `Ctrl` (or `Control`), `Shift`, `Alt` (or `Option`), `Meta` (or `Cmd`), and `Mod`, which is
+/// `Meta` on Apple platforms and `Ctrl` elsewhere. They are followed by the [key](KeyboardEvent::key), which is
+/// matched case-insensitively; `Space` and `Plus` stand for the space and `+` keys. A shortcut only matches when
+/// exactly its modifiers are pressed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shortcut {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+    pub meta: bool,
+    pub key: String,
+}
+
+impl Shortcut {
+    /// Whether `event` was fired for this key combination.
+    pub fn matches(&self, event: &KeyboardEvent) -> bool {
+        event.ctrl_key() == self.ctrl
+            && event.shift_key() == self.shift
+            && event.alt_key() == self.alt
+            && event.meta_key() == self.meta
+            && event.key().eq_ignore_ascii_case(&self.key)
+    }
+}
+
+impl FromStr for Shortcut {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = match parts.pop() {
+            Some("") | None => return Err(format!("shortcut {:?} has no key", s)),
+            Some(key) if key.eq_ignore_ascii_case("space") => " ".to_string(),
+            Some(key) if key.eq_ignore_ascii_case("plus") => "+".to_string(),
+            Some(key) => key.to_string(),
+        };
+        let mut shortcut = Shortcut {
+            ctrl: false,
+            shift: false,
+            alt: false,
+            meta: false,
+            key,
+        };
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => shortcut.ctrl = true,
+                "shift" => shortcut.shift = true,
+                "alt" | "option" => shortcut.alt = true,
+                "meta" | "cmd" => shortcut.meta = true,
+                "mod" if is_apple() => shortcut.meta = true,
+                "mod" => shortcut.ctrl = true,
+                _ => {
+                    return Err(format!(
+                        "unknown modifier {:?} in shortcut {:?}",
+                        modifier, s
+                    ))
+                }
+            }
+        }
+        Ok(shortcut)
+    }
+}
+
+fn is_apple() -> bool {
+    window()
+        .and_then(|window| window.navigator().platform().ok())
+        .map(|platform| platform.starts_with("Mac") || platform.starts_with("iP"))
+        .unwrap_or(false)
+}
+
+/// Calls `handler` for each `keydown` that matches `shortcut` while focus is inside the element, and prevents its
+/// default action, until the element is disconnected. `this` may be either the element or its shadow root.
+///
+/// Panics if `shortcut` is not a valid [Shortcut].
+pub fn on(this: &HtmlElement, shortcut: &str, mut handler: impl FnMut(&KeyboardEvent) + 'static) {
+    let host = host(this);
+    let shortcut: Shortcut = shortcut.parse().unwrap_throw();
+    let listener = Closure::wrap(Box::new(move |event: KeyboardEvent| {
+        if shortcut.matches(&event) {
+            event.prevent_default();
+            handler(&event);
+        }
+    }) as Box<dyn FnMut(KeyboardEvent)>);
+    host.add_event_listener_with_callback("keydown", listener.as_ref().unchecked_ref())
+        .unwrap_throw();
+    Scope::new(&host).on_cleanup({
+        let host = host.clone();
+        move || {
+            host.remove_event_listener_with_callback("keydown", listener.as_ref().unchecked_ref())
+                .unwrap_throw();
+        }
+    });
+}
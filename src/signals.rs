@@ -0,0 +1,65 @@
+//! Fine-grained reactive bindings to [futures-signals](https://docs.rs/futures-signals), for components that don't
+//! use a framework. Requires the `signals` feature.
+//!
+//! [bind_text] and [bind_attr] keep a text node or an attribute in sync with a [Signal], setting it to the signal's
+//! current value and then to each new one, until the custom element that owns them is disconnected:
+//!
+//! ```rust,ignore
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     let count: Text = self.refs.expect("count");
+//!     signals::bind_text(this, &count, self.count.signal().map(|n| n.to_string()));
+//!     let button: Element = self.refs.expect("reset");
+//!     signals::bind_attr(this, &button, "disabled", self.count.signal().map(|n| (n == 0).then(String::new)));
+//! }
+//! ```
+//!
+//! The bindings are dropped when the element is disconnected, so they are usually created in
+//! [connected_callback](crate::CustomElement::connected_callback).
+
+use futures_signals::signal::{Signal, SignalExt};
+use futures_util::future::{abortable, ready};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{CharacterData, Element, HtmlElement};
+
+use crate::scope::Scope;
+
+/// Sets the text of `node`, a text node or comment, to each value of `signal`, until `this` is disconnected.
+pub fn bind_text<S>(this: &HtmlElement, node: &CharacterData, signal: S)
+where
+    S: Signal + 'static,
+    S::Item: AsRef<str>,
+{
+    let node = node.clone();
+    run_until_disconnected(this, signal, move |value| node.set_data(value.as_ref()));
+}
+
+/// Sets the attribute `name` of `element` to each value of `signal`, removing it for `None`, until `this` is
+/// disconnected.
+pub fn bind_attr<S, T>(this: &HtmlElement, element: &Element, name: &str, signal: S)
+where
+    S: Signal<Item = Option<T>> + 'static,
+    T: AsRef<str>,
+{
+    let (element, name) = (element.clone(), name.to_string());
+    run_until_disconnected(this, signal, move |value| match value {
+        Some(value) => element.set_attribute(&name, value.as_ref()).unwrap_throw(),
+        None => element.remove_attribute(&name).unwrap_throw(),
+    });
+}
+
+// Calls `apply` with each value of `signal`, until the element is disconnected.
+fn run_until_disconnected<S: Signal + 'static>(
+    this: &HtmlElement,
+    signal: S,
+    mut apply: impl FnMut(S::Item) + 'static,
+) {
+    let (binding, handle) = abortable(signal.for_each(move |value| {
+        apply(value);
+        ready(())
+    }));
+    spawn_local(async move {
+        let _ = binding.await;
+    });
+    Scope::new(this).on_cleanup(move || handle.abort());
+}
@@ -0,0 +1,442 @@
+//! Styling helpers beyond [inject_style](crate::inject_style).
+//!
+//! Without a shadow root, a component's styles apply to the whole page, and the page's styles apply to the component.
+//! [inject_scoped_style] emulates the first half of shadow DOM's encapsulation for light-DOM components, by rewriting
+//! every selector in a stylesheet to only match inside elements with the component's tag name.
+//!
+//! In the other direction, [register_global] shares a base stylesheet, like a design system's resets, with the
+//! shadow roots of every element defined with this crate.
+//!
+//! Until an element is defined, which may be after its wasm module has been downloaded, the page shows it as an
+//! unknown element, with its light-DOM children unstyled. [hide_until_defined] hides such elements until their
+//! definitions arrive, and [hide_undefined_css] returns the equivalent CSS, to put in the page's own stylesheet
+//! so that they are hidden before any script runs.
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Document, HtmlElement};
+
+use crate::{csp, defined_tag_name, document_of, host};
+
+/// The class that [inject_scoped_style] adds to every element with this element's tag name, like
+/// `scoped-my-component`.
+pub fn scope_class(this: &HtmlElement) -> String {
+    format!("scoped-{}", defined_tag_name(this))
+}
+
+/// Adds `css` to the document with each of its selectors rewritten to only match the host and its descendants,
+/// for components whose [shadow](crate::CustomElement::shadow) is `false`. `:host` and `:host(...)` select the
+/// host element itself, as they would in a shadow root. If the element has a shadow root, `css` is simply added
+/// to it, like [inject_style](crate::inject_style).
+///
+/// The rewritten stylesheet is shared by every element with the same tag name, and only added once to each
+/// document. Unlike shadow DOM, this does not stop the page's styles from applying to the component, and the
+/// component's selectors also match inside the light DOM of components nested in it.
+///
+/// ```rust,ignore
+/// inject_scoped_style(this, ":host { display: block; } p { color: green; }");
+/// // adds ".scoped-my-component { display: block; } .scoped-my-component p { color: green; }"
+/// ```
+pub fn inject_scoped_style(this: &HtmlElement, css: &str) {
+    let host = host(this);
+    if host.shadow_root().is_some() {
+        crate::inject_style(&host, css);
+        return;
+    }
+
+    let class = scope_class(&host);
+    host.class_list().add_1(&class).unwrap_throw();
+
+    // the stylesheets already added to each document are kept on the document, so that an element in an iframe
+    // gets its own
+    let document = document_of(&host);
+    let key = JsValue::from_str("_scopedStyles");
+    let mut added = js_sys::Reflect::get(&document, &key).unwrap_throw();
+    if added.is_undefined() {
+        added = js_sys::Set::new(&JsValue::UNDEFINED).into();
+        js_sys::Reflect::set(&document, &key, &added).unwrap_throw();
+    }
+    let added: js_sys::Set = added.unchecked_into();
+    let id = JsValue::from_str(&format!("{}\n{}", class, css));
+    if !added.has(&id) {
+        added.add(&id);
+        let style = document.create_element("style").unwrap_throw();
+        csp::apply(&style);
+        style.set_text_content(Some(&scope_css(css, &class)));
+        document
+            .head()
+            .unwrap_throw()
+            .append_child(&style)
+            .unwrap_throw();
+    }
+}
+
+// Rewrites each style rule in `css` to only apply inside elements with the class `scope`.
+fn scope_css(css: &str, scope: &str) -> String {
+    let css = strip_comments(css);
+    let mut scoped = String::with_capacity(css.len());
+    let mut rest = css.as_str();
+    loop {
+        rest = rest.trim_start();
+        let start = match unquoted(rest).find(|&(_, c)| c == '{' || c == ';') {
+            Some((start, _)) => start,
+            None => break,
+        };
+        // a statement like `@import url(...);`
+        if rest.as_bytes()[start] == b';' {
+            scoped.push_str(&rest[..=start]);
+            rest = &rest[start + 1..];
+            continue;
+        }
+        let end = matching_brace(rest, start);
+        let prelude = rest[..start].trim();
+        let body = &rest[start + 1..end];
+        if prelude.starts_with("@media")
+            || prelude.starts_with("@supports")
+            || prelude.starts_with("@container")
+            || prelude.starts_with("@layer")
+        {
+            scoped.push_str(&format!("{} {{ {} }}", prelude, scope_css(body, scope)));
+        } else if prelude.starts_with('@') {
+            // `@keyframes`, `@font-face`, and the like contain no selectors
+            scoped.push_str(rest.get(..=end).unwrap_or(rest));
+        } else {
+            let selectors = split_selectors(prelude)
+                .into_iter()
+                .map(|selector| scope_selector(selector, scope))
+                .collect::<Vec<_>>()
+                .join(", ");
+            scoped.push_str(&format!("{} {{{}}}", selectors, body));
+        }
+        rest = rest.get(end + 1..).unwrap_or("");
+    }
+    scoped
+}
+
+// The characters of `css` that are outside of quoted strings, with their indices, so that a `{` or `,` in a string,
+// like `content: "{"` or `[title="a, b"]`, is not taken for syntax. Quotes and escaped characters are skipped too.
+fn unquoted(css: &str) -> impl Iterator<Item = (usize, char)> + '_ {
+    let mut quote = None;
+    let mut escaped = false;
+    css.char_indices().filter(move |&(_, c)| {
+        if escaped {
+            escaped = false;
+            return false;
+        }
+        if c == '\\' {
+            escaped = true;
+            return false;
+        }
+        match quote {
+            Some(open) => {
+                if c == open {
+                    quote = None;
+                }
+                false
+            }
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                false
+            }
+            None => true,
+        }
+    })
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut stripped = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some((start, _)) =
+        unquoted(rest).find(|&(index, c)| c == '/' && rest[index + 1..].starts_with('*'))
+    {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+// The index of the `}` that closes the `{` at `open`, or the end of `css` if it is never closed.
+fn matching_brace(css: &str, open: usize) -> usize {
+    let mut depth = 0;
+    for (index, c) in unquoted(&css[open..]) {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return open + index;
+                }
+            }
+            _ => {}
+        }
+    }
+    css.len()
+}
+
+// Splits a selector list on the commas that are not inside parentheses, like those in `:is(a, b)`.
+fn split_selectors(selectors: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in unquoted(selectors) {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                split.push(selectors[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(selectors[start..].trim());
+    split
+}
+
+// The index of the `)` that closes a `(` just before the start of `selector`.
+fn closing_paren(selector: &str) -> usize {
+    let mut depth = 1;
+    for (index, c) in unquoted(selector) {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => {}
+        }
+    }
+    selector.len()
+}
+
+fn scope_selector(selector: &str, scope: &str) -> String {
+    if let Some(rest) = selector.strip_prefix(":host-context(") {
+        let close = closing_paren(rest);
+        let after = rest.get(close + 1..).unwrap_or("");
+        format!("{} .{}{}", &rest[..close], scope, after)
+    } else if let Some(rest) = selector.strip_prefix(":host(") {
+        let close = closing_paren(rest);
+        let after = rest.get(close + 1..).unwrap_or("");
+        format!(".{}{}{}", scope, &rest[..close], after)
+    } else if let Some(after) = selector.strip_prefix(":host") {
+        format!(".{}{}", scope, after)
+    } else {
+        format!(".{} {}", scope, selector)
+    }
+}
+
+/// Adds `css` to the shadow root of every element defined with this crate, both those that already exist and those
+/// created later, before the styles that each component adds itself.
+///
+/// The stylesheet is parsed once and shared as a
+/// [constructable stylesheet](https://developer.mozilla.org/en-US/docs/Web/API/CSSStyleSheet/CSSStyleSheet); in
+/// browsers that do not support them, each shadow root gets its own `<style>` element instead, with the page's
+/// [nonce](crate::csp). In the few browsers without `WeakRef`, elements that already exist only get it if it was
+/// registered before they were created.
+///
+/// ```rust,ignore
+/// #[wasm_bindgen]
+/// pub fn define_elements() {
+///     styles::register_global(include_str!("reset.css"));
+///     MyButton::define("my-button");
+/// }
+/// ```
+pub fn register_global(css: &str) {
+    register_global_style(css, csp::nonce());
+}
+
+/// CSS that hides the elements with the given tag names until they are defined, using the
+/// [`:defined`](https://developer.mozilla.org/en-US/docs/Web/CSS/:defined) pseudo-class.
+///
+/// ```rust,ignore
+/// hide_undefined_css(&["my-button", "my-card"]);
+/// // "my-button:not(:defined), my-card:not(:defined) { visibility: hidden; }"
+/// ```
+pub fn hide_undefined_css(tag_names: &[&str]) -> String {
+    let selectors = tag_names
+        .iter()
+        .map(|tag_name| format!("{}:not(:defined)", tag_name))
+        .collect::<Vec<_>>();
+    format!("{} {{ visibility: hidden; }}", selectors.join(", "))
+}
+
+/// Adds a stylesheet to the document that hides the elements with the given tag names until they are defined, like
+/// [hide_undefined_css]. Each tag name's stylesheet is removed once it has been defined with this crate, so unlike
+/// that CSS, it also hides an element [defined lazily](crate::lazy) until its bundle has loaded, although its
+/// placeholder already matches `:defined`. Tag names that are already defined are skipped.
+///
+/// Call this before the elements are defined, for example before loading them [lazily](crate::lazy). For elements
+/// in an iframe, use [hide_until_defined_in] with the iframe's document.
+///
+///
+/// ```rust,ignore
+/// #[wasm_bindgen(start)]
+/// pub fn start() {
+///     styles::hide_until_defined(&["data-grid"]);
+///     lazy::define_lazy("data-grid", "/grid.js", "define_grid");
+/// }
+/// ```
+pub fn hide_until_defined(tag_names: &[&str]) {
+    hide_until_defined_in(
+        &window().unwrap_throw().document().unwrap_throw(),
+        tag_names,
+    );
+}
+
+/// Like [hide_until_defined], for the elements in `document`, which are defined in its window with
+/// [define_in](crate::CustomElement::define_in).
+pub fn hide_until_defined_in(document: &Document, tag_names: &[&str]) {
+    let window = match document.default_view() {
+        Some(window) => window,
+        None => return,
+    };
+    for tag_name in tag_names {
+        if is_defined(&window, tag_name) || hide_style(document, tag_name).is_some() {
+            continue;
+        }
+        let style = document.create_element("style").unwrap_throw();
+        style
+            .set_attribute("data-hide-until-defined", tag_name)
+            .unwrap_throw();
+        csp::apply(&style);
+        style.set_text_content(Some(&format!("{} {{ visibility: hidden; }}", tag_name)));
+        document
+            .head()
+            .unwrap_throw()
+            .append_child(&style)
+            .unwrap_throw();
+    }
+}
+
+// Removes the stylesheet added by `hide_until_defined` for a tag name that has just been defined in `global`, from
+// that window's document, if any.
+pub(crate) fn defined(tag_name: &str, global: &JsValue) {
+    let document = js_sys::Reflect::get(global, &JsValue::from_str("document"))
+        .ok()
+        .and_then(|document| document.dyn_into::<Document>().ok());
+    if let Some(style) = document.and_then(|document| hide_style(&document, tag_name)) {
+        style.remove();
+    }
+}
+
+// The stylesheet added by `hide_until_defined` to `document` for `tag_name`.
+fn hide_style(document: &Document, tag_name: &str) -> Option<web_sys::Element> {
+    let selector = format!("style[data-hide-until-defined=\"{}\"]", tag_name);
+    document.query_selector(&selector).unwrap_throw()
+}
+
+#[wasm_bindgen(module = "/src/make_custom_element.js")]
+extern "C" {
+    fn register_global_style(css: &str, nonce: Option<String>);
+
+    fn is_defined(window: &JsValue, tag_name: &str) -> bool;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes_each_rule() {
+        assert_eq!(
+            scope_css("p { color: green; } a:hover { color: red; }", "s"),
+            ".s p { color: green; }.s a:hover { color: red; }"
+        );
+    }
+
+    #[test]
+    fn scopes_each_selector_in_a_list() {
+        assert_eq!(scope_css("h1, h2 > span {}", "s"), ".s h1, .s h2 > span {}");
+    }
+
+    #[test]
+    fn keeps_commas_inside_functional_pseudo_classes() {
+        assert_eq!(
+            scope_css(":is(a, b) > c, d:not(.x, .y) {}", "s"),
+            ".s :is(a, b) > c, .s d:not(.x, .y) {}"
+        );
+        assert_eq!(
+            split_selectors("a:where(:not(b, c), d), e"),
+            vec!["a:where(:not(b, c), d)", "e"]
+        );
+    }
+
+    #[test]
+    fn rewrites_host_selectors() {
+        assert_eq!(
+            scope_css(":host { display: block; }", "s"),
+            ".s { display: block; }"
+        );
+        assert_eq!(scope_css(":host(.active) p {}", "s"), ".s.active p {}");
+        assert_eq!(
+            scope_css(":host(:not(.a, .b)) {}", "s"),
+            ".s:not(.a, .b) {}"
+        );
+        assert_eq!(scope_css(":host-context(.dark) p {}", "s"), ".dark .s p {}");
+        assert_eq!(scope_css(":host, p {}", "s"), ".s, .s p {}");
+    }
+
+    #[test]
+    fn scopes_rules_inside_conditional_group_rules() {
+        assert_eq!(
+            scope_css("@media (min-width: 600px) { p { margin: 0; } }", "s"),
+            "@media (min-width: 600px) { .s p { margin: 0; } }"
+        );
+        assert_eq!(
+            scope_css(
+                "@supports (display: grid) { @media print { :host, a {} } }",
+                "s"
+            ),
+            "@supports (display: grid) { @media print { .s, .s a {} } }"
+        );
+    }
+
+    #[test]
+    fn leaves_other_at_rules_alone() {
+        let keyframes = "@keyframes fade { from { opacity: 0; } to { opacity: 1; } }";
+        assert_eq!(scope_css(keyframes, "s"), keyframes);
+        assert_eq!(
+            scope_css("@import url(base.css); p {}", "s"),
+            "@import url(base.css);.s p {}"
+        );
+    }
+
+    #[test]
+    fn ignores_comments() {
+        assert_eq!(
+            scope_css("/* a, b { } */ p { color: red; /* } */ }", "s"),
+            ".s p { color: red;  }"
+        );
+        assert_eq!(strip_comments("a /* unclosed"), "a ");
+    }
+
+    #[test]
+    fn ignores_syntax_inside_strings() {
+        assert_eq!(
+            scope_css(r#"p::before { content: "}"; } a {}"#, "s"),
+            r#".s p::before { content: "}"; }.s a {}"#
+        );
+        assert_eq!(
+            scope_css(r#"[title="a, b"], q::after { content: '/* {' }"#, "s"),
+            r#".s [title="a, b"], .s q::after { content: '/* {' }"#
+        );
+        assert_eq!(
+            scope_css(r#"p::before { content: "\"}"; }"#, "s"),
+            r#".s p::before { content: "\"}"; }"#
+        );
+    }
+
+    #[test]
+    fn hides_undefined_elements() {
+        assert_eq!(
+            hide_undefined_css(&["my-button", "my-card"]),
+            "my-button:not(:defined), my-card:not(:defined) { visibility: hidden; }"
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+use web_sys::HtmlElement;
+
+use crate::instances::dispatch;
+
+// The connected elements listening to some document-level source (the URL, the locale, etc.).
+// Each subscriber is notified through its handler for the `kind` of callback, like `"routeChangedCallback"`.
+pub(crate) struct Subscribers {
+    kind: &'static str,
+    elements: RefCell<Vec<HtmlElement>>,
+}
+
+impl Subscribers {
+    pub(crate) const fn new(kind: &'static str) -> Self {
+        Self {
+            kind,
+            elements: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn subscribe(&self, el: &HtmlElement) {
+        self.elements.borrow_mut().push(el.clone());
+    }
+
+    pub(crate) fn unsubscribe(&self, el: &HtmlElement) {
+        self.elements
+            .borrow_mut()
+            .retain(|subscriber| subscriber != el);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.elements.borrow().is_empty()
+    }
+
+    pub(crate) fn notify(&self, args: &[&JsValue]) {
+        // callbacks may connect or disconnect elements, so don't hold the borrow while calling them
+        let elements = self.elements.borrow().clone();
+        for el in elements {
+            self.deliver(&el, args);
+        }
+    }
+
+    // Calls the element's handler with the element itself, followed by `args`.
+    pub(crate) fn deliver(&self, el: &HtmlElement, args: &[&JsValue]) {
+        dispatch(el, self.kind, args);
+    }
+}
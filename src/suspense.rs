@@ -0,0 +1,141 @@
+//! Placeholders for content that is loaded asynchronously. Requires the `async` feature.
+//!
+//! Components backed by data that has to be fetched can't build their DOM synchronously in
+//! [inject_children](crate::CustomElement::inject_children). [inject_async] shows a placeholder in the meantime,
+//! like a spinner or skeleton, and swaps in the real content once the future that builds it resolves.
+//!
+//! ```rust,ignore
+//! fn inject_children(&mut self, this: &HtmlElement) {
+//!     let id = self.id.clone();
+//!     suspense::inject_async(this, Placeholder::Slot("loading"), async move {
+//!         let user = api::fetch_user(&id).await?;
+//!         Ok(render_user(&user))
+//!     });
+//! }
+//! ```
+//!
+//! Large initial renders can instead be streamed with [inject_stream], which appends nodes as a [Stream] produces
+//! them, and yields to the event loop between them so the page stays responsive.
+
+use std::future::Future;
+
+use futures_util::{Stream, StreamExt};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{CustomEvent, CustomEventInit, HtmlElement, Node};
+
+use crate::{events, host, logging};
+
+/// What to show while the content passed to [inject_async] is loading.
+#[derive(Clone, Debug)]
+pub enum Placeholder {
+    /// Nothing.
+    None,
+    /// A node built by the component, like a spinner or a skeleton of the content.
+    Node(Node),
+    /// A `<slot>` with the given name, so that the page can provide its own placeholder:
+    /// `<user-card><span slot="loading">Loading…</span></user-card>`. Only useful in a shadow root.
+    Slot(&'static str),
+}
+
+/// Appends `placeholder` to `this`, which is usually the shadow root handed to
+/// [inject_children](crate::CustomElement::inject_children), and replaces it with the node `content` resolves to.
+///
+/// Once the content is in place, a `content-ready` event, which bubbles and is composed, is fired from the host
+/// element. If `content` fails, the placeholder is removed, and the error is logged and reported with a
+/// [`ce-error`](crate::events) event.
+pub fn inject_async(
+    this: &HtmlElement,
+    placeholder: Placeholder,
+    content: impl Future<Output = Result<Node, JsValue>> + 'static,
+) {
+    let placeholder: Option<Node> = match placeholder {
+        Placeholder::None => None,
+        Placeholder::Node(node) => Some(node),
+        Placeholder::Slot(name) => {
+            let slot = this
+                .owner_document()
+                .unwrap_throw()
+                .create_element("slot")
+                .unwrap_throw();
+            slot.set_attribute("name", name).unwrap_throw();
+            Some(slot.into())
+        }
+    };
+    if let Some(placeholder) = &placeholder {
+        this.append_child(placeholder).unwrap_throw();
+    }
+
+    let root = this.clone();
+    spawn_local(async move {
+        let content = content.await;
+        if let Some(placeholder) = &placeholder {
+            if placeholder.parent_node().is_some() {
+                root.remove_child(placeholder).unwrap_throw();
+            }
+        }
+        match content {
+            Ok(content) => {
+                root.append_child(&content).unwrap_throw();
+                content_ready(&root);
+            }
+            Err(error) => {
+                events::report_error(&root, "inject_async", &error);
+                logging::error("error loading custom element content", &error);
+            }
+        }
+    });
+}
+
+// How long to keep appending nodes before yielding to the event loop, in milliseconds.
+const STREAM_BUDGET: f64 = 8.0;
+
+/// Appends each node `nodes` produces to `this`, which is usually the shadow root handed to
+/// [inject_children](crate::CustomElement::inject_children), as it arrives. Whenever it has spent a few milliseconds
+/// appending nodes, it waits for a new task before appending more, so that a stream producing thousands of nodes at
+/// once doesn't block the main thread.
+///
+/// Once the stream ends, a `content-ready` event is fired from the host element, as with [inject_async].
+///
+/// ```rust,ignore
+/// fn inject_children(&mut self, this: &HtmlElement) {
+///     let rows = self.rows.clone();
+///     suspense::inject_stream(this, futures::stream::iter(rows.into_iter().map(render_row)));
+/// }
+/// ```
+pub fn inject_stream(this: &HtmlElement, nodes: impl Stream<Item = Node> + 'static) {
+    let root = this.clone();
+    spawn_local(async move {
+        let mut nodes = Box::pin(nodes);
+        let mut started = js_sys::Date::now();
+        while let Some(node) = nodes.next().await {
+            root.append_child(&node).unwrap_throw();
+            if js_sys::Date::now() - started > STREAM_BUDGET {
+                next_task().await;
+                started = js_sys::Date::now();
+            }
+        }
+        content_ready(&root);
+    });
+}
+
+// Resolves in a new task, after the browser has had a chance to render and handle input.
+async fn next_task() {
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("setTimeout"))
+            .unwrap_throw()
+            .unchecked_into::<js_sys::Function>()
+            .call1(&JsValue::NULL, &resolve)
+            .unwrap_throw();
+    });
+    JsFuture::from(promise).await.unwrap_throw();
+}
+
+fn content_ready(root: &HtmlElement) {
+    let init = CustomEventInit::new();
+    init.set_bubbles(true);
+    init.set_composed(true);
+    let event = CustomEvent::new_with_event_init_dict("content-ready", &init).unwrap_throw();
+    host(root).dispatch_event(&event).unwrap_throw();
+}
@@ -0,0 +1,70 @@
+//! Templates parsed once per component type, and cloned for each instance.
+//!
+//! Setting `innerHTML` parses the markup each time. For a component with a lot of static markup, it is faster to
+//! parse it into a `<template>` once, and clone the template's content for each instance, which is what
+//! [TemplateCache] does:
+//!
+//! ```rust,ignore
+//! fn inject_children(&mut self, this: &HtmlElement) {
+//!     let content = TemplateCache::get_or_init::<Self>(|| include_str!("data_grid.html").to_string());
+//!     this.append_child(&content).unwrap_throw();
+//!     self.refs = Refs::collect(this);
+//! }
+//! ```
+
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, DocumentFragment, HtmlTemplateElement};
+
+use crate::trusted_types;
+
+thread_local! {
+    static TEMPLATES: RefCell<HashMap<TypeId, HtmlTemplateElement>> = RefCell::new(HashMap::new());
+}
+
+/// The templates of each component type. See the [module documentation](self).
+pub struct TemplateCache;
+
+impl TemplateCache {
+    /// A copy of the content of `T`'s template, which is parsed from the markup `html` returns the first time it is
+    /// needed.
+    pub fn get_or_init<T: 'static>(html: impl FnOnce() -> String) -> DocumentFragment {
+        let key = TypeId::of::<T>();
+        let existing = TEMPLATES.with(|templates| templates.borrow().get(&key).cloned());
+        let template = match existing {
+            Some(template) => template,
+            None => {
+                let template: HtmlTemplateElement = window()
+                    .unwrap_throw()
+                    .document()
+                    .unwrap_throw()
+                    .create_element("template")
+                    .unwrap_throw()
+                    .unchecked_into();
+                trusted_types::set_trusted_inner_html(&template, &html());
+                TEMPLATES.with(|templates| templates.borrow_mut().insert(key, template.clone()));
+                template
+            }
+        };
+        template
+            .content()
+            .clone_node_with_deep(true)
+            .unwrap_throw()
+            .unchecked_into()
+    }
+
+    /// Whether `T`'s template has been parsed.
+    pub fn contains<T: 'static>() -> bool {
+        TEMPLATES.with(|templates| templates.borrow().contains_key(&TypeId::of::<T>()))
+    }
+
+    /// Forgets `T`'s template, so that it is parsed again the next time it is needed, for example after the markup
+    /// has been reloaded during development.
+    pub fn invalidate<T: 'static>() {
+        TEMPLATES.with(|templates| templates.borrow_mut().remove(&TypeId::of::<T>()));
+    }
+}
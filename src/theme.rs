@@ -0,0 +1,237 @@
+//! A theming root for design systems. Requires the `theme` feature.
+//!
+//! [ThemeProvider] is a custom element whose `theme-*` attributes are the theme: each one sets the CSS custom
+//! property of the same name, so `theme-primary` sets `--primary`, which the provider's descendants inherit, into
+//! their shadow roots too:
+//!
+//! ```html
+//! <theme-provider theme-primary="#0060df" theme-radius="4px">
+//!   <my-button></my-button>
+//! </theme-provider>
+//! ```
+//!
+//! Components that need the theme in Rust, like one that draws on a canvas, can [consume] it. The provider answers
+//! requests made with the [context protocol](https://github.com/webcomponents-cg/community-protocols/blob/main/proposals/context.md),
+//! so components written with other libraries can subscribe to it as well, with the context key [THEME_CONTEXT].
+//! They receive the theme as an object with a property for each custom property, like `{ "--primary": "#0060df" }`.
+//!
+//! ```rust,ignore
+//! ThemeProvider::define("theme-provider");
+//!
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     let chart = self.chart.clone();
+//!     theme::consume(this, move |theme| chart.set_color(theme.get("--primary").unwrap_or("black")));
+//! }
+//! ```
+//!
+//! Define `ThemeProvider` before the components inside it, so that it is ready to answer their requests when they are
+//! connected.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{CustomEvent, CustomEventInit, Event, HtmlElement};
+
+use crate::attributes::HostSetup;
+use crate::scope::Scope;
+use crate::{host, CustomElement};
+
+/// The context key with which components request the theme from a [ThemeProvider].
+pub const THEME_CONTEXT: &str = "theme";
+
+// the prefix of the attributes that make up the theme
+const PREFIX: &str = "theme-";
+
+/// A set of CSS custom properties, by name, like `--primary`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Theme {
+    properties: BTreeMap<String, String>,
+}
+
+impl Theme {
+    /// The value of a custom property, if the theme sets it.
+    pub fn get(&self, property: &str) -> Option<&str> {
+        self.properties.get(property).map(String::as_str)
+    }
+
+    /// Each custom property the theme sets, with its value, in alphabetical order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.properties
+            .iter()
+            .map(|(property, value)| (property.as_str(), value.as_str()))
+    }
+
+    fn to_js(&self) -> JsValue {
+        let object = js_sys::Object::new();
+        for (property, value) in self.iter() {
+            js_sys::Reflect::set(&object, &property.into(), &value.into()).unwrap_throw();
+        }
+        object.into()
+    }
+
+    fn from_js(value: &JsValue) -> Self {
+        let properties = js_sys::Object::entries(value.unchecked_ref())
+            .iter()
+            .filter_map(|entry| {
+                let entry: js_sys::Array = entry.unchecked_into();
+                Some((entry.get(0).as_string()?, entry.get(1).as_string()?))
+            })
+            .collect();
+        Self { properties }
+    }
+}
+
+type Subscribers = Rc<RefCell<Vec<(u32, js_sys::Function)>>>;
+
+/// A custom element that sets CSS custom properties for its descendants from its `theme-*` attributes, and provides
+/// them to components that request them. See the [module documentation](self).
+#[derive(Default)]
+pub struct ThemeProvider {
+    theme: Rc<RefCell<Theme>>,
+    subscribers: Subscribers,
+    next_id: Rc<Cell<u32>>,
+}
+
+impl ThemeProvider {
+    fn notify(&self) {
+        let theme = self.theme.borrow().to_js();
+        // a subscriber may unsubscribe while it is called
+        let subscribers = self.subscribers.borrow().clone();
+        for (_, callback) in subscribers {
+            callback.call1(&JsValue::NULL, &theme).unwrap_throw();
+        }
+    }
+}
+
+impl CustomElement for ThemeProvider {
+    fn shadow() -> bool {
+        false
+    }
+
+    fn observe_all_attributes() -> bool {
+        true
+    }
+
+    // the provider only passes the theme down, so it should not affect layout
+    fn host_setup() -> HostSetup {
+        HostSetup::new().style("display", "contents")
+    }
+
+    fn attribute_changed_callback(
+        &mut self,
+        this: &HtmlElement,
+        name: String,
+        _old_value: Option<String>,
+        new_value: Option<String>,
+    ) {
+        let Some(name) = name.strip_prefix(PREFIX) else {
+            return;
+        };
+        let property = format!("--{}", name);
+        let style = host(this).style();
+        match new_value {
+            Some(value) => {
+                style.set_property(&property, &value).unwrap_throw();
+                self.theme.borrow_mut().properties.insert(property, value);
+            }
+            None => {
+                style.remove_property(&property).unwrap_throw();
+                self.theme.borrow_mut().properties.remove(&property);
+            }
+        }
+        self.notify();
+    }
+
+    fn connected_callback(&mut self, this: &HtmlElement) {
+        let host = host(this);
+        let (theme, subscribers, next_id) = (
+            self.theme.clone(),
+            self.subscribers.clone(),
+            self.next_id.clone(),
+        );
+        let listener = Closure::wrap(Box::new(move |event: Event| {
+            let context = js_sys::Reflect::get(&event, &"context".into()).unwrap_throw();
+            let callback = js_sys::Reflect::get(&event, &"callback".into()).unwrap_throw();
+            if context != THEME_CONTEXT || !callback.is_function() {
+                return;
+            }
+            event.stop_immediate_propagation();
+            let callback: js_sys::Function = callback.unchecked_into();
+            let subscribe = js_sys::Reflect::get(&event, &"subscribe".into())
+                .unwrap_throw()
+                .is_truthy();
+            if !subscribe {
+                callback
+                    .call1(&JsValue::NULL, &theme.borrow().to_js())
+                    .unwrap_throw();
+                return;
+            }
+            let id = next_id.get();
+            next_id.set(id + 1);
+            subscribers.borrow_mut().push((id, callback.clone()));
+            let unsubscribe = Closure::once_into_js({
+                let subscribers = Rc::downgrade(&subscribers);
+                move || {
+                    if let Some(subscribers) = subscribers.upgrade() {
+                        subscribers.borrow_mut().retain(|(other, _)| *other != id);
+                    }
+                }
+            });
+            callback
+                .call2(&JsValue::NULL, &theme.borrow().to_js(), &unsubscribe)
+                .unwrap_throw();
+        }) as Box<dyn FnMut(Event)>);
+        host.add_event_listener_with_callback("context-request", listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+        // components inside request the theme again when they are reconnected
+        let subscribers = self.subscribers.clone();
+        Scope::new(&host).on_cleanup({
+            let host = host.clone();
+            move || {
+                host.remove_event_listener_with_callback(
+                    "context-request",
+                    listener.as_ref().unchecked_ref(),
+                )
+                .unwrap_throw();
+                subscribers.borrow_mut().clear();
+            }
+        });
+    }
+}
+
+/// Requests the theme from the nearest [ThemeProvider] around the element, and calls `callback` with it, and again
+/// each time it changes, until the element is disconnected. `this` may be either the element or its shadow root.
+///
+/// Does nothing if there is no provider around the element.
+pub fn consume(this: &HtmlElement, mut callback: impl FnMut(&Theme) + 'static) {
+    let host = host(this);
+    let unsubscribe = Rc::new(RefCell::new(None::<js_sys::Function>));
+    let receive = Closure::wrap(Box::new({
+        let unsubscribe = unsubscribe.clone();
+        move |theme: JsValue, unsubscribe_fn: JsValue| {
+            if let Some(unsubscribe_fn) = unsubscribe_fn.dyn_ref::<js_sys::Function>() {
+                unsubscribe.borrow_mut().replace(unsubscribe_fn.clone());
+            }
+            callback(&Theme::from_js(&theme));
+        }
+    }) as Box<dyn FnMut(JsValue, JsValue)>);
+
+    let init = CustomEventInit::new();
+    init.set_bubbles(true);
+    init.set_composed(true);
+    let event = CustomEvent::new_with_event_init_dict("context-request", &init).unwrap_throw();
+    js_sys::Reflect::set(&event, &"context".into(), &THEME_CONTEXT.into()).unwrap_throw();
+    js_sys::Reflect::set(&event, &"callback".into(), receive.as_ref()).unwrap_throw();
+    js_sys::Reflect::set(&event, &"subscribe".into(), &JsValue::TRUE).unwrap_throw();
+    host.dispatch_event(&event).unwrap_throw();
+
+    Scope::new(&host).on_cleanup(move || {
+        if let Some(unsubscribe) = unsubscribe.borrow_mut().take() {
+            unsubscribe.call0(&JsValue::NULL).unwrap_throw();
+        }
+        drop(receive);
+    });
+}
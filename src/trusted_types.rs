@@ -0,0 +1,123 @@
+//! Compatibility with [Trusted Types](https://developer.mozilla.org/en-US/docs/Web/API/Trusted_Types_API).
+//!
+//! A page whose Content Security Policy includes `require-trusted-types-for 'script'` only lets `innerHTML` be set
+//! to a `TrustedHTML` value, created by one of the page's policies, and throws for a string. The crate parses markup
+//! in a few places, like [TemplateCache](crate::template::TemplateCache), and does so through a policy of its own,
+//! named [POLICY_NAME], which the page's CSP has to allow:
+//!
+//! ```text
+//! Content-Security-Policy: require-trusted-types-for 'script'; trusted-types custom-elements
+//! ```
+//!
+//! The crate's policy accepts any markup, since it only parses markup written by components, or that
+//! [sanitize_html](crate::sanitize_html) then cleans, and it is only used inside the crate. Applications that review
+//! every policy can create their own instead and hand it to the crate with [set_policy].
+//!
+//! Components that set `innerHTML` themselves can use [set_inner_html], which accepts a `TrustedHTML` created by one
+//! of the application's policies, or a string, which it sanitizes with [sanitize_html](crate::sanitize_html) rather
+//! than trusting. `inject_style` does not parse markup, so it needs no policy.
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+
+use crate::logging;
+
+/// The name of the policy the crate creates, which the page's `trusted-types` CSP directive has to allow.
+pub const POLICY_NAME: &str = "custom-elements";
+
+thread_local! {
+    // `undefined` once it is known that there is no policy, because the browser does not support Trusted Types or
+    // the page does not allow the crate's policy
+    static POLICY: RefCell<Option<JsValue>> = const { RefCell::new(None) };
+}
+
+/// Uses `policy`, a policy the application created with `trustedTypes.createPolicy()`, instead of creating the
+/// crate's own. Call it at startup, before any elements are created.
+pub fn set_policy(policy: JsValue) {
+    POLICY.with(|current| *current.borrow_mut() = Some(policy));
+}
+
+// The policy the crate uses: the one given to `set_policy`, or else its own, which it creates the first time it is
+// needed. `None` if the browser does not support Trusted Types, or the page's CSP does not allow the policy.
+fn policy() -> Option<JsValue> {
+    let policy = POLICY.with(|policy| {
+        policy
+            .borrow_mut()
+            .get_or_insert_with(create_policy)
+            .clone()
+    });
+    Some(policy).filter(|policy| !policy.is_undefined())
+}
+
+// `html` as a `TrustedHTML` created by the crate's policy, or as a string if there is none. Only for markup written
+// by the crate or its components, or parsed somewhere inert to be sanitized.
+fn trusted_html(html: &str) -> JsValue {
+    let html = JsValue::from_str(html);
+    let trusted = policy().and_then(|policy| {
+        let create_html: js_sys::Function = js_sys::Reflect::get(&policy, &"createHTML".into())
+            .ok()?
+            .dyn_into()
+            .ok()?;
+        create_html.call1(&policy, &html).ok()
+    });
+    trusted.unwrap_or(html)
+}
+
+/// Sets the content of `element` to `html`, which is either a `TrustedHTML` created by one of the application's
+/// policies, which is set as the `innerHTML` as it is, or a string, which is not trusted: its content is
+/// [sanitized](crate::sanitize_html) and added in place of the element's children.
+pub fn set_inner_html(element: &Element, html: &JsValue) {
+    match html.as_string() {
+        Some(html) => {
+            element.set_text_content(None);
+            element
+                .append_child(&crate::sanitize::sanitize_html_in(
+                    &crate::document_of(element),
+                    &html,
+                ))
+                .unwrap_throw();
+        }
+        None => {
+            js_sys::Reflect::set(element, &"innerHTML".into(), html).unwrap_throw();
+        }
+    }
+}
+
+// Sets the `innerHTML` of `element` to `html` through the crate's policy, for markup written by the crate or its
+// components.
+pub(crate) fn set_trusted_inner_html(element: &Element, html: &str) {
+    js_sys::Reflect::set(element, &"innerHTML".into(), &trusted_html(html)).unwrap_throw();
+}
+
+fn create_policy() -> JsValue {
+    let trusted_types = js_sys::Reflect::get(&js_sys::global(), &"trustedTypes".into())
+        .unwrap_or(JsValue::UNDEFINED);
+    if trusted_types.is_undefined() {
+        return JsValue::UNDEFINED;
+    }
+    let rules = js_sys::Object::new();
+    let create_html = Closure::wrap(Box::new(|html: String| html) as Box<dyn Fn(String) -> String>);
+    js_sys::Reflect::set(&rules, &"createHTML".into(), &create_html.into_js_value()).unwrap_throw();
+    let created = js_sys::Reflect::get(&trusted_types, &"createPolicy".into())
+        .and_then(|create_policy| create_policy.dyn_into::<js_sys::Function>())
+        .and_then(|create_policy| {
+            create_policy.call2(&trusted_types, &JsValue::from_str(POLICY_NAME), &rules)
+        });
+    match created {
+        Ok(policy) => policy,
+        Err(error) => {
+            logging::error(
+                &format!(
+                    "could not create the Trusted Types policy {:?}; allow it in the page's `trusted-types` CSP \
+                     directive, or pass a policy to trusted_types::set_policy",
+                    POLICY_NAME
+                ),
+                &error,
+            );
+            JsValue::UNDEFINED
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Several versions of one component, side by side.
+//!
+//! A library that makes a breaking change to a component can keep shipping the old version alongside the new one by
+//! defining each under a versioned tag name with [define_versioned](crate::CustomElement::define_versioned):
+//! `x-button` version `2` becomes `<x-button-v2>`. Pages that have moved to the new version can then make the plain
+//! tag name point at it with [alias]:
+//!
+//! ```rust,ignore
+//! ButtonV1::define_versioned("x-button", "1");
+//! ButtonV2::define_versioned("x-button", "2");
+//! // <x-button> is now <x-button-v2>, while <x-button-v1> keeps working
+//! versions::alias("x-button", "2");
+//! ```
+//!
+//! An alias is a definition like any other, so it can only be made once per page, and only if nothing else has defined
+//! the plain tag name.
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{bundle, fallback};
+
+struct Version {
+    tag_name: String,
+    version: String,
+    superclass_tag: Option<String>,
+}
+
+thread_local! {
+    // the versions defined with `define_versioned`, in the order they were defined
+    static VERSIONS: RefCell<Vec<Version>> = const { RefCell::new(Vec::new()) };
+    // the plain tag names that have been aliased, with the version each points at
+    static ALIASES: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The tag name of a version of a component: `x-button` version `2` is `x-button-v2`. Versions should be lower case,
+/// since tag names are.
+pub fn versioned_tag(tag_name: &str, version: &str) -> String {
+    format!("{}-v{}", tag_name, version)
+}
+
+/// The versions of `tag_name` defined with [define_versioned](crate::CustomElement::define_versioned), in the order
+/// they were defined.
+pub fn versions(tag_name: &str) -> Vec<String> {
+    VERSIONS.with(|versions| {
+        versions
+            .borrow()
+            .iter()
+            .filter(|v| v.tag_name == tag_name)
+            .map(|v| v.version.clone())
+            .collect()
+    })
+}
+
+/// The version that `tag_name` has been made an [alias] of, if any.
+pub fn aliased(tag_name: &str) -> Option<String> {
+    ALIASES.with(|aliases| {
+        aliases
+            .borrow()
+            .iter()
+            .find(|(alias, _)| alias == tag_name)
+            .map(|(_, version)| version.clone())
+    })
+}
+
+/// Defines `tag_name` as another name for one of its versions, so that `<x-button>` creates the same component as
+/// `<x-button-v2>`.
+///
+/// Panics if that version has not been defined, or if `tag_name` has already been defined.
+pub fn alias(tag_name: &str, version: &str) {
+    let versioned = versioned_tag(tag_name, version);
+    let global = js_sys::global();
+    let registry = fallback::registry(&global, tag_name);
+    let class = bundle::existing(&registry, &versioned)
+        .expect_throw("aliased a version of a custom element that has not been defined");
+    let superclass_tag = VERSIONS.with(|versions| {
+        versions
+            .borrow()
+            .iter()
+            .find(|v| v.tag_name == tag_name && v.version == version)
+            .and_then(|v| v.superclass_tag.clone())
+    });
+    define_alias(&registry, tag_name, &class, superclass_tag.as_deref());
+    ALIASES.with(|aliases| {
+        aliases
+            .borrow_mut()
+            .push((tag_name.to_string(), version.to_string()))
+    });
+}
+
+// Records a version about to be defined, and returns its tag name.
+pub(crate) fn register(tag_name: &str, version: &str, superclass_tag: Option<&str>) -> String {
+    VERSIONS.with(|versions| {
+        versions.borrow_mut().push(Version {
+            tag_name: tag_name.to_string(),
+            version: version.to_string(),
+            superclass_tag: superclass_tag.map(String::from),
+        })
+    });
+    versioned_tag(tag_name, version)
+}
+
+#[wasm_bindgen(module = "/src/make_custom_element.js")]
+extern "C" {
+    fn define_alias(
+        registry: &JsValue,
+        alias: &str,
+        cls: &js_sys::Function,
+        superclass_tag: Option<&str>,
+    );
+}
@@ -0,0 +1,214 @@
+//! Virtualized lists, for components like data grids that show a few rows of a list too long to render in full.
+//! Requires the `virtual-list` feature.
+//!
+//! A [VirtualList] keeps only the items that are scrolled into view (plus a few on either side) in the DOM, creating
+//! each one with an item factory as it scrolls into view and removing it once it scrolls out. Every item has the same
+//! height, so that the position of each one is known without rendering the others.
+//!
+//! ```rust,ignore
+//! fn inject_children(&mut self, this: &HtmlElement) {
+//!     let viewport: HtmlElement = query(this, ".rows").unwrap_throw();
+//!     let rows = self.rows.clone();
+//!     self.list = Some(VirtualList::new(&viewport, 32.0, rows.len(), move |index| {
+//!         let row = MyRow::create_element(&document());
+//!         row.set_attribute("label", &rows[index]).unwrap_throw();
+//!         row.into()
+//!     }));
+//! }
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, Event, HtmlElement};
+
+type ItemFactory = Box<dyn FnMut(usize) -> Element>;
+
+struct State {
+    viewport: HtmlElement,
+    content: HtmlElement,
+    item_height: f64,
+    len: usize,
+    overscan: usize,
+    factory: ItemFactory,
+    items: HashMap<usize, Element>,
+}
+
+impl State {
+    fn visible_range(&self) -> Range<usize> {
+        if self.item_height <= 0.0 {
+            return 0..0;
+        }
+        let top = self.viewport.scroll_top() as f64;
+        let height = self.viewport.client_height() as f64;
+        let first = (top / self.item_height).floor() as usize;
+        let last = ((top + height) / self.item_height).ceil() as usize;
+        first.saturating_sub(self.overscan).min(self.len)..(last + self.overscan).min(self.len)
+    }
+
+    fn render(&mut self) {
+        let range = self.visible_range();
+        self.items.retain(|index, item| {
+            let keep = range.contains(index);
+            if !keep {
+                item.remove();
+            }
+            keep
+        });
+        for index in range {
+            if self.items.contains_key(&index) {
+                continue;
+            }
+            let item = (self.factory)(index);
+            if let Some(item) = item.dyn_ref::<HtmlElement>() {
+                let style = item.style();
+                style.set_property("position", "absolute").unwrap_throw();
+                style.set_property("left", "0").unwrap_throw();
+                style.set_property("right", "0").unwrap_throw();
+                style
+                    .set_property("height", &format!("{}px", self.item_height))
+                    .unwrap_throw();
+                style
+                    .set_property(
+                        "transform",
+                        &format!("translateY({}px)", index as f64 * self.item_height),
+                    )
+                    .unwrap_throw();
+            }
+            self.content.append_child(&item).unwrap_throw();
+            self.items.insert(index, item);
+        }
+    }
+
+    fn set_content_height(&self) {
+        self.content
+            .style()
+            .set_property(
+                "height",
+                &format!("{}px", self.len as f64 * self.item_height),
+            )
+            .unwrap_throw();
+    }
+}
+
+/// A list that renders only the items scrolled into view inside a scrollable viewport element.
+///
+/// The items are placed in a content element appended to the viewport, which is sized to the height of the whole
+/// list so that the viewport scrolls as though every item were rendered. Dropping the list stops it from following
+/// the viewport's scroll position, but leaves the rendered items in place.
+pub struct VirtualList {
+    state: Rc<RefCell<State>>,
+    listener: Closure<dyn FnMut(Event)>,
+}
+
+impl VirtualList {
+    /// Creates a list of `len` items, each `item_height` CSS pixels high, inside `viewport`, which is made scrollable.
+    /// `factory` is called with an item's index to create it each time it scrolls into view.
+    pub fn new(
+        viewport: &HtmlElement,
+        item_height: f64,
+        len: usize,
+        factory: impl FnMut(usize) -> Element + 'static,
+    ) -> Self {
+        viewport
+            .style()
+            .set_property("overflow-y", "auto")
+            .unwrap_throw();
+        let content: HtmlElement = viewport
+            .owner_document()
+            .unwrap_throw()
+            .create_element("div")
+            .unwrap_throw()
+            .unchecked_into();
+        content
+            .style()
+            .set_property("position", "relative")
+            .unwrap_throw();
+        viewport.append_child(&content).unwrap_throw();
+
+        let state = Rc::new(RefCell::new(State {
+            viewport: viewport.clone(),
+            content,
+            item_height,
+            len,
+            overscan: 3,
+            factory: Box::new(factory),
+            items: HashMap::new(),
+        }));
+        state.borrow().set_content_height();
+        state.borrow_mut().render();
+
+        let listener = Closure::wrap(Box::new({
+            let state = state.clone();
+            move |_: Event| state.borrow_mut().render()
+        }) as Box<dyn FnMut(Event)>);
+        viewport
+            .add_event_listener_with_callback("scroll", listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+
+        Self { state, listener }
+    }
+
+    /// The number of items in the list.
+    pub fn len(&self) -> usize {
+        self.state.borrow().len
+    }
+
+    /// Whether the list has no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Changes the number of items in the list. Items that are already rendered are kept.
+    pub fn set_len(&self, len: usize) {
+        let mut state = self.state.borrow_mut();
+        state.len = len;
+        state.set_content_height();
+        state.render();
+    }
+
+    /// Sets the number of items rendered beyond each edge of the viewport, which keeps them from visibly popping in
+    /// while scrolling quickly. Defaults to 3.
+    pub fn set_overscan(&self, overscan: usize) {
+        let mut state = self.state.borrow_mut();
+        state.overscan = overscan;
+        state.render();
+    }
+
+    /// The indices of the items currently rendered.
+    pub fn visible_range(&self) -> Range<usize> {
+        self.state.borrow().visible_range()
+    }
+
+    /// Removes every rendered item and creates them again, for example after the data they show has changed.
+    /// This also picks up changes to the viewport's height.
+    pub fn refresh(&self) {
+        let mut state = self.state.borrow_mut();
+        for (_, item) in state.items.drain() {
+            item.remove();
+        }
+        state.render();
+    }
+
+    /// Scrolls the viewport so that the item at `index` is at its top.
+    pub fn scroll_to(&self, index: usize) {
+        let state = self.state.borrow();
+        state
+            .viewport
+            .set_scroll_top((index as f64 * state.item_height) as i32);
+    }
+}
+
+impl Drop for VirtualList {
+    fn drop(&mut self) {
+        self.state
+            .borrow()
+            .viewport
+            .remove_event_listener_with_callback("scroll", self.listener.as_ref().unchecked_ref())
+            .unwrap_throw();
+    }
+}
@@ -0,0 +1,96 @@
+//! The visibility of the page, shared by all custom elements defined by this crate.
+//!
+//! Every element whose type returns `true` from [observe_visibility](crate::CustomElement::observe_visibility) has
+//! its [document_visibility_changed](crate::CustomElement::document_visibility_changed) invoked when the page is
+//! hidden or shown, for example when the user switches tabs, through a single `visibilitychange` listener shared by
+//! all elements in the same document.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{window, Document, Event, HtmlElement};
+
+use crate::document_of;
+use crate::subscribers::Subscribers;
+
+// The elements observing the visibility of one document, and its `visibilitychange` listener.
+struct Listened {
+    document: Document,
+    subscribers: Rc<Subscribers>,
+    listener: Closure<dyn FnMut(Event)>,
+}
+
+thread_local! {
+    static DOCUMENTS: RefCell<Vec<Listened>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Whether the page is currently visible.
+pub fn is_visible() -> bool {
+    !window().unwrap_throw().document().unwrap_throw().hidden()
+}
+
+// Registers a connected element and immediately delivers the visibility of its document to it.
+pub(crate) fn subscribe(this: &HtmlElement) {
+    let document = document_of(this);
+    let subscribers = DOCUMENTS.with(|documents| {
+        let mut documents = documents.borrow_mut();
+        let index = match documents
+            .iter()
+            .position(|listened| listened.document == document)
+        {
+            Some(index) => index,
+            None => {
+                documents.push(listen(&document));
+                documents.len() - 1
+            }
+        };
+        documents[index].subscribers.clone()
+    });
+    subscribers.subscribe(this);
+    subscribers.deliver(this, &[&JsValue::from_bool(!document.hidden())]);
+}
+
+// Unregisters a disconnected element, and removes the listener of each document in which no connected element
+// observes the visibility.
+pub(crate) fn unsubscribe(this: &HtmlElement) {
+    let unused = DOCUMENTS.with(|documents| {
+        let mut documents = documents.borrow_mut();
+        for listened in documents.iter() {
+            listened.subscribers.unsubscribe(this);
+        }
+        let (unused, used) = documents
+            .drain(..)
+            .partition(|listened| listened.subscribers.is_empty());
+        *documents = used;
+        unused
+    });
+    // dropped, with their closures, once the list is no longer borrowed
+    for listened in unused {
+        listened
+            .document
+            .remove_event_listener_with_callback(
+                "visibilitychange",
+                listened.listener.as_ref().unchecked_ref(),
+            )
+            .unwrap_throw();
+    }
+}
+
+// Sets up the `visibilitychange` listener of `document`, the first time any element in it subscribes.
+fn listen(document: &Document) -> Listened {
+    let subscribers = Rc::new(Subscribers::new("visibilityChangedCallback"));
+    let listener = Closure::wrap(Box::new({
+        let (document, subscribers) = (document.clone(), subscribers.clone());
+        move |_: Event| subscribers.notify(&[&JsValue::from_bool(!document.hidden())])
+    }) as Box<dyn FnMut(Event)>);
+    document
+        .add_event_listener_with_callback("visibilitychange", listener.as_ref().unchecked_ref())
+        .unwrap_throw();
+    Listened {
+        document: document.clone(),
+        subscribers,
+        listener,
+    }
+}
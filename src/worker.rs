@@ -0,0 +1,117 @@
+//! Offloads a component's heavy computation to a Web Worker. Requires the `worker` feature.
+//!
+//! The custom element itself always lives on the main thread. A [WorkerBridge] spawns a worker when it is created
+//! and terminates it when the element is disconnected or the bridge is dropped, and exchanges typed messages with it, serialized with
+//! [serde](https://serde.rs). Inside the worker, [respond_to] answers each message.
+//!
+//! ```rust,ignore
+//! // main thread
+//! fn connected_callback(&mut self, this: &HtmlElement) {
+//!     let output = self.output.clone();
+//!     self.parser = Some(WorkerBridge::spawn(this, "./parser_worker.js", move |ast: Ast| {
+//!         output.set_data(&ast.to_string());
+//!     }));
+//! }
+//!
+//! // worker
+//! #[wasm_bindgen]
+//! pub fn start_worker() {
+//!     respond_to(|source: String| parse(&source));
+//! }
+//! ```
+
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    DedicatedWorkerGlobalScope, HtmlElement, MessageEvent, Worker, WorkerOptions, WorkerType,
+};
+
+use crate::scope::Scope;
+
+/// A typed message channel to a Web Worker owned by a custom element.
+///
+/// `In` is the type of the messages sent to the worker, and `Out` the type of the messages it sends back.
+pub struct WorkerBridge<In, Out> {
+    worker: Worker,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _types: PhantomData<fn(In) -> Out>,
+}
+
+impl<In: Serialize, Out: DeserializeOwned + 'static> WorkerBridge<In, Out> {
+    /// Spawns a module worker from the script at `script_url`, calling `on_message` with each message it sends back.
+    ///
+    /// The worker is terminated when the bridge is dropped or the next time the element is disconnected,
+    /// so the bridge is usually created in [connected_callback](crate::CustomElement::connected_callback).
+    pub fn spawn(
+        this: &HtmlElement,
+        script_url: &str,
+        mut on_message: impl FnMut(Out) + 'static,
+    ) -> Self {
+        let options = WorkerOptions::new();
+        options.set_type(WorkerType::Module);
+        let worker = Worker::new_with_options(script_url, &options).unwrap_throw();
+
+        let listener = Closure::wrap(Box::new(move |event: MessageEvent| {
+            on_message(serde_wasm_bindgen::from_value(event.data()).unwrap_throw());
+        }) as Box<dyn FnMut(MessageEvent)>);
+        worker.set_onmessage(Some(listener.as_ref().unchecked_ref()));
+
+        Scope::new(this).on_cleanup({
+            let worker = worker.clone();
+            move || worker.terminate()
+        });
+
+        Self {
+            worker,
+            _on_message: listener,
+            _types: PhantomData,
+        }
+    }
+
+    /// Sends a message to the worker.
+    pub fn post(&self, message: &In) {
+        let message = serde_wasm_bindgen::to_value(message).unwrap_throw();
+        self.worker.post_message(&message).unwrap_throw();
+    }
+
+    /// Terminates the worker immediately, rather than waiting for the element to be disconnected.
+    pub fn terminate(&self) {
+        self.worker.terminate();
+    }
+
+    /// The underlying worker.
+    pub fn worker(&self) -> &Worker {
+        &self.worker
+    }
+}
+
+impl<In, Out> Drop for WorkerBridge<In, Out> {
+    // a worker no one can send messages to or hear from any more would otherwise keep running until the element is
+    // disconnected
+    fn drop(&mut self) {
+        self.worker.set_onmessage(None);
+        self.worker.terminate();
+    }
+}
+
+/// Called from inside a worker: handles each message sent by a [WorkerBridge] with `handler`,
+/// and sends its return value back to the main thread.
+pub fn respond_to<In: DeserializeOwned + 'static, Out: Serialize>(
+    mut handler: impl FnMut(In) -> Out + 'static,
+) {
+    let scope: DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let listener = Closure::wrap(Box::new({
+        let scope = scope.clone();
+        move |event: MessageEvent| {
+            let message = serde_wasm_bindgen::from_value(event.data()).unwrap_throw();
+            let response = serde_wasm_bindgen::to_value(&handler(message)).unwrap_throw();
+            scope.post_message(&response).unwrap_throw();
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
+    scope.set_onmessage(Some(listener.as_ref().unchecked_ref()));
+    listener.forget();
+}
@@ -0,0 +1,482 @@
+//! An adapter that defines a [Yew](https://yew.rs) component as a custom element. Requires the `yew` feature.
+//!
+//! [YewElement] mounts the component inside the element while it is connected, and keeps its properties in sync with the element's
+//! attributes: each field of the component's `Properties` is observed as an attribute with the same name, and
+//! whenever one of them changes, the attributes are parsed into a new set of properties, which Yew passes to the
+//! component's `changed`. The properties are parsed with [serde](https://serde.rs), so they must implement
+//! `Deserialize`; each attribute's value is parsed as the type of its field, and a boolean field is `true` when its
+//! attribute is present (unless its value is `"false"`), as with HTML's own boolean attributes.
+//!
+//! ```rust,ignore
+//! #[derive(Properties, PartialEq, Default, Deserialize)]
+//! #[serde(default, rename_all = "kebab-case")]
+//! pub struct CounterProps {
+//!     pub initial_value: i64,
+//!     pub label: String,
+//!     pub disabled: bool,
+//! }
+//!
+//! #[wasm_bindgen]
+//! pub fn define_elements() {
+//!     // <my-counter initial-value="3" label="Clicks" disabled></my-counter>
+//!     YewElement::<Counter>::define("my-counter");
+//! }
+//! ```
+//!
+//! With `#[serde(default)]`, attributes that are missing leave their fields at their default values; without it,
+//! the component is not mounted until every field's attribute has been set. `rename_all = "kebab-case"` gives the
+//! attributes conventional names, like `initial-value` rather than `initial_value`.
+//!
+//! The component and its descendants can reach the custom element through the [HostContext] context, for example to
+//! dispatch events on it or measure it:
+//!
+//! ```rust,ignore
+//! #[function_component]
+//! fn Counter(props: &CounterProps) -> Html {
+//!     let host = use_context::<HostContext>().unwrap();
+//!     let onclick = move |_| {
+//!         let event = CustomEvent::new("increment").unwrap();
+//!         host.element.dispatch_event(&event).unwrap();
+//!     };
+//!     html! { <button {onclick}>{ &props.label }</button> }
+//! }
+//! ```
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use ::yew::context::{ContextProvider, ContextProviderProps};
+use ::yew::html::{BaseComponent, Component, Context, Html, Properties};
+use ::yew::virtual_dom::{VChild, VComp};
+use ::yew::{AppHandle, Renderer};
+use serde::de::value::{Error, MapDeserializer};
+use serde::de::{
+    Deserialize, DeserializeOwned, Deserializer, Error as _, IntoDeserializer, Visitor,
+};
+use serde::forward_to_deserialize_any;
+use wasm_bindgen::prelude::*;
+use web_sys::{Element, HtmlElement, ShadowRoot};
+
+use crate::{host, logging, CustomElement};
+
+/// The custom element a [YewElement]'s component is mounted in, provided as a Yew context to the component and
+/// all of its descendants.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HostContext {
+    /// The custom element itself.
+    pub element: HtmlElement,
+    /// The element's shadow root, if it has one; the component is rendered inside it.
+    pub shadow_root: Option<ShadowRoot>,
+}
+
+/// A custom element that renders the Yew component `C`, with properties parsed from its attributes.
+///
+/// The component is mounted when the element is connected, and unmounted, dropping its whole tree, when the element
+/// is disconnected; it is mounted again, with fresh state, if the element is reconnected. With `KEEP_ALIVE` set to
+/// `true`, the element [coalesces moves](CustomElement::coalesce_moves), so that an element removed and reinserted
+/// in the same task, like one being reparented or sorted, keeps its component and its state:
+///
+/// ```rust,ignore
+/// YewElement::<KanbanCard, true>::define("kanban-card");
+/// ```
+pub struct YewElement<C: BaseComponent, const KEEP_ALIVE: bool = false> {
+    root: Option<Element>,
+    host: Option<HostContext>,
+    app: Option<AppHandle<HostProvider<C>>>,
+    connected: bool,
+    attributes: HashMap<String, String>,
+}
+
+impl<C: BaseComponent, const KEEP_ALIVE: bool> Default for YewElement<C, KEEP_ALIVE> {
+    fn default() -> Self {
+        Self {
+            root: None,
+            host: None,
+            app: None,
+            connected: false,
+            attributes: HashMap::new(),
+        }
+    }
+}
+
+impl<C, const KEEP_ALIVE: bool> YewElement<C, KEEP_ALIVE>
+where
+    C: BaseComponent,
+    C::Properties: DeserializeOwned,
+{
+    // Parses the current attributes into properties and passes them to the component, mounting it if the element
+    // is connected and the component is not mounted yet.
+    fn render(&mut self) {
+        if self.app.is_none() && !self.connected {
+            return;
+        }
+        let props = match C::Properties::deserialize(Attributes(&self.attributes)) {
+            Ok(props) => props,
+            Err(error) => {
+                logging::error(
+                    "invalid attributes for Yew component",
+                    &JsValue::from_str(&error.to_string()),
+                );
+                return;
+            }
+        };
+        let host = match &self.host {
+            Some(host) => host.clone(),
+            None => return,
+        };
+        let props = HostProps {
+            host,
+            props: Rc::new(props),
+        };
+        match (&mut self.app, &self.root) {
+            (Some(app), _) => app.update(props),
+            (None, Some(root)) => {
+                self.app = Some(
+                    Renderer::<HostProvider<C>>::with_root_and_props(root.clone(), props).render(),
+                );
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+impl<C, const KEEP_ALIVE: bool> CustomElement for YewElement<C, KEEP_ALIVE>
+where
+    C: BaseComponent,
+    C::Properties: DeserializeOwned,
+{
+    fn inject_children(&mut self, this: &HtmlElement) {
+        // Yew mounts into an element, which a shadow root is not, so the component gets a container of its own
+        let root = this
+            .owner_document()
+            .unwrap_throw()
+            .create_element("div")
+            .unwrap_throw();
+        this.append_child(&root).unwrap_throw();
+        self.root = Some(root);
+
+        let element = host(this);
+        self.host = Some(HostContext {
+            shadow_root: element.shadow_root(),
+            element,
+        });
+    }
+
+    fn connected_callback(&mut self, _this: &HtmlElement) {
+        self.connected = true;
+        self.render();
+    }
+
+    fn disconnected_callback(&mut self, _this: &HtmlElement) {
+        self.connected = false;
+        if let Some(app) = self.app.take() {
+            app.destroy();
+        }
+    }
+
+    fn coalesce_moves() -> bool {
+        KEEP_ALIVE
+    }
+
+    fn observed_attributes() -> &'static [&'static str] {
+        field_names::<C::Properties>()
+    }
+
+    fn attribute_changed_callback(
+        &mut self,
+        _this: &HtmlElement,
+        name: String,
+        _old_value: Option<String>,
+        new_value: Option<String>,
+    ) {
+        match new_value {
+            Some(value) => self.attributes.insert(name, value),
+            None => self.attributes.remove(&name),
+        };
+        self.render();
+    }
+}
+
+// The properties of `HostProvider<C>`: the properties of `C`, and the context to provide to it.
+struct HostProps<C: BaseComponent> {
+    host: HostContext,
+    props: Rc<C::Properties>,
+}
+
+impl<C: BaseComponent> PartialEq for HostProps<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.host == other.host && self.props == other.props
+    }
+}
+
+impl<C: BaseComponent> Properties for HostProps<C> {
+    // only ever constructed directly, never with `html!`
+    type Builder = ();
+
+    fn builder() -> Self::Builder {}
+}
+
+// The root of the app mounted by `YewElement<C>`, which renders `C` inside a `HostContext` provider.
+struct HostProvider<C>(PhantomData<C>);
+
+impl<C: BaseComponent> Component for HostProvider<C> {
+    type Message = ();
+    type Properties = HostProps<C>;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self(PhantomData)
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let HostProps { host, props } = ctx.props();
+        let children = VComp::new::<C>(props.clone(), None).into();
+        let provider_props = ContextProviderProps {
+            context: host.clone(),
+            children,
+        };
+        VChild::<ContextProvider<HostContext>>::new(provider_props, None).into()
+    }
+}
+
+// The names of the fields of the struct `T`, found by asking it to deserialize itself and noting the fields it
+// asks for. Empty if `T` is not deserialized as a struct with named fields.
+fn field_names<T: DeserializeOwned>() -> &'static [&'static str] {
+    let fields = Cell::new(&[][..]);
+    let _ = T::deserialize(FieldNames(&fields));
+    fields.get()
+}
+
+struct FieldNames<'a>(&'a Cell<&'static [&'static str]>);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(Error::custom("properties are not a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.0.set(fields);
+        Err(Error::custom("only collecting field names"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+// Deserializes a struct from a map of attribute names to values.
+struct Attributes<'a>(&'a HashMap<String, String>);
+
+impl<'de> Deserializer<'de> for Attributes<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_map(MapDeserializer::new(
+            self.0
+                .iter()
+                .map(|(name, value)| (name.as_str(), AttributeValue { name, value })),
+        ))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+// Deserializes a single attribute's value by parsing it as whatever type is asked for.
+struct AttributeValue<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+impl AttributeValue<'_> {
+    fn parse<T: std::str::FromStr>(&self) -> Result<T, Error>
+    where
+        T::Err: std::fmt::Display,
+    {
+        self.value.trim().parse().map_err(|error| {
+            Error::custom(format!(
+                "attribute `{}` has invalid value {:?}: {}",
+                self.name, self.value, error
+            ))
+        })
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for AttributeValue<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for AttributeValue<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_str(self.value)
+    }
+
+    // an attribute that is present is `true`, unless it is explicitly `"false"`
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_bool(self.value != "false")
+    }
+
+    deserialize_parsed! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // an enum of unit variants, named by the attribute's value
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(self.value))
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, Deserialize, PartialEq)]
+    #[serde(rename_all = "kebab-case")]
+    enum Size {
+        #[default]
+        Small,
+        ExtraLarge,
+    }
+
+    #[derive(Debug, Default, Deserialize, PartialEq)]
+    #[serde(default, rename_all = "kebab-case")]
+    struct Props {
+        initial_value: i64,
+        ratio: f32,
+        label: String,
+        disabled: bool,
+        size: Size,
+        max: Option<u8>,
+    }
+
+    fn props(attributes: &[(&str, &str)]) -> Result<Props, Error> {
+        let attributes = attributes
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Props::deserialize(Attributes(&attributes))
+    }
+
+    #[test]
+    fn parses_each_attribute_as_its_field_type() {
+        assert_eq!(
+            props(&[
+                ("initial-value", " -3 "),
+                ("ratio", "0.5"),
+                ("label", "Clicks"),
+                ("size", "extra-large"),
+                ("max", "10"),
+            ])
+            .unwrap(),
+            Props {
+                initial_value: -3,
+                ratio: 0.5,
+                label: "Clicks".to_string(),
+                size: Size::ExtraLarge,
+                max: Some(10),
+                ..Props::default()
+            }
+        );
+        assert_eq!(props(&[]).unwrap(), Props::default());
+    }
+
+    #[test]
+    fn booleans_are_true_unless_false() {
+        assert!(props(&[("disabled", "")]).unwrap().disabled);
+        assert!(props(&[("disabled", "disabled")]).unwrap().disabled);
+        assert!(!props(&[("disabled", "false")]).unwrap().disabled);
+        assert!(!props(&[]).unwrap().disabled);
+    }
+
+    #[test]
+    fn reports_invalid_values() {
+        assert_eq!(
+            props(&[("initial-value", "three")])
+                .unwrap_err()
+                .to_string(),
+            "attribute `initial-value` has invalid value \"three\": invalid digit found in string"
+        );
+        assert_eq!(
+            props(&[("max", "256")]).unwrap_err().to_string(),
+            "attribute `max` has invalid value \"256\": number too large to fit in target type"
+        );
+        assert!(props(&[("size", "medium")])
+            .unwrap_err()
+            .to_string()
+            .contains("unknown variant `medium`"));
+    }
+
+    #[test]
+    fn observes_each_field() {
+        assert_eq!(
+            field_names::<Props>(),
+            ["initial-value", "ratio", "label", "disabled", "size", "max"]
+        );
+        assert!(field_names::<String>().is_empty());
+    }
+}